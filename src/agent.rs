@@ -0,0 +1,230 @@
+//! AI agent command logging for zam
+//!
+//! Agents (Claude Code and similar tools) run shell commands on the user's
+//! behalf. `zam log --from-hook-json` reads the hook payload such a tool
+//! sends on stdin and records the command with its directory, exit code,
+//! output sizes and duration. Every such command carries an `actor` (the
+//! agent name) so it can be told apart from commands typed by the user.
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Actor recorded for Claude Code hook payloads
+pub const CLAUDE_CODE_ACTOR: &str = "claude-code";
+
+/// Actor recorded for other agents that do not name themselves
+pub const DEFAULT_AGENT_ACTOR: &str = "agent";
+
+/// A command run by an agent
+#[derive(Debug, Clone, PartialEq)]
+pub struct AgentRun {
+    pub command: String,
+    /// Directory the command ran in (`None` to use the current directory)
+    pub directory: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub exit_code: Option<i32>,
+    /// Agent name, e.g. `claude-code`
+    pub actor: String,
+    /// Tool that ran the command, e.g. `Bash`
+    pub tool_name: Option<String>,
+    /// The agent's own session ID, if the payload has one
+    pub agent_session_id: Option<String>,
+    pub stdout_bytes: Option<usize>,
+    pub stderr_bytes: Option<usize>,
+    pub duration_ms: Option<u64>,
+}
+
+impl AgentRun {
+    /// A command run by `actor` with no further metadata
+    pub fn new(command: &str, actor: &str) -> Self {
+        Self {
+            command: command.to_string(),
+            directory: None,
+            timestamp: Utc::now(),
+            exit_code: None,
+            actor: actor.to_string(),
+            tool_name: None,
+            agent_session_id: None,
+            stdout_bytes: None,
+            stderr_bytes: None,
+            duration_ms: None,
+        }
+    }
+
+    /// Parse a hook payload. Claude Code's `PostToolUse` shape
+    /// (`tool_input.command`, `tool_response.stdout`, ...) is read as well
+    /// as flat payloads with top-level `command`, `cwd` and `exit_code`.
+    pub fn from_hook_json(payload: &str) -> Result<Self> {
+        let json: Value = serde_json::from_str(payload)?;
+        let input = &json["tool_input"];
+        let response = &json["tool_response"];
+
+        let command = first_str(&[&input["command"], &json["command"]]).ok_or_else(|| {
+            Error::invalid_arguments("Hook payload has no command (expected tool_input.command)")
+        })?;
+
+        let actor = first_str(&[&json["actor"], &json["agent"]]).unwrap_or_else(|| {
+            if json.get("hook_event_name").is_some() {
+                CLAUDE_CODE_ACTOR.to_string()
+            } else {
+                DEFAULT_AGENT_ACTOR.to_string()
+            }
+        });
+
+        let exit_code = [
+            &response["exit_code"],
+            &response["exitCode"],
+            &response["returncode"],
+            &json["exit_code"],
+        ]
+        .iter()
+        .find_map(|v| v.as_i64())
+        .map(|code| code as i32);
+
+        let output_bytes = |name: &str| {
+            let counted = format!("{name}_bytes");
+            [&response[&counted], &json[&counted]]
+                .iter()
+                .find_map(|v| v.as_u64().map(|n| n as usize))
+                .or_else(|| {
+                    [&response[name], &json[name]]
+                        .iter()
+                        .find_map(|v| v.as_str().map(str::len))
+                })
+        };
+
+        Ok(Self {
+            directory: first_str(&[&json["cwd"], &input["cwd"]]),
+            exit_code,
+            actor,
+            tool_name: first_str(&[&json["tool_name"]]),
+            agent_session_id: first_str(&[&json["session_id"]]),
+            stdout_bytes: output_bytes("stdout"),
+            stderr_bytes: output_bytes("stderr"),
+            duration_ms: [&json["duration_ms"], &response["duration_ms"]]
+                .iter()
+                .find_map(|v| v.as_u64()),
+            ..Self::new(&command, "")
+        })
+    }
+}
+
+/// First non-empty string among `values`
+fn first_str(values: &[&Value]) -> Option<String> {
+    values
+        .iter()
+        .filter_map(|v| v.as_str())
+        .find(|s| !s.is_empty())
+        .map(String::from)
+}
+
+/// Which commands to show by who ran them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ActorFilter {
+    #[default]
+    All,
+    /// Commands typed by the user
+    Human,
+    /// Commands run by any agent
+    Agents,
+    /// Commands run by one agent
+    Named(String),
+}
+
+impl ActorFilter {
+    /// Parse `human`, `agent`/`agents`, `all` or an agent name
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "all" => ActorFilter::All,
+            "human" => ActorFilter::Human,
+            "agent" | "agents" => ActorFilter::Agents,
+            name => ActorFilter::Named(name.to_string()),
+        }
+    }
+
+    /// Next filter when cycling All → Human → Agents → All
+    pub fn cycle(&self) -> Self {
+        match self {
+            ActorFilter::All => ActorFilter::Human,
+            ActorFilter::Human => ActorFilter::Agents,
+            ActorFilter::Agents | ActorFilter::Named(_) => ActorFilter::All,
+        }
+    }
+
+    /// Short label for status lines
+    pub fn label(&self) -> &str {
+        match self {
+            ActorFilter::All => "all",
+            ActorFilter::Human => "human",
+            ActorFilter::Agents => "agents",
+            ActorFilter::Named(name) => name,
+        }
+    }
+
+    /// SQL condition on the `actor` column. A `Named` filter binds its
+    /// name to `?n`, returned as the parameter value.
+    pub fn sql_condition(&self, n: usize) -> (String, Option<String>) {
+        match self {
+            ActorFilter::All => ("1".to_string(), None),
+            ActorFilter::Human => ("actor IS NULL".to_string(), None),
+            ActorFilter::Agents => ("actor IS NOT NULL".to_string(), None),
+            ActorFilter::Named(name) => (format!("actor = ?{n}"), Some(name.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_claude_code_payload() {
+        let payload = r#"{
+            "session_id": "abc123",
+            "cwd": "/srv/app",
+            "hook_event_name": "PostToolUse",
+            "tool_name": "Bash",
+            "tool_input": {"command": "cargo test", "description": "Run tests"},
+            "tool_response": {"stdout": "ok\n", "stderr": "", "exit_code": 101}
+        }"#;
+        let run = AgentRun::from_hook_json(payload).unwrap();
+        assert_eq!(run.command, "cargo test");
+        assert_eq!(run.directory.as_deref(), Some("/srv/app"));
+        assert_eq!(run.exit_code, Some(101));
+        assert_eq!(run.actor, CLAUDE_CODE_ACTOR);
+        assert_eq!(run.tool_name.as_deref(), Some("Bash"));
+        assert_eq!(run.agent_session_id.as_deref(), Some("abc123"));
+        assert_eq!(run.stdout_bytes, Some(3));
+        assert_eq!(run.stderr_bytes, Some(0));
+    }
+
+    #[test]
+    fn test_flat_payload() {
+        let payload = r#"{"command": "make", "agent": "aider", "exit_code": 0,
+                          "stdout_bytes": 2048, "duration_ms": 1500}"#;
+        let run = AgentRun::from_hook_json(payload).unwrap();
+        assert_eq!(run.actor, "aider");
+        assert_eq!(run.directory, None);
+        assert_eq!(run.stdout_bytes, Some(2048));
+        assert_eq!(run.duration_ms, Some(1500));
+
+        assert!(AgentRun::from_hook_json(r#"{"tool_input": {}}"#).is_err());
+        assert!(AgentRun::from_hook_json("cargo test").is_err());
+    }
+
+    #[test]
+    fn test_actor_filter() {
+        assert_eq!(ActorFilter::parse("human"), ActorFilter::Human);
+        assert_eq!(ActorFilter::parse("agents"), ActorFilter::Agents);
+        assert_eq!(
+            ActorFilter::parse("claude-code"),
+            ActorFilter::Named("claude-code".to_string())
+        );
+        assert_eq!(ActorFilter::All.cycle().cycle().cycle(), ActorFilter::All);
+        assert_eq!(
+            ActorFilter::Named("x".into()).sql_condition(2),
+            ("actor = ?2".to_string(), Some("x".to_string()))
+        );
+    }
+}
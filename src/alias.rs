@@ -0,0 +1,424 @@
+//! Alias checks and suggestions for zam
+//!
+//! Before an alias is saved its name is checked against shell builtins and
+//! keywords, executables on `$PATH` and the aliases zam already manages,
+//! so a new alias does not silently hide a command. Suggestions propose
+//! aliases for long commands that are run often.
+
+use crate::database::Alias;
+use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Builtins and reserved words of bash, zsh and fish
+pub const SHELL_BUILTINS: &[&str] = &[
+    "!",
+    ".",
+    ":",
+    "[",
+    "[[",
+    "]]",
+    "{",
+    "}",
+    "alias",
+    "and",
+    "autoload",
+    "begin",
+    "bg",
+    "bind",
+    "bindkey",
+    "break",
+    "builtin",
+    "caller",
+    "case",
+    "cd",
+    "command",
+    "compgen",
+    "complete",
+    "continue",
+    "declare",
+    "dirs",
+    "disown",
+    "do",
+    "done",
+    "echo",
+    "elif",
+    "else",
+    "emulate",
+    "enable",
+    "end",
+    "esac",
+    "eval",
+    "exec",
+    "exit",
+    "export",
+    "false",
+    "fc",
+    "fg",
+    "fi",
+    "for",
+    "function",
+    "functions",
+    "getopts",
+    "hash",
+    "help",
+    "history",
+    "if",
+    "in",
+    "jobs",
+    "kill",
+    "let",
+    "local",
+    "logout",
+    "not",
+    "or",
+    "popd",
+    "printf",
+    "pushd",
+    "pwd",
+    "read",
+    "readonly",
+    "rehash",
+    "return",
+    "select",
+    "set",
+    "setopt",
+    "shift",
+    "shopt",
+    "source",
+    "suspend",
+    "test",
+    "then",
+    "time",
+    "times",
+    "trap",
+    "true",
+    "type",
+    "typeset",
+    "ulimit",
+    "umask",
+    "unalias",
+    "unset",
+    "unsetopt",
+    "until",
+    "wait",
+    "whence",
+    "where",
+    "which",
+    "while",
+];
+
+/// Commands shorter than this are not worth an alias
+pub const MIN_SUGGESTION_LENGTH: usize = 12;
+
+/// Commands run fewer times than this get no suggestion
+pub const MIN_SUGGESTION_RUNS: usize = 3;
+
+/// Redaction placeholders such as `<password:1>` or `<redacted>`
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<[a-z_]+(:\d+)?>").expect("valid placeholder pattern"));
+
+/// Why an alias name should not be used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AliasConflict {
+    /// Not a name every shell accepts
+    InvalidName,
+    /// A shell builtin or reserved word
+    Builtin,
+    /// An executable found on `$PATH`
+    Executable(PathBuf),
+    /// An alias zam already manages, with its command
+    Alias(String),
+}
+
+impl AliasConflict {
+    /// Whether saving would shadow a command rather than break something.
+    /// Shadowing an executable (`ls` for `ls --color`) is often intended.
+    pub fn can_override(&self) -> bool {
+        matches!(self, AliasConflict::Executable(_))
+    }
+}
+
+impl fmt::Display for AliasConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AliasConflict::InvalidName => {
+                write!(f, "is not a valid name: use letters, digits and _ . + -")
+            }
+            AliasConflict::Builtin => write!(f, "is a shell builtin"),
+            AliasConflict::Executable(path) => write!(f, "would shadow {}", path.display()),
+            AliasConflict::Alias(command) => write!(f, "is already an alias for '{}'", command),
+        }
+    }
+}
+
+/// A proposed alias for a frequently run command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AliasSuggestion {
+    pub name: String,
+    pub command: String,
+    pub runs: usize,
+    /// Characters not typed had the alias been used for every run
+    pub saved: usize,
+}
+
+/// Whether `name` is accepted as an alias by bash, zsh and fish
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.+-".contains(c))
+}
+
+/// The first problem with using `name` as a new alias, if any
+pub fn find_conflict(name: &str, existing: &[Alias]) -> Option<AliasConflict> {
+    if !is_valid_name(name) {
+        return Some(AliasConflict::InvalidName);
+    }
+    if let Some(alias) = existing.iter().find(|a| a.alias == name) {
+        return Some(AliasConflict::Alias(alias.command.clone()));
+    }
+    if SHELL_BUILTINS.contains(&name) {
+        return Some(AliasConflict::Builtin);
+    }
+    find_executable(name).map(AliasConflict::Executable)
+}
+
+/// Path of the executable `name` resolves to on `$PATH`
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(name))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// Aliases for the commands of `frequent` (command, runs) that would save
+/// the most typing, best first. Commands that already have an alias, that
+/// contain redaction placeholders or span lines are skipped. Names are the
+/// initials of the command's words, numbered when taken.
+pub fn suggest_aliases(
+    frequent: &[(String, usize)],
+    existing: &[Alias],
+    limit: usize,
+) -> Vec<AliasSuggestion> {
+    let mut suggestions: Vec<AliasSuggestion> = frequent
+        .iter()
+        .filter(|(command, runs)| {
+            *runs >= MIN_SUGGESTION_RUNS
+                && command.chars().count() >= MIN_SUGGESTION_LENGTH
+                && !command.contains('\n')
+                && !contains_placeholder(command)
+                && !existing.iter().any(|a| a.command == *command)
+        })
+        .map(|(command, runs)| AliasSuggestion {
+            name: String::new(),
+            command: command.clone(),
+            runs: *runs,
+            saved: 0,
+        })
+        .collect();
+
+    let mut taken: Vec<String> = Vec::new();
+    for suggestion in &mut suggestions {
+        let name = suggest_name(&suggestion.command, existing, &taken);
+        suggestion.saved = suggestion.runs
+            * suggestion
+                .command
+                .chars()
+                .count()
+                .saturating_sub(name.len());
+        suggestion.name = name.clone();
+        taken.push(name);
+    }
+
+    suggestions.sort_by(|a, b| {
+        b.saved
+            .cmp(&a.saved)
+            .then_with(|| a.command.cmp(&b.command))
+    });
+    suggestions.truncate(limit);
+    suggestions
+}
+
+/// A free name for an alias of `command`: the initials of its words,
+/// numbered if they conflict with anything or are in `taken`
+pub fn suggest_name(command: &str, existing: &[Alias], taken: &[String]) -> String {
+    let base = initials(command);
+    std::iter::once(base.clone())
+        .chain((2..).map(|n| format!("{}{}", base, n)))
+        .find(|name| !taken.contains(name) && find_conflict(name, existing).is_none())
+        .expect("numbered names are unbounded")
+}
+
+/// Whether `command` still holds redaction placeholders. Such a command
+/// cannot be aliased: the alias would run the placeholder.
+pub fn contains_placeholder(command: &str) -> bool {
+    PLACEHOLDER.is_match(command)
+}
+
+/// The alias `command` invokes: the one named by its first word
+pub fn invoked_alias<'a>(command: &str, aliases: &'a [Alias]) -> Option<&'a Alias> {
+    let first = command.split_whitespace().next()?;
+    aliases.iter().find(|a| a.alias == first)
+}
+
+/// An alias `command` could have used: the one with the longest expansion
+/// that `command` spells out in full, optionally followed by arguments.
+/// Commands that already start with an alias get no hint.
+pub fn alias_for_expansion<'a>(command: &str, aliases: &'a [Alias]) -> Option<&'a Alias> {
+    let command = command.trim();
+    if invoked_alias(command, aliases).is_some() {
+        return None;
+    }
+    aliases
+        .iter()
+        .filter(|a| {
+            let expansion = a.command.trim();
+            !expansion.is_empty()
+                && command
+                    .strip_prefix(expansion)
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with(char::is_whitespace))
+        })
+        .max_by_key(|a| a.command.trim().len())
+}
+
+/// Lowercase first letters of the words of `command` that are not options,
+/// at most four (`docker compose up -d` gives `dcu`)
+fn initials(command: &str) -> String {
+    let initials: String = command
+        .split_whitespace()
+        .filter(|word| !word.starts_with('-'))
+        .filter_map(|word| word.chars().find(|c| c.is_ascii_alphanumeric()))
+        .map(|c| c.to_ascii_lowercase())
+        .take(4)
+        .collect();
+    if initials.is_empty() {
+        "a".to_string()
+    } else {
+        initials
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn alias(name: &str, command: &str) -> Alias {
+        Alias {
+            alias: name.to_string(),
+            command: command.to_string(),
+            description: String::new(),
+            date_created: Utc::now(),
+            date_updated: Utc::now(),
+            use_count: 0,
+            last_used: None,
+        }
+    }
+
+    #[test]
+    fn test_find_conflict() {
+        let existing = [alias("gs", "git status")];
+        assert_eq!(
+            find_conflict("", &existing),
+            Some(AliasConflict::InvalidName)
+        );
+        assert_eq!(
+            find_conflict("-x", &existing),
+            Some(AliasConflict::InvalidName)
+        );
+        assert_eq!(
+            find_conflict("a b", &existing),
+            Some(AliasConflict::InvalidName)
+        );
+        assert_eq!(
+            find_conflict("gs", &existing),
+            Some(AliasConflict::Alias("git status".to_string()))
+        );
+        assert_eq!(find_conflict("cd", &existing), Some(AliasConflict::Builtin));
+        assert!(matches!(
+            find_conflict("sh", &existing),
+            Some(AliasConflict::Executable(_))
+        ));
+        assert!(find_conflict("sh", &existing).unwrap().can_override());
+        assert_eq!(find_conflict("zq-unlikely-name", &existing), None);
+    }
+
+    #[test]
+    fn test_suggest_aliases() {
+        let frequent = vec![
+            ("docker compose up -d".to_string(), 10),
+            ("git status".to_string(), 50),
+            ("kubectl get pods".to_string(), 2),
+            ("mysql -p<password:1> prod".to_string(), 9),
+            ("git log --oneline --graph".to_string(), 4),
+            ("grep -rn TODO src".to_string(), 3),
+            ("terraform plan -out tf.plan".to_string(), 5),
+        ];
+        let existing = [alias("gl", "git log --oneline --graph")];
+        let suggestions = suggest_aliases(&frequent, &existing, 10);
+
+        let names: Vec<(&str, &str)> = suggestions
+            .iter()
+            .map(|s| (s.name.as_str(), s.command.as_str()))
+            .collect();
+        // Too short, too rare, redacted and already aliased are left out
+        assert_eq!(
+            names,
+            vec![
+                ("dcu", "docker compose up -d"),
+                ("tpt", "terraform plan -out tf.plan"),
+                ("gts", "grep -rn TODO src"),
+            ]
+        );
+        assert_eq!(suggestions[0].saved, 10 * (20 - 3));
+        assert_eq!(suggest_aliases(&frequent, &existing, 1).len(), 1);
+    }
+
+    #[test]
+    fn test_taken_names_are_numbered() {
+        let frequent = vec![
+            ("git checkout main".to_string(), 8),
+            ("zam sessions --tree".to_string(), 5),
+        ];
+        let suggestions = suggest_aliases(&frequent, &[alias("gcm", "git commit -m")], 10);
+        assert_eq!(suggestions[0].name, "gcm2");
+        assert_eq!(suggestions[1].name, "zs");
+    }
+
+    #[test]
+    fn test_alias_matching() {
+        let aliases = [
+            alias("g", "git"),
+            alias("gs", "git status"),
+            alias("gss", "git status --short"),
+        ];
+        assert_eq!(invoked_alias("gs -b", &aliases).unwrap().alias, "gs");
+        assert!(invoked_alias("git status", &aliases).is_none());
+        assert!(invoked_alias("", &aliases).is_none());
+
+        let hint = |command| alias_for_expansion(command, &aliases).map(|a| a.alias.as_str());
+        assert_eq!(hint("git status"), Some("gs"));
+        assert_eq!(hint("git status --short ."), Some("gss"));
+        assert_eq!(hint("git statuses"), Some("g"));
+        assert_eq!(hint("gs"), None);
+        assert_eq!(hint("ls"), None);
+    }
+}
@@ -0,0 +1,289 @@
+//! Redaction audit reports for zam
+//!
+//! Summarises what redaction has captured in the database (by token type,
+//! pattern, host and month), flags commands that look sensitive but were
+//! stored in clear text, and lists secrets that were reused across commands.
+
+use crate::database::Database;
+use crate::error::Result;
+use crate::redaction::value_fingerprint;
+use crate::structured::SensitiveKeys;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Keywords that suggest a command handles a secret
+pub const RISKY_KEYWORDS: &[&str] = &[
+    "password",
+    "passwd",
+    "passphrase",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "private_key",
+    "credential",
+    "credentials",
+];
+
+/// Minimum length of a word considered for the entropy check
+const MIN_ENTROPY_WORD_LENGTH: usize = 20;
+
+/// Full audit report
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub total_commands: usize,
+    pub redacted_commands: usize,
+    pub total_tokens: usize,
+    pub by_token_type: BTreeMap<String, usize>,
+    pub by_pattern: BTreeMap<String, usize>,
+    pub by_host: BTreeMap<String, usize>,
+    pub by_month: BTreeMap<String, usize>,
+    pub risky_commands: Vec<RiskyCommand>,
+    pub reused_tokens: Vec<ReusedTokenReport>,
+}
+
+/// A command stored without redaction that looks like it may hold a secret
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskyCommand {
+    pub command_id: i64,
+    pub timestamp: DateTime<Utc>,
+    pub command: String,
+    pub reasons: Vec<String>,
+}
+
+/// A secret captured in more than one command. The value itself is never
+/// reported, only its fingerprint and a short preview.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReusedTokenReport {
+    pub token_type: String,
+    pub fingerprint: String,
+    pub preview: String,
+    pub command_ids: Vec<i64>,
+}
+
+impl AuditReport {
+    /// Build an audit report from the database, listing at most
+    /// `risky_limit` risky commands (most recent first)
+    pub fn build(db: &Database, risky_limit: usize) -> Result<Self> {
+        let mut report = Self {
+            total_commands: 0,
+            redacted_commands: 0,
+            total_tokens: 0,
+            by_token_type: BTreeMap::new(),
+            by_pattern: BTreeMap::new(),
+            by_host: BTreeMap::new(),
+            by_month: BTreeMap::new(),
+            risky_commands: Vec::new(),
+            reused_tokens: Vec::new(),
+        };
+
+        for row in db.get_token_audit_rows()? {
+            report.total_tokens += row.count;
+            *report.by_token_type.entry(row.token_type).or_insert(0) += row.count;
+            *report
+                .by_pattern
+                .entry(row.pattern.unwrap_or_else(|| "unknown".to_string()))
+                .or_insert(0) += row.count;
+            *report.by_host.entry(row.hostname).or_insert(0) += row.count;
+            *report.by_month.entry(row.month).or_insert(0) += row.count;
+        }
+
+        let allowlist: HashSet<String> = db.get_allowlist()?.into_iter().collect();
+        let keywords = SensitiveKeys::new(RISKY_KEYWORDS);
+        let commands = db.get_all_commands()?;
+        report.total_commands = commands.len();
+        for cmd in commands.iter().rev() {
+            if cmd.redacted {
+                report.redacted_commands += 1;
+                continue;
+            }
+            if report.risky_commands.len() >= risky_limit {
+                continue;
+            }
+            let reasons = risk_reasons(&cmd.command, &keywords, &allowlist);
+            if !reasons.is_empty() {
+                report.risky_commands.push(RiskyCommand {
+                    command_id: cmd.id.as_i64(),
+                    timestamp: cmd.timestamp,
+                    command: cmd.command.clone(),
+                    reasons,
+                });
+            }
+        }
+
+        report.reused_tokens = db
+            .get_reused_tokens()?
+            .into_iter()
+            .map(|t| ReusedTokenReport {
+                fingerprint: value_fingerprint(&t.original_value),
+                preview: mask(&t.original_value),
+                token_type: t.token_type,
+                command_ids: t.command_ids,
+            })
+            .collect();
+
+        Ok(report)
+    }
+}
+
+/// Explain why an unredacted command looks risky (empty if it does not)
+pub fn risk_reasons(
+    command: &str,
+    keywords: &SensitiveKeys,
+    allowlist: &HashSet<String>,
+) -> Vec<String> {
+    let mut reasons = Vec::new();
+
+    let mut seen = HashSet::new();
+    for word in command
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '-'))
+        .map(|w| w.trim_start_matches('-'))
+    {
+        if !word.is_empty() && keywords.is_sensitive(word) && seen.insert(word.to_lowercase()) {
+            reasons.push(format!("sensitive keyword '{}'", word));
+        }
+    }
+
+    for word in command.split(|c: char| c.is_whitespace() || "=:'\",;()".contains(c)) {
+        if !looks_random(word) || allowlist.contains(&value_fingerprint(word)) {
+            continue;
+        }
+        reasons.push(format!(
+            "high-entropy string '{}' ({:.1} bits/char)",
+            mask(word),
+            shannon_entropy(word)
+        ));
+    }
+
+    reasons
+}
+
+/// Whether a word looks like a random credential rather than a path, flag
+/// or identifier. Hex digests and UUIDs stay below the threshold.
+fn looks_random(word: &str) -> bool {
+    if word.len() < MIN_ENTROPY_WORD_LENGTH
+        || word.starts_with(['/', '~', '.', '-'])
+        || word.contains("://")
+        || !word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "+/=_-".contains(c))
+        || !word.chars().any(|c| c.is_ascii_digit())
+        || !word.chars().any(|c| c.is_ascii_alphabetic())
+    {
+        return false;
+    }
+
+    let max_entropy = (word.len().min(64) as f64).log2();
+    shannon_entropy(word) >= 0.85 * max_entropy
+}
+
+/// Shannon entropy of a string in bits per character
+pub fn shannon_entropy(s: &str) -> f64 {
+    let mut counts: HashMap<char, usize> = HashMap::new();
+    for c in s.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let len = s.chars().count() as f64;
+    counts
+        .values()
+        .map(|&n| {
+            let p = n as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Short preview that never reveals a whole secret
+fn mask(value: &str) -> String {
+    let prefix: String = value.chars().take(4).collect();
+    format!("{}…", prefix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::NamedTempFile;
+
+    fn reasons(command: &str) -> Vec<String> {
+        risk_reasons(
+            command,
+            &SensitiveKeys::new(RISKY_KEYWORDS),
+            &HashSet::new(),
+        )
+    }
+
+    #[test]
+    fn test_shannon_entropy() {
+        assert_eq!(shannon_entropy("aaaa"), 0.0);
+        assert!((shannon_entropy("abcd") - 2.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_keyword_reasons() {
+        let found = reasons("vault read -field=db_password secret/app");
+        assert_eq!(
+            found,
+            vec![
+                "sensitive keyword 'db_password'".to_string(),
+                "sensitive keyword 'secret'".to_string(),
+            ]
+        );
+        assert!(reasons("ls -la ~/projects").is_empty());
+    }
+
+    #[test]
+    fn test_entropy_reasons() {
+        let found = reasons("export X=Zk3pQ9vR7tL2mW8xB4nC6yH1");
+        assert_eq!(found.len(), 1);
+        assert!(found[0].starts_with("high-entropy string 'Zk3p…'"));
+
+        // Hex digests, UUIDs and paths are not flagged
+        assert!(reasons("git show 3f786850e387550fdab836ed7e6dc881de23001b").is_empty());
+        assert!(reasons("docker rm 123e4567-e89b-12d3-a456-426614174000").is_empty());
+        assert!(reasons("cat /var/lib/app/data2024/export_2024_01_01.csv").is_empty());
+    }
+
+    #[test]
+    fn test_allowlisted_values_not_flagged() {
+        let value = "Zk3pQ9vR7tL2mW8xB4nC6yH1";
+        let allowlist: HashSet<String> = [value_fingerprint(value)].into_iter().collect();
+        let keys = SensitiveKeys::new(RISKY_KEYWORDS);
+        assert!(risk_reasons(&format!("echo {}", value), &keys, &allowlist).is_empty());
+    }
+
+    #[test]
+    fn test_build_report() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        let first = db
+            .add_command("psql <password:1>", "/", Utc::now(), true, None)
+            .unwrap();
+        let second = db
+            .add_command("mysql -p <password:1>", "/", Utc::now(), true, None)
+            .unwrap();
+        db.store_token(first, "password", "<password:1>", "hunter22", "flag-p")
+            .unwrap();
+        db.store_token(second, "password", "<password:1>", "hunter22", "flag-p")
+            .unwrap();
+        db.add_command("echo $API_TOKEN", "/", Utc::now(), false, None)
+            .unwrap();
+
+        let report = AuditReport::build(&db, 10).unwrap();
+        assert_eq!(report.total_commands, 3);
+        assert_eq!(report.redacted_commands, 2);
+        assert_eq!(report.total_tokens, 2);
+        assert_eq!(report.by_token_type["password"], 2);
+        assert_eq!(report.by_pattern["flag-p"], 2);
+        assert_eq!(report.by_host.values().sum::<usize>(), 2);
+        assert_eq!(report.by_month.len(), 1);
+        assert_eq!(report.risky_commands.len(), 1);
+        assert_eq!(report.reused_tokens.len(), 1);
+        assert_eq!(report.reused_tokens[0].command_ids, vec![first, second]);
+        assert_eq!(report.reused_tokens[0].preview, "hunt…");
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(!json.contains("hunter22"));
+    }
+}
@@ -2,10 +2,16 @@
 //!
 //! This module defines the HistoryProvider trait that both file-based
 //! and database backends implement, allowing handlers to work with
-//! either backend without pattern matching.
+//! either backend without pattern matching. The TUI reads through the
+//! narrower HistoryQuery trait, which both backends implement too.
 
-use crate::error::Result;
+use crate::agent::ActorFilter;
+use crate::database::{
+    Alias, CommandEntry, CommandFilter, DatabaseStats, Host, Session, Token, UsageStats,
+};
+use crate::error::{Error, Result};
 use crate::history::HistoryEntry;
+use crate::types::{CommandId, HostId};
 
 /// Common interface for history storage backends
 ///
@@ -46,3 +52,145 @@ pub trait HistoryProvider {
     /// Indices should be in the order returned by get_entries().
     fn delete_entries(&mut self, indices: &[usize]) -> Result<usize>;
 }
+
+/// Queries behind the interactive TUI
+///
+/// `Database` implements every method. Backends without sessions, aliases,
+/// hosts, tokens or preferences, such as the file-based `HistoryManager`,
+/// only implement the command queries: the defaults report no rows and
+/// refuse changes, so those tabs show up empty. The TUI tests drive it
+/// through an in-memory implementation the same way.
+pub trait HistoryQuery {
+    /// Count unique commands containing `filter`
+    fn count_unique_commands_filtered(
+        &self,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<usize>;
+
+    /// Unique commands containing `filter`, most recent first, paginated
+    fn get_unique_commands_filtered(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<Vec<CommandEntry>>;
+
+    /// Unique commands run in `directory`, most recent first
+    fn get_commands_for_directory(&self, directory: &str) -> Result<Vec<CommandEntry>>;
+
+    /// The `limit` most run commands with their run counts
+    fn get_frequent_commands(&self, limit: usize) -> Result<Vec<(String, usize)>>;
+
+    /// How many times `command` was run
+    fn count_command_runs(&self, command: &str) -> Result<usize>;
+
+    /// Delete one command from history
+    fn delete_command(&self, id: CommandId) -> Result<()>;
+
+    /// Totals shown on the help tab
+    fn get_stats(&self) -> Result<DatabaseStats>;
+
+    /// Usage charts of the commands matching `filter`
+    fn usage_stats(&self, _filter: &CommandFilter, _top: usize) -> Result<UsageStats> {
+        Ok(UsageStats::default())
+    }
+
+    /// Count sessions matching `filter`
+    fn count_sessions_filtered(&self, _filter: Option<&str>) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Sessions matching `filter`, paginated
+    fn get_sessions_filtered(
+        &self,
+        _offset: usize,
+        _limit: usize,
+        _filter: Option<&str>,
+    ) -> Result<Vec<Session>> {
+        Ok(Vec::new())
+    }
+
+    /// Number of commands in each of `session_ids`
+    fn count_commands_for_sessions(&self, session_ids: &[&str]) -> Result<Vec<usize>> {
+        Ok(vec![0; session_ids.len()])
+    }
+
+    fn get_session(&self, _session_id: &str) -> Result<Option<Session>> {
+        Ok(None)
+    }
+
+    /// Commands of a session, newest first
+    fn get_commands_for_session(&self, _session_id: &str) -> Result<Vec<CommandEntry>> {
+        Ok(Vec::new())
+    }
+
+    /// Commands of a session and its descendants, newest first
+    fn get_commands_for_session_tree(&self, _session_id: &str) -> Result<Vec<CommandEntry>> {
+        Ok(Vec::new())
+    }
+
+    fn delete_session(&self, _id: &str) -> Result<()> {
+        Err(unsupported("Sessions"))
+    }
+
+    fn list_aliases(&self) -> Result<Vec<Alias>> {
+        Ok(Vec::new())
+    }
+
+    fn add_alias(&self, _alias: &str, _command: &str, _description: &str) -> Result<()> {
+        Err(unsupported("Aliases"))
+    }
+
+    fn update_alias(&self, _alias: &str, _command: &str, _description: Option<&str>) -> Result<()> {
+        Err(unsupported("Aliases"))
+    }
+
+    fn remove_alias(&self, _alias: &str) -> Result<()> {
+        Err(unsupported("Aliases"))
+    }
+
+    fn get_hosts(&self) -> Result<Vec<Host>> {
+        Ok(Vec::new())
+    }
+
+    fn delete_host(&self, _id: HostId) -> Result<()> {
+        Err(unsupported("Hosts"))
+    }
+
+    fn get_all_tokens(&self) -> Result<Vec<Token>> {
+        Ok(Vec::new())
+    }
+
+    /// Tokens redacted from one command
+    fn get_tokens_for_command(&self, _command_id: CommandId) -> Result<Vec<Token>> {
+        Ok(Vec::new())
+    }
+
+    /// Mark a token as not a secret and restore it in its command
+    fn allow_token(&self, _id: i64) -> Result<Token> {
+        Err(unsupported("Tokens"))
+    }
+
+    fn delete_token(&self, _id: i64) -> Result<()> {
+        Err(unsupported("Tokens"))
+    }
+
+    fn get_preference(&self, _key: &str) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn get_bool_preference(&self, key: &str) -> Result<bool> {
+        Ok(self.get_preference(key)?.as_deref() == Some("true"))
+    }
+
+    /// Remember a TUI setting; dropped by backends without preferences
+    fn set_preference(&self, _key: &str, _value: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn unsupported(what: &str) -> Error {
+    Error::custom(format!("{} require the database backend", what))
+}
@@ -1,13 +1,23 @@
 //! Command-line argument structures for zam
 
+use crate::config::TuiHeight;
 use clap::Args;
 use std::path::PathBuf;
 
 #[derive(Args)]
 pub struct LogArgs {
     /// Command to log
-    #[arg(value_name = "COMMAND")]
-    pub command: String,
+    #[arg(value_name = "COMMAND", required_unless_present = "from_hook_json")]
+    pub command: Option<String>,
+
+    /// Read an agent hook payload (e.g. Claude Code's PostToolUse JSON)
+    /// from stdin and log the command it describes
+    #[arg(long, conflicts_with = "command")]
+    pub from_hook_json: bool,
+
+    /// Record the command as run by this agent (e.g. claude-code)
+    #[arg(long, value_name = "NAME")]
+    pub actor: Option<String>,
 
     /// Timestamp in Unix format (optional)
     #[arg(short = 'T', long)]
@@ -28,6 +38,11 @@ pub struct LogArgs {
     /// Use a static session ID (e.g. for non-interactive tools like Claude Code)
     #[arg(short = 'S', long)]
     pub session_id: Option<String>,
+
+    /// Record the session as a child of this session (e.g. an agent
+    /// sub-task started from an interactive shell)
+    #[arg(long, value_name = "SESSION_ID")]
+    pub parent_session: Option<String>,
 }
 
 #[derive(Args)]
@@ -37,6 +52,32 @@ pub struct EndSessionArgs {
     pub session_id: String,
 }
 
+#[derive(Args)]
+pub struct PrivateArgs {
+    /// Turn private mode on or off (shows the current state if omitted)
+    #[arg(value_enum)]
+    pub state: Option<PrivateState>,
+
+    /// Keep commands in an ephemeral buffer for this session's Ctrl-R
+    /// instead of dropping them
+    #[arg(short = 'e', long)]
+    pub ephemeral: bool,
+
+    /// Session ID to change (defaults to $ZAM_SESSION_ID)
+    #[arg(short = 'S', long)]
+    pub session_id: Option<String>,
+
+    /// Output export statements for the ZAM_PRIVATE prompt indicator
+    #[arg(long)]
+    pub export: bool,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum PrivateState {
+    On,
+    Off,
+}
+
 #[derive(Args)]
 pub struct AuthArgs {
     /// 1Password item name to load secrets from
@@ -109,6 +150,10 @@ pub struct SearchArgs {
     /// Search before specific date (format: YYYY-MM-DD)
     #[arg(long)]
     pub before: Option<String>,
+
+    /// Only commands run by `human`, any `agent`, or the named agent
+    #[arg(long, value_name = "ACTOR")]
+    pub actor: Option<String>,
 }
 
 #[derive(Args)]
@@ -254,6 +299,11 @@ pub struct ShellArgs {
     /// Include custom key bindings
     #[arg(long)]
     pub custom_bindings: bool,
+
+    /// Bind Ctrl-R to the TUI in bash and fish instead of fzf, which moves
+    /// to Alt-R (zsh always uses the TUI)
+    #[arg(long)]
+    pub tui: bool,
 }
 
 #[derive(Args)]
@@ -289,10 +339,14 @@ pub struct FrequentArgs {
 #[derive(Args)]
 pub struct ValidateArgs {
     /// Redaction pattern to validate
-    #[arg(value_name = "PATTERN")]
-    pub pattern: String,
+    #[arg(value_name = "PATTERN", required_unless_present = "rule")]
+    pub pattern: Option<String>,
+
+    /// Name of a rule-pack rule to validate (runs its examples)
+    #[arg(short = 'r', long, conflicts_with = "pattern")]
+    pub rule: Option<String>,
 
-    /// Test string to validate against the pattern
+    /// Test string to validate against the pattern or rule
     #[arg(short = 't', long)]
     pub test: Option<String>,
 }
@@ -364,9 +418,167 @@ pub struct SessionsArgs {
     #[arg(short = 'C', long)]
     pub show_commands: Option<String>,
 
+    /// With --show-commands, include commands of descendant sessions
+    #[arg(long, requires = "show_commands")]
+    pub descendants: bool,
+
     /// Show detailed information
     #[arg(short = 'D', long)]
     pub detailed: bool,
+
+    /// Close sessions whose shell process is gone
+    #[arg(long)]
+    pub reap: bool,
+
+    /// Session to name, tag or annotate (defaults to $ZAM_SESSION_ID)
+    #[arg(short = 'S', long)]
+    pub session_id: Option<String>,
+
+    /// Give the session a name (empty string clears it)
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Add a tag to the session (repeatable)
+    #[arg(long = "tag", value_name = "TAG")]
+    pub tags: Vec<String>,
+
+    /// Remove a tag from the session (repeatable)
+    #[arg(long = "untag", value_name = "TAG")]
+    pub untags: Vec<String>,
+
+    /// Attach a note to the session (empty string clears it)
+    #[arg(long)]
+    pub note: Option<String>,
+
+    /// List sessions (across all hosts unless --host-id is given)
+    #[arg(short = 'L', long)]
+    pub list: bool,
+
+    /// List sessions as a tree of parents and children
+    #[arg(short = 'T', long)]
+    pub tree: bool,
+
+    /// Only list sessions carrying this tag
+    #[arg(long, value_name = "TAG")]
+    pub with_tag: Option<String>,
+
+    /// Only list sessions whose name or ID matches this glob
+    #[arg(long, value_name = "GLOB")]
+    pub named: Option<String>,
+
+    /// Only list sessions started on or after this date (format: YYYY-MM-DD)
+    #[arg(long)]
+    pub since: Option<String>,
+
+    /// Only list sessions started on or before this date (format: YYYY-MM-DD)
+    #[arg(long)]
+    pub before: Option<String>,
+
+    /// Export a session transcript
+    #[arg(short = 'E', long, value_name = "SESSION_ID")]
+    pub export: Option<String>,
+
+    /// Transcript format for --export
+    #[arg(
+        short = 'f',
+        long,
+        value_enum,
+        default_value = "md",
+        requires = "export"
+    )]
+    pub format: TranscriptFormat,
+
+    /// Restore redacted values in the exported transcript
+    #[arg(long, requires = "export")]
+    pub reveal: bool,
+
+    /// Write the transcript to a file (stdout if not specified)
+    #[arg(short = 'O', long, requires = "export")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct TuiArgs {
+    /// Print `run` or `insert` on the first line, then the chosen command
+    /// (for shell key bindings)
+    #[arg(long)]
+    pub widget: bool,
+
+    /// Draw inline below the prompt instead of full screen: a line count,
+    /// a percentage such as 40%, or full (overrides tui.height)
+    #[arg(long, value_name = "HEIGHT")]
+    pub height: Option<TuiHeight>,
+}
+
+#[derive(Args)]
+pub struct ReplayArgs {
+    /// Session ID to replay
+    #[arg(value_name = "SESSION_ID")]
+    pub session_id: String,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy)]
+pub enum TranscriptFormat {
+    /// Shell script with cd steps, failed commands commented out
+    Sh,
+    /// Markdown runbook
+    Md,
+    /// JSON
+    Json,
+}
+
+#[derive(Args)]
+pub struct AuditArgs {
+    /// Output format
+    #[arg(short = 'f', long, value_enum, default_value = "table")]
+    pub format: AuditFormat,
+
+    /// Maximum number of risky commands to list
+    #[arg(short = 'n', long, default_value = "50")]
+    pub limit: usize,
+}
+
+#[derive(clap::ValueEnum, Clone)]
+pub enum AuditFormat {
+    Table,
+    Json,
+}
+
+#[derive(Args)]
+pub struct RedactArgs {
+    #[command(subcommand)]
+    pub command: RedactCommands,
+}
+
+#[derive(clap::Subcommand)]
+pub enum RedactCommands {
+    /// Mark a command's redacted tokens as not-a-secret and restore the original text
+    Allow(RedactAllowArgs),
+}
+
+#[derive(Args)]
+pub struct RedactAllowArgs {
+    /// Command ID whose tokens should be allowed
+    pub command_id: i64,
+
+    /// Only allow the token with this placeholder (e.g. "<api_key:1>")
+    #[arg(short = 't', long)]
+    pub token: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ForgetArgs {
+    /// Literal secret to purge ("-" reads it from stdin)
+    #[arg(long, conflicts_with = "pattern", required_unless_present = "pattern")]
+    pub value: Option<String>,
+
+    /// Regex matching the secret to purge
+    #[arg(long)]
+    pub pattern: Option<String>,
+
+    /// Show which rows would be affected without changing anything
+    #[arg(long)]
+    pub dry_run: bool,
 }
 
 #[derive(Args)]
@@ -387,6 +599,8 @@ pub enum AliasCommands {
     List(AliasListArgs),
     /// Export aliases as a shell script
     Export(AliasExportArgs),
+    /// Show how often each alias is used
+    Stats(AliasStatsArgs),
     /// Sync aliases from shell (reads alias output from stdin)
     Sync,
 }
@@ -425,6 +639,13 @@ pub struct AliasListArgs {
     pub shell: bool,
 }
 
+#[derive(Args)]
+pub struct AliasStatsArgs {
+    /// Only list aliases that were never used
+    #[arg(long)]
+    pub unused: bool,
+}
+
 #[derive(Args)]
 pub struct AliasExportArgs {
     /// Output file (stdout if not specified)
@@ -1,5 +1,6 @@
 //! Alias management handlers for zam CLI
 
+use crate::alias::{AliasConflict, find_conflict};
 use crate::cli::args::*;
 use crate::cli::{CliApp, HistoryBackend};
 use crate::error::{Error, Result};
@@ -17,6 +18,24 @@ pub fn handle_alias(app: &mut CliApp, args: &AliasArgs) -> Result<()> {
 
     match &args.command {
         AliasCommands::Add(add_args) => {
+            match find_conflict(&add_args.name, &db.list_aliases()?) {
+                Some(conflict) if conflict.can_override() => {
+                    eprintln!("Warning: alias '{}' {}", add_args.name, conflict);
+                }
+                Some(conflict @ AliasConflict::Alias(_)) => {
+                    return Err(Error::invalid_arguments(format!(
+                        "Alias '{}' {}; change it with 'zam alias update'",
+                        add_args.name, conflict
+                    )));
+                }
+                Some(conflict) => {
+                    return Err(Error::invalid_arguments(format!(
+                        "Alias '{}' {}",
+                        add_args.name, conflict
+                    )));
+                }
+                None => {}
+            }
             db.add_alias(&add_args.name, &add_args.command, &add_args.description)?;
             if !app.quiet {
                 println!("Alias '{}' added successfully", add_args.name);
@@ -108,6 +127,75 @@ pub fn handle_alias(app: &mut CliApp, args: &AliasArgs) -> Result<()> {
                 print!("{}", output);
             }
         }
+        AliasCommands::Stats(stats_args) => {
+            let mut aliases = db.list_aliases()?;
+            if stats_args.unused {
+                aliases.retain(|a| a.use_count == 0);
+            }
+            // Most used first, ties broken by the most recent use
+            aliases.sort_by(|a, b| {
+                b.use_count
+                    .cmp(&a.use_count)
+                    .then_with(|| b.last_used.cmp(&a.last_used))
+                    .then_with(|| a.alias.cmp(&b.alias))
+            });
+
+            if aliases.is_empty() {
+                println!(
+                    "{}",
+                    if stats_args.unused {
+                        "No unused aliases."
+                    } else {
+                        "No aliases found."
+                    }
+                );
+            } else {
+                let name_width = aliases
+                    .iter()
+                    .map(|a| a.alias.len())
+                    .max()
+                    .unwrap_or(5)
+                    .max(5);
+
+                println!(
+                    "{:<name_width$}  {:>6}  {:<16}  COMMAND",
+                    "ALIAS", "USES", "LAST USED"
+                );
+                println!(
+                    "{:<name_width$}  {:>6}  {:<16}  {}",
+                    "-".repeat(name_width),
+                    "-".repeat(6),
+                    "-".repeat(16),
+                    "-".repeat(20)
+                );
+
+                for a in &aliases {
+                    let last_used = a
+                        .last_used
+                        .map(|t| {
+                            t.with_timezone(&chrono::Local)
+                                .format("%Y-%m-%d %H:%M")
+                                .to_string()
+                        })
+                        .unwrap_or_else(|| "never".to_string());
+                    let cmd_display = if a.command.chars().count() > 60 {
+                        format!("{}...", a.command.chars().take(57).collect::<String>())
+                    } else {
+                        a.command.clone()
+                    };
+                    println!(
+                        "{:<name_width$}  {:>6}  {:<16}  {}",
+                        a.alias, a.use_count, last_used, cmd_display
+                    );
+                }
+
+                let unused = aliases.iter().filter(|a| a.use_count == 0).count();
+                if unused > 0 && !stats_args.unused && !app.quiet {
+                    println!();
+                    println!("{} of {} aliases never used", unused, aliases.len());
+                }
+            }
+        }
         AliasCommands::Sync => {
             let aliases = parse_alias_lines_from_stdin()?;
             let count = db.sync_aliases(&aliases)?;
@@ -1,13 +1,59 @@
 //! Basic command handlers for zam CLI
 
+use super::util::parse_date_arg;
+use crate::agent::{ActorFilter, AgentRun};
 use crate::cli::args::*;
 use crate::cli::{CliApp, HistoryBackend};
+use crate::database::SessionFilter;
 use crate::error::{Error, Result};
-use crate::search::SearchQuery;
+use crate::history::HistoryEntry;
+use crate::ignore::IgnorePolicy;
+use crate::private::{EphemeralBuffer, PrivateMode};
+use crate::rules::{self, RuleSet};
+use crate::search::{SearchQuery, split_session_qualifier};
+use std::collections::HashSet;
+use std::io::{self, Read};
 
 pub fn handle_log(app: &mut CliApp, args: &LogArgs) -> Result<()> {
+    // Agent commands carry an actor and whatever their hook reported
+    let mut agent_run = if args.from_hook_json {
+        let mut payload = String::new();
+        io::stdin().read_to_string(&mut payload)?;
+        Some(AgentRun::from_hook_json(&payload)?)
+    } else {
+        args.actor
+            .as_ref()
+            .zip(args.command.as_ref())
+            .map(|(actor, command)| AgentRun::new(command, actor))
+    };
+    if let Some(run) = agent_run.as_mut() {
+        if let Some(ref actor) = args.actor {
+            run.actor = actor.clone();
+        }
+        if args.exit_code.is_some() {
+            run.exit_code = args.exit_code;
+        }
+        if let Some(ref directory) = args.directory {
+            run.directory = Some(directory.clone());
+        }
+    }
+
+    let command = match (&agent_run, &args.command) {
+        (Some(run), _) => run.command.clone(),
+        (None, Some(command)) => command.clone(),
+        (None, None) => return Err(Error::invalid_arguments("No command to log")),
+    };
+    // Agent commands without --session-id are grouped by the agent's session
+    let session_id = args.session_id.clone().or_else(|| {
+        agent_run.as_ref().and_then(|run| {
+            run.agent_session_id
+                .as_ref()
+                .map(|id| format!("{}-{}", run.actor, id))
+        })
+    });
+
     if !app.quiet {
-        app.verbose_println(&format!("Logging command: {}", args.command));
+        app.verbose_println(&format!("Logging command: {}", command));
     }
 
     // Handle timestamp
@@ -20,9 +66,38 @@ pub fn handle_log(app: &mut CliApp, args: &LogArgs) -> Result<()> {
     } else {
         None
     };
+    if let (Some(run), Some(timestamp)) = (agent_run.as_mut(), timestamp) {
+        run.timestamp = timestamp;
+    }
+
+    // Private sessions drop commands or keep them, redacted, in the
+    // ephemeral buffer. Without a runtime directory they are dropped.
+    if let (Some(session_id), HistoryBackend::Database(mgr)) = (&session_id, &app.backend)
+        && let Some(mode) = mgr.db.get_session_private(session_id)?
+    {
+        if mode == PrivateMode::Ephemeral
+            && let Some(buffer) = EphemeralBuffer::for_session(session_id)
+        {
+            let directory = std::env::current_dir()
+                .map(|d| d.to_string_lossy().to_string())
+                .unwrap_or_else(|_| "<unknown>".to_string());
+            if IgnorePolicy::from_config(&app.config)?
+                .check(&command, &directory)
+                .is_none()
+            {
+                buffer.push(
+                    &mgr.redact_command(&command)?,
+                    &directory,
+                    timestamp.unwrap_or_else(chrono::Utc::now),
+                )?;
+            }
+        }
+        app.verbose_println("Private session: command not recorded");
+        return Ok(());
+    }
 
     // Set static session ID if provided (database backend only)
-    if let Some(ref session_id) = args.session_id {
+    if let Some(ref session_id) = session_id {
         match &mut app.backend {
             HistoryBackend::Database(mgr) => {
                 mgr.set_session_id(session_id)?;
@@ -35,18 +110,41 @@ pub fn handle_log(app: &mut CliApp, args: &LogArgs) -> Result<()> {
         }
     }
 
+    if let Some(ref parent) = args.parent_session {
+        match &mut app.backend {
+            HistoryBackend::Database(mgr) => {
+                let session_id = mgr.db.ensure_session()?;
+                mgr.db.set_session_parent(&session_id, parent)?;
+            }
+            HistoryBackend::File(_) => {
+                return Err(Error::custom(
+                    "--parent-session requires database backend (use --use-db)",
+                ));
+            }
+        }
+    }
+
     // Log the command
-    if timestamp.is_none() && args.exit_code.is_none() {
+    if let Some(ref run) = agent_run {
+        match &mut app.backend {
+            HistoryBackend::Database(mgr) => mgr.log_agent_run(run)?,
+            HistoryBackend::File(_) => {
+                return Err(Error::custom(
+                    "Agent commands require database backend. Remove --use-file flag to use the default database backend.",
+                ));
+            }
+        }
+    } else if timestamp.is_none() && args.exit_code.is_none() {
         // Use trait method for simple case
-        app.provider_mut().log_command(&args.command)?;
+        app.provider_mut().log_command(&command)?;
     } else {
         // Use backend-specific methods for timestamp/exit_code support
         match &mut app.backend {
             HistoryBackend::File(mgr) => {
-                mgr.log_command_with_timestamp(&args.command, timestamp)?;
+                mgr.log_command_with_timestamp(&command, timestamp)?;
             }
             HistoryBackend::Database(mgr) => {
-                mgr.log_command_with_timestamp(&args.command, timestamp, args.exit_code)?;
+                mgr.log_command_with_timestamp(&command, timestamp, args.exit_code)?;
             }
         }
     }
@@ -55,22 +153,72 @@ pub fn handle_log(app: &mut CliApp, args: &LogArgs) -> Result<()> {
         app.verbose_println("Command logged successfully");
     }
 
+    // Shown on the terminal by the shell hook that ran `zam log`
+    if app.config.shell_integration.alias_hints
+        && agent_run.is_none()
+        && let HistoryBackend::Database(mgr) = &app.backend
+        && let Some(alias) = mgr.alias_hint(&command)?
+    {
+        eprintln!("tip: alias `{}` = {}", alias.alias, alias.command);
+    }
+
     Ok(())
 }
 
 pub fn handle_search(app: &mut CliApp, args: &SearchArgs) -> Result<()> {
+    let (term, session_glob) = split_session_qualifier(&args.term);
+    let actor = args.actor.as_deref().map(ActorFilter::parse);
+
     // Get entries based on backend
     let entries = match &app.backend {
+        HistoryBackend::File(_) if session_glob.is_some() => {
+            return Err(Error::custom(
+                "session: filters require database backend. Remove --use-file flag to use the default database backend.",
+            ));
+        }
+        HistoryBackend::File(_) if actor.is_some() => {
+            return Err(Error::custom(
+                "--actor requires database backend. Remove --use-file flag to use the default database backend.",
+            ));
+        }
         HistoryBackend::File(mgr) => mgr.get_entries()?,
+        HistoryBackend::Database(mgr) if session_glob.is_some() => {
+            let filter = SessionFilter {
+                name: session_glob,
+                ..Default::default()
+            };
+            let actor_ids: Option<HashSet<_>> = match actor {
+                Some(ref actor) => Some(
+                    mgr.db
+                        .get_commands_by_actor(actor)?
+                        .into_iter()
+                        .map(|c| c.id)
+                        .collect(),
+                ),
+                None => None,
+            };
+            let mut entries: Vec<HistoryEntry> = Vec::new();
+            for session in mgr.db.find_sessions(&filter)? {
+                entries.extend(
+                    mgr.get_commands_for_session(session.id.as_str())?
+                        .into_iter()
+                        .filter(|c| actor_ids.as_ref().is_none_or(|ids| ids.contains(&c.id)))
+                        .map(HistoryEntry::from),
+                );
+            }
+            entries
+        }
+        HistoryBackend::Database(mgr) if actor.is_some() => mgr
+            .db
+            .get_commands_by_actor(actor.as_ref().unwrap())?
+            .into_iter()
+            .map(HistoryEntry::from)
+            .collect(),
         HistoryBackend::Database(mgr) => {
             // For database, use direct search if no complex filters
             if args.since.is_none() && args.before.is_none() && !args.regex && !args.exact {
-                let db_results = mgr.search(
-                    &args.term,
-                    args.directory.as_deref(),
-                    None,
-                    Some(args.limit),
-                )?;
+                let db_results =
+                    mgr.search(&term, args.directory.as_deref(), None, Some(args.limit))?;
 
                 // Display results
                 for result in &db_results {
@@ -106,7 +254,7 @@ pub fn handle_search(app: &mut CliApp, args: &SearchArgs) -> Result<()> {
     };
 
     // Build search query
-    let mut query = SearchQuery::new(args.term.clone());
+    let mut query = SearchQuery::new(term);
 
     if let Some(dir) = &args.directory {
         query = query.with_directory(dir.clone());
@@ -132,22 +280,10 @@ pub fn handle_search(app: &mut CliApp, args: &SearchArgs) -> Result<()> {
 
     // Parse time filters
     if let Some(since_str) = &args.since {
-        let since = chrono::NaiveDate::parse_from_str(since_str, "%Y-%m-%d")
-            .map_err(|_| Error::InvalidTimestamp {
-                timestamp: since_str.clone(),
-            })?
-            .and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_utc();
+        let since = parse_date_arg(since_str, false)?;
 
         let end = if let Some(before_str) = &args.before {
-            chrono::NaiveDate::parse_from_str(before_str, "%Y-%m-%d")
-                .map_err(|_| Error::InvalidTimestamp {
-                    timestamp: before_str.clone(),
-                })?
-                .and_hms_opt(23, 59, 59)
-                .unwrap()
-                .and_utc()
+            parse_date_arg(before_str, true)?
         } else {
             chrono::Utc::now()
         };
@@ -214,6 +350,19 @@ pub fn handle_recent(app: &mut CliApp, args: &RecentArgs) -> Result<()> {
 pub fn handle_fzf(app: &mut CliApp, args: &FzfArgs) -> Result<()> {
     let mut entries = app.provider().get_entries()?;
 
+    // Commands kept in memory by an ephemeral private session
+    if let Some(buffer) = std::env::var("ZAM_SESSION_ID")
+        .ok()
+        .and_then(|id| EphemeralBuffer::for_session(&id))
+    {
+        entries.extend(
+            buffer
+                .entries()?
+                .into_iter()
+                .map(|e| HistoryEntry::new(e.command, e.timestamp, e.directory)),
+        );
+    }
+
     // Filter by directory if specified
     if let Some(dir) = &args.directory {
         entries.retain(|entry| entry.directory.contains(dir));
@@ -268,13 +417,18 @@ pub fn handle_frequent(app: &mut CliApp, args: &FrequentArgs) -> Result<()> {
     Ok(())
 }
 
-pub fn handle_validate(_app: &mut CliApp, args: &ValidateArgs) -> Result<()> {
+pub fn handle_validate(app: &mut CliApp, args: &ValidateArgs) -> Result<()> {
     use regex::Regex;
 
+    if let Some(rule) = &args.rule {
+        return handle_validate_rule(app, rule, args.test.as_deref());
+    }
+    let pattern = args.pattern.as_deref().unwrap_or_default();
+
     // Try to compile the pattern
-    match Regex::new(&args.pattern) {
+    match Regex::new(pattern) {
         Ok(re) => {
-            println!("✓ Pattern is valid: {}", args.pattern);
+            println!("✓ Pattern is valid: {}", pattern);
 
             if let Some(test_str) = &args.test {
                 if re.is_match(test_str) {
@@ -293,10 +447,7 @@ pub fn handle_validate(_app: &mut CliApp, args: &ValidateArgs) -> Result<()> {
                 }
             } else {
                 println!("\nTo test this pattern against a string, use:");
-                println!(
-                    "  zam validate '{}' --test 'your test string'",
-                    args.pattern
-                );
+                println!("  zam validate '{}' --test 'your test string'", pattern);
             }
         }
         Err(e) => {
@@ -307,3 +458,81 @@ pub fn handle_validate(_app: &mut CliApp, args: &ValidateArgs) -> Result<()> {
 
     Ok(())
 }
+
+fn handle_validate_rule(app: &CliApp, name: &str, test: Option<&str>) -> Result<()> {
+    let rules_dir = match &app.config.redaction.rules_dir {
+        Some(dir) => Some(dir.clone()),
+        None => rules::default_rules_dir().ok(),
+    };
+    let rule_set = RuleSet::load(rules_dir.as_deref())?;
+    // `zam log` skips these quietly, so this is where they get noticed
+    for pack in rule_set.skipped() {
+        println!("✗ Skipped rule pack {}: {}", pack.source, pack.error);
+    }
+    if !rule_set.skipped().is_empty() {
+        println!();
+    }
+
+    let Some(rule) = rule_set.get(name) else {
+        let available: Vec<&str> = rule_set.rules().iter().map(|r| r.name.as_str()).collect();
+        return Err(Error::invalid_arguments(format!(
+            "Unknown rule '{}'. Available rules: {}",
+            name,
+            available.join(", ")
+        )));
+    };
+
+    println!("Rule: {} ({})", rule.name, rule.source);
+    if !rule.description.is_empty() {
+        println!("  {}", rule.description);
+    }
+    println!("  Programs:   {}", rule.programs.join(", "));
+    println!("  Token type: {}", rule.token_type);
+    println!("  Pattern:    {}", rule.pattern);
+
+    if let Some(test_str) = test {
+        let matches = rule.find_matches(test_str);
+        if matches.is_empty() {
+            println!("\n✗ Rule does not match test string");
+        } else {
+            let mut redacted = test_str.to_string();
+            for m in matches.iter().rev() {
+                redacted.replace_range(m.start..m.end, &app.config.redaction.placeholder);
+            }
+            println!("\n✓ Rule matches test string");
+            for m in &matches {
+                println!("  Secret: {}", m.value);
+            }
+            println!("  Redacted: {}", redacted);
+        }
+        return Ok(());
+    }
+
+    println!();
+    let results = rule.run_examples();
+    let failed = results.iter().filter(|r| !r.passed()).count();
+    for result in &results {
+        let mark = if result.passed() { "✓" } else { "✗" };
+        match &result.expected {
+            Some(secret) => println!("{} {}  (expects: {})", mark, result.input, secret),
+            None => println!("{} {}  (expects no match)", mark, result.input),
+        }
+        if !result.passed() {
+            println!(
+                "    got: {}",
+                result.actual.as_deref().unwrap_or("no match")
+            );
+        }
+    }
+
+    if failed > 0 {
+        return Err(Error::custom(format!(
+            "Rule '{}' failed {} of {} examples",
+            rule.name,
+            failed,
+            results.len()
+        )));
+    }
+    println!("\nAll {} examples passed", results.len());
+    Ok(())
+}
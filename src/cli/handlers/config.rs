@@ -116,13 +116,15 @@ pub fn handle_status(app: &mut CliApp) -> Result<()> {
                 println!("   Remove --use-file flag to use the default database backend\n");
             }
         }
-        HistoryBackend::Database(_) => {
+        HistoryBackend::Database(mgr) => {
             println!("Backend: SQLite Database");
             println!(
                 "Storage: {}",
                 app.config.history_file.with_extension("db").display()
             );
-            println!("Type: Multi-host, session-aware\n");
+            println!("Type: Multi-host, session-aware");
+            let (open, closed) = mgr.db.count_sessions_by_state()?;
+            println!("Sessions: {} open, {} closed\n", open, closed);
 
             if app.config.history_file.exists() {
                 println!(
@@ -156,6 +158,10 @@ pub fn handle_status(app: &mut CliApp) -> Result<()> {
         "  Log duplicates: {}",
         app.config.shell_integration.log_duplicates
     );
+    println!(
+        "  Alias hints: {}",
+        app.config.shell_integration.alias_hints
+    );
 
     if !app.config.shell_integration.exclude_commands.is_empty() {
         println!(
@@ -1,8 +1,14 @@
 //! Database-specific handlers for zam CLI
 
+use super::util::parse_date_arg;
 use crate::cli::args::*;
 use crate::cli::{CliApp, HistoryBackend};
+use crate::database::{Session, SessionFilter};
 use crate::error::{Error, Result};
+use crate::private::{EphemeralBuffer, PrivateMode};
+use crate::session::tree_order;
+use crate::transcript::Transcript;
+use crate::types::HostId;
 
 pub fn handle_merge(app: &mut CliApp, args: &MergeArgs) -> Result<()> {
     let mgr = match &mut app.backend {
@@ -124,7 +130,7 @@ pub fn handle_hosts(app: &mut CliApp, args: &HostsArgs) -> Result<()> {
 }
 
 pub fn handle_sessions(app: &mut CliApp, args: &SessionsArgs) -> Result<()> {
-    let mgr = match &app.backend {
+    let mgr = match &mut app.backend {
         HistoryBackend::Database(mgr) => mgr,
         HistoryBackend::File(_) => {
             return Err(Error::custom(
@@ -133,57 +139,218 @@ pub fn handle_sessions(app: &mut CliApp, args: &SessionsArgs) -> Result<()> {
         }
     };
 
-    if let Some(ref session_id) = args.show_commands {
-        let commands = mgr.get_commands_for_session(session_id)?;
+    let edits = args.name.is_some()
+        || args.note.is_some()
+        || !args.tags.is_empty()
+        || !args.untags.is_empty();
+    let listing = args.list
+        || args.tree
+        || args.host_id.is_some()
+        || args.with_tag.is_some()
+        || args.named.is_some()
+        || args.since.is_some()
+        || args.before.is_some();
+
+    if args.reap {
+        let reaped = mgr.db.reap_stale_sessions()?;
+        if !app.quiet {
+            for session_id in &reaped {
+                println!("Closed stale session {}", session_id);
+            }
+            println!("{} stale sessions closed", reaped.len());
+        }
+    } else if let Some(ref session_id) = args.export {
+        let session = mgr
+            .db
+            .get_session(session_id)?
+            .ok_or_else(|| Error::custom(format!("Session {} not found", session_id)))?;
+        let mut transcript = Transcript::new(session, mgr.get_commands_for_session(session_id)?);
+        if args.reveal {
+            transcript.reveal(&mgr.db.get_tokens_by_session(session_id)?);
+        }
+
+        let output = match args.format {
+            TranscriptFormat::Sh => transcript.to_shell_script(),
+            TranscriptFormat::Md => transcript.to_markdown(),
+            TranscriptFormat::Json => transcript.to_json()? + "\n",
+        };
+        if let Some(ref path) = args.output {
+            std::fs::write(path, output)?;
+            if !app.quiet {
+                println!(
+                    "Exported {} commands to {}",
+                    transcript.commands.len(),
+                    path.display()
+                );
+            }
+        } else {
+            print!("{}", output);
+        }
+    } else if edits {
+        let session_id = args
+            .session_id
+            .clone()
+            .or_else(|| std::env::var("ZAM_SESSION_ID").ok())
+            .ok_or_else(|| Error::custom("--session-id required (or set ZAM_SESSION_ID)"))?;
+
+        if !mgr.db.session_exists(&session_id)? {
+            // The shell's own session is created lazily by its first command
+            if std::env::var("ZAM_SESSION_ID").ok().as_deref() != Some(session_id.as_str()) {
+                return Err(Error::custom(format!("Session {} not found", session_id)));
+            }
+            mgr.db.resume_session(&session_id)?;
+        }
+
+        if let Some(ref name) = args.name {
+            mgr.db.set_session_name(&session_id, Some(name))?;
+        }
+        if let Some(ref note) = args.note {
+            mgr.db.set_session_note(&session_id, Some(note))?;
+        }
+        mgr.db.add_session_tags(&session_id, &args.tags)?;
+        mgr.db.remove_session_tags(&session_id, &args.untags)?;
+
+        if !app.quiet
+            && let Some(session) = mgr.db.get_session(&session_id)?
+        {
+            print_session(&session);
+        }
+    } else if let Some(ref session_id) = args.show_commands {
+        let commands = if args.descendants {
+            mgr.db.get_commands_for_session_tree(session_id)?
+        } else {
+            mgr.get_commands_for_session(session_id)?
+        };
         if commands.is_empty() {
             println!("No commands found for session {}", session_id);
         } else {
             println!("=== Commands in session {} ===\n", session_id);
             for cmd in &commands {
+                let origin = if args.descendants {
+                    format!("[{}] ", cmd.session_id)
+                } else {
+                    String::new()
+                };
                 println!(
-                    "{} {} {}",
+                    "{} {}{} {}",
                     cmd.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                    origin,
                     cmd.directory,
                     cmd.command
                 );
             }
             println!("\n{} commands", commands.len());
         }
-    } else if let Some(host_id) = args.host_id {
-        let sessions = mgr.get_sessions_for_host(host_id)?;
-
-        let filtered: Vec<_> = if args.active {
-            sessions
-                .into_iter()
-                .filter(|s| s.ended_at.is_none())
-                .collect()
-        } else {
-            sessions
+    } else if listing {
+        let filter = SessionFilter {
+            name: args.named.clone(),
+            tag: args.with_tag.clone(),
+            host_id: args.host_id.map(HostId::new),
+            since: args
+                .since
+                .as_deref()
+                .map(|d| parse_date_arg(d, false))
+                .transpose()?,
+            before: args
+                .before
+                .as_deref()
+                .map(|d| parse_date_arg(d, true))
+                .transpose()?,
+            active_only: args.active,
         };
+        let sessions = mgr.db.find_sessions(&filter)?;
 
         println!("=== Sessions ===\n");
-        for session in filtered {
-            println!("ID: {}", session.id);
-            println!("Host: {} ({})", session.hostname, session.host_id);
-            println!(
-                "Started: {}",
-                session.started_at.format("%Y-%m-%d %H:%M:%S")
-            );
-            if let Some(ended) = session.ended_at {
-                println!("Ended: {}", ended.format("%Y-%m-%d %H:%M:%S"));
-            } else {
-                println!("Status: Active");
+        if args.tree {
+            for (i, depth) in tree_order(&sessions) {
+                print_session_tree_line(&sessions[i], depth);
             }
             println!();
+        } else {
+            for session in &sessions {
+                print_session(session);
+            }
+        }
+        if !app.quiet {
+            println!("{} sessions", sessions.len());
         }
     } else {
-        println!("Must specify --host-id or --show-commands <SESSION_ID>");
+        println!(
+            "Must specify --list, --host-id, --show-commands <SESSION_ID>, --export <SESSION_ID>, --name/--tag/--note or --reap"
+        );
     }
 
     Ok(())
 }
 
+fn print_session_tree_line(session: &Session, depth: usize) {
+    let branch = if depth == 0 {
+        String::new()
+    } else {
+        format!("{}└─ ", "   ".repeat(depth - 1))
+    };
+    let mut line = format!("{}{}", branch, session.id);
+    if let Some(ref name) = session.name {
+        line.push_str(&format!(" \"{}\"", name));
+    }
+    if !session.tags.is_empty() {
+        line.push_str(&format!(" [{}]", session.tags.join(", ")));
+    }
+    let status = match session.ended_at {
+        Some(_) => "ended",
+        None => "active",
+    };
+    println!(
+        "{}  {}  {}  {}",
+        line,
+        session.hostname,
+        session.started_at.format("%Y-%m-%d %H:%M:%S"),
+        status
+    );
+}
+
+fn print_session(session: &Session) {
+    println!("ID: {}", session.id);
+    if let Some(ref name) = session.name {
+        println!("Name: {}", name);
+    }
+    if !session.tags.is_empty() {
+        println!("Tags: {}", session.tags.join(", "));
+    }
+    if let Some(ref note) = session.note {
+        println!("Note: {}", note);
+    }
+    if let Some(ref parent) = session.parent_id {
+        println!("Parent: {}", parent);
+    }
+    if let Some(ref original) = session.replay_of {
+        println!("Replay of: {}", original);
+    }
+    println!("Host: {} ({})", session.hostname, session.host_id);
+    println!(
+        "Started: {}",
+        session.started_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    if let Some(pid) = session.pid {
+        println!("Shell PID: {}", pid);
+    }
+    if let Some(ref tty) = session.tty {
+        println!("TTY: {}", tty);
+    }
+    if let Some(ended) = session.ended_at {
+        println!("Ended: {}", ended.format("%Y-%m-%d %H:%M:%S"));
+    } else {
+        println!("Status: Active");
+    }
+    println!();
+}
+
 pub fn handle_end_session(app: &mut CliApp, args: &EndSessionArgs) -> Result<()> {
+    // Commands buffered in ephemeral private mode never outlive the session
+    if let Some(buffer) = EphemeralBuffer::for_session(&args.session_id) {
+        buffer.wipe()?;
+    }
+
     let mgr = match &mut app.backend {
         HistoryBackend::Database(mgr) => mgr,
         HistoryBackend::File(_) => {
@@ -202,6 +369,74 @@ pub fn handle_end_session(app: &mut CliApp, args: &EndSessionArgs) -> Result<()>
     Ok(())
 }
 
+pub fn handle_private(app: &mut CliApp, args: &PrivateArgs) -> Result<()> {
+    let mgr = match &mut app.backend {
+        HistoryBackend::Database(mgr) => mgr,
+        HistoryBackend::File(_) => {
+            return Err(Error::custom(
+                "Private sessions require database backend. Remove --use-file flag to use the default database backend.",
+            ));
+        }
+    };
+
+    let session_id = args
+        .session_id
+        .clone()
+        .or_else(|| std::env::var("ZAM_SESSION_ID").ok())
+        .ok_or_else(|| Error::custom("--session-id required (or set ZAM_SESSION_ID)"))?;
+
+    let mode = match args.state {
+        Some(PrivateState::On) => {
+            let mode = if args.ephemeral {
+                if !EphemeralBuffer::is_available() {
+                    return Err(Error::custom(
+                        "Ephemeral private mode needs a runtime directory ($XDG_RUNTIME_DIR) to keep commands off disk; use `zam private on` to drop them instead",
+                    ));
+                }
+                PrivateMode::Ephemeral
+            } else {
+                PrivateMode::Drop
+            };
+            mgr.db.set_session_private(&session_id, Some(mode))?;
+            Some(mode)
+        }
+        Some(PrivateState::Off) => {
+            mgr.db.set_session_private(&session_id, None)?;
+            if let Some(buffer) = EphemeralBuffer::for_session(&session_id) {
+                buffer.wipe()?;
+            }
+            None
+        }
+        None => mgr.db.get_session_private(&session_id)?,
+    };
+
+    if args.export {
+        match mode {
+            Some(mode) => println!("export ZAM_PRIVATE='{}'", mode.as_str()),
+            None => println!("unset ZAM_PRIVATE"),
+        }
+        return Ok(());
+    }
+
+    if !app.quiet {
+        match mode {
+            Some(PrivateMode::Drop) => {
+                println!(
+                    "Private mode on for {}: commands are not recorded",
+                    session_id
+                )
+            }
+            Some(PrivateMode::Ephemeral) => println!(
+                "Private mode on for {}: commands are kept for this session only",
+                session_id
+            ),
+            None => println!("Private mode off for {}", session_id),
+        }
+    }
+
+    Ok(())
+}
+
 pub fn handle_vacuum(app: &mut CliApp, args: &VacuumArgs) -> Result<()> {
     let mgr = match &mut app.backend {
         HistoryBackend::Database(mgr) => mgr,
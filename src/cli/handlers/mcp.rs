@@ -0,0 +1,28 @@
+//! MCP server handler for zam CLI
+
+use crate::cli::{CliApp, HistoryBackend};
+use crate::error::{Error, Result};
+use crate::mcp::McpServer;
+use std::io;
+
+pub fn handle_mcp(app: &mut CliApp) -> Result<()> {
+    let mgr = match &app.backend {
+        HistoryBackend::Database(mgr) => mgr,
+        HistoryBackend::File(_) => {
+            return Err(Error::custom(
+                "MCP server requires database backend. Remove --use-file flag to use the default database backend.",
+            ));
+        }
+    };
+
+    // The CLI falls back to defaults when the config file is invalid, which
+    // would expose every tool; refuse to serve instead
+    if let Some(ref e) = app.config_error {
+        return Err(Error::custom(format!(
+            "Refusing to serve MCP with an invalid configuration: {}",
+            e
+        )));
+    }
+
+    McpServer::new(&mgr.db, &app.config.mcp).serve(io::stdin().lock(), io::stdout().lock())
+}
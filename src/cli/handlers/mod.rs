@@ -5,6 +5,9 @@
 //! - `import_export`: Import and export handlers
 //! - `database`: Database-specific handlers (migrate, merge, tokens, hosts, sessions)
 //! - `config`: Configuration and shell integration handlers
+//! - `redact`: Redaction allowlist and audit handlers
+//! - `replay`: Interactive session replay
+//! - `mcp`: Model Context Protocol server for agents
 //! - `util`: Utility functions for handlers
 
 mod alias;
@@ -13,8 +16,12 @@ mod basic;
 mod config;
 mod database;
 mod import_export;
+mod mcp;
+mod redact;
+mod replay;
 mod shell_integration;
 mod tui_handler;
+mod util;
 
 pub use alias::*;
 pub use auth::*;
@@ -22,5 +29,8 @@ pub use basic::*;
 pub use config::*;
 pub use database::*;
 pub use import_export::*;
+pub use mcp::*;
+pub use redact::*;
+pub use replay::*;
 pub use shell_integration::*;
 pub use tui_handler::*;
@@ -0,0 +1,220 @@
+//! Redaction correction, audit and purge handlers for zam CLI
+
+use crate::audit::AuditReport;
+use crate::cli::args::*;
+use crate::cli::{CliApp, HistoryBackend};
+use crate::database::Database;
+use crate::error::{Error, Result};
+use crate::forget::{ForgetSummary, build_matcher};
+use crate::history::HistoryManager;
+use std::collections::BTreeMap;
+use std::io::{self, BufRead};
+
+pub fn handle_redact(app: &mut CliApp, args: &RedactArgs) -> Result<()> {
+    let mgr = match &mut app.backend {
+        HistoryBackend::Database(mgr) => mgr,
+        HistoryBackend::File(_) => {
+            return Err(Error::custom(
+                "Redaction allowlist requires the database backend. Remove --use-file flag to use the default database backend.",
+            ));
+        }
+    };
+
+    match &args.command {
+        RedactCommands::Allow(allow_args) => {
+            let allowed = mgr.allow_tokens(allow_args.command_id, allow_args.token.as_deref())?;
+
+            if allowed.is_empty() {
+                return Err(Error::invalid_arguments(match &allow_args.token {
+                    Some(placeholder) => format!(
+                        "Command {} has no token {}",
+                        allow_args.command_id, placeholder
+                    ),
+                    None => format!(
+                        "Command {} has no stored tokens; its original text cannot be restored",
+                        allow_args.command_id
+                    ),
+                }));
+            }
+
+            if !app.quiet {
+                for token in &allowed {
+                    println!(
+                        "Allowed {} ({}): will not be redacted again",
+                        token.placeholder, token.token_type
+                    );
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn handle_audit(app: &mut CliApp, args: &AuditArgs) -> Result<()> {
+    let db = match &app.backend {
+        HistoryBackend::Database(mgr) => &mgr.db,
+        HistoryBackend::File(_) => {
+            return Err(Error::custom(
+                "Audit requires database backend. Remove --use-file flag to use the default database backend.",
+            ));
+        }
+    };
+
+    let report = AuditReport::build(db, args.limit)?;
+
+    match args.format {
+        AuditFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+        AuditFormat::Table => print_audit_table(&report),
+    }
+
+    Ok(())
+}
+
+fn print_audit_table(report: &AuditReport) {
+    println!("Redaction Audit");
+    println!("===============");
+    println!("Commands:           {}", report.total_commands);
+    println!("Redacted commands:  {}", report.redacted_commands);
+    println!("Secrets captured:   {}", report.total_tokens);
+
+    print_counts("By token type", &report.by_token_type);
+    print_counts("By pattern", &report.by_pattern);
+    print_counts("By host", &report.by_host);
+    print_counts("By month", &report.by_month);
+
+    println!("\nRisky unredacted commands");
+    println!("-------------------------");
+    if report.risky_commands.is_empty() {
+        println!("  none");
+    }
+    for risky in &report.risky_commands {
+        let preview: String = risky.command.chars().take(60).collect();
+        println!(
+            "  #{:<6} {}  {}",
+            risky.command_id,
+            risky.timestamp.format("%Y-%m-%d"),
+            preview
+        );
+        for reason in &risky.reasons {
+            println!("           - {}", reason);
+        }
+    }
+
+    println!("\nReused secrets");
+    println!("--------------");
+    if report.reused_tokens.is_empty() {
+        println!("  none");
+    }
+    for reused in &report.reused_tokens {
+        let ids: Vec<String> = reused.command_ids.iter().map(|id| id.to_string()).collect();
+        println!(
+            "  {:<16} {:<8} {:<14} commands {}",
+            reused.fingerprint,
+            reused.preview,
+            reused.token_type,
+            ids.join(", ")
+        );
+    }
+}
+
+fn print_counts(title: &str, counts: &BTreeMap<String, usize>) {
+    println!("\n{}", title);
+    println!("{}", "-".repeat(title.len()));
+    if counts.is_empty() {
+        println!("  none");
+    }
+    for (key, count) in counts {
+        println!("  {:<30} {:>6}", key, count);
+    }
+}
+
+pub fn handle_forget(app: &mut CliApp, args: &ForgetArgs) -> Result<()> {
+    let value = match args.value.as_deref() {
+        Some("-") => {
+            let mut line = String::new();
+            io::stdin().lock().read_line(&mut line)?;
+            Some(line.trim_end_matches(['\n', '\r']).to_string())
+        }
+        value => value.map(str::to_string),
+    };
+    let matcher = build_matcher(value.as_deref(), args.pattern.as_deref())?;
+    let replacement = app.config.redaction.placeholder.clone();
+    let db_path = app.config.history_file.with_extension("db");
+    let history_file = app.config.history_file.clone();
+
+    // Purge both stores regardless of the active backend: a secret logged
+    // before switching backends must not survive in the other one.
+    let db_summary = match &app.backend {
+        HistoryBackend::Database(mgr) => Some(mgr.db.forget_matching(
+            &matcher,
+            &replacement,
+            args.dry_run,
+        )?),
+        HistoryBackend::File(_) if db_path.exists() => {
+            Some(Database::new(&db_path)?.forget_matching(&matcher, &replacement, args.dry_run)?)
+        }
+        HistoryBackend::File(_) => None,
+    };
+    let file_lines = match &mut app.backend {
+        HistoryBackend::File(mgr) => {
+            Some(mgr.forget_matching(&matcher, &replacement, args.dry_run)?)
+        }
+        HistoryBackend::Database(_) if history_file.exists() => {
+            Some(HistoryManager::new(app.config.clone())?.forget_matching(
+                &matcher,
+                &replacement,
+                args.dry_run,
+            )?)
+        }
+        HistoryBackend::Database(_) => None,
+    };
+
+    if app.quiet {
+        return Ok(());
+    }
+
+    let nothing_found = db_summary.as_ref().is_none_or(ForgetSummary::is_empty)
+        && file_lines.as_ref().is_none_or(Vec::is_empty);
+    if nothing_found {
+        println!("No matches found");
+        return Ok(());
+    }
+
+    let (verb, header) = if args.dry_run {
+        ("would be ", "Dry run: nothing was changed\n")
+    } else {
+        ("", "")
+    };
+    print!("{}", header);
+
+    if let Some(summary) = &db_summary {
+        println!("Database ({})", db_path.display());
+        println!("  Commands {}rewritten: {}", verb, summary.commands.len());
+        for (id, command) in &summary.commands {
+            let preview: String = command.chars().take(70).collect();
+            println!("    #{:<6} {}", id, preview);
+        }
+        println!("  Tokens {}deleted: {}", verb, summary.tokens.len());
+        for (id, token_type) in &summary.tokens {
+            println!("    #{:<6} {}", id, token_type);
+        }
+        println!(
+            "  Allowlist entries {}removed: {}",
+            verb, summary.allowlist_entries
+        );
+    }
+
+    if let Some(lines) = &file_lines {
+        println!("History file ({})", history_file.display());
+        println!("  Lines {}rewritten: {}", verb, lines.len());
+        for line in lines {
+            let preview: String = line.chars().take(70).collect();
+            println!("    {}", preview);
+        }
+    }
+
+    println!("\nShell history files (e.g. ~/.zsh_history) are not modified.");
+
+    Ok(())
+}
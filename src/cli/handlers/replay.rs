@@ -0,0 +1,161 @@
+//! Interactive session replay handler for zam CLI
+
+use crate::cli::args::*;
+use crate::cli::{CliApp, HistoryBackend};
+use crate::error::{Error, Result};
+use crate::replay::{ReplayAction, fill_placeholders, replay_session_id, tokens_for};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+use std::process::Command;
+
+pub fn handle_replay(app: &mut CliApp, args: &ReplayArgs) -> Result<()> {
+    let mgr = match &mut app.backend {
+        HistoryBackend::Database(mgr) => mgr,
+        HistoryBackend::File(_) => {
+            return Err(Error::custom(
+                "Replay requires database backend. Remove --use-file flag to use the default database backend.",
+            ));
+        }
+    };
+
+    let original = mgr
+        .db
+        .get_session(&args.session_id)?
+        .ok_or_else(|| Error::custom(format!("Session {} not found", args.session_id)))?;
+    let mut commands = mgr.get_commands_for_session(&args.session_id)?;
+    commands.sort_by_key(|c| (c.timestamp, c.id.as_i64()));
+    if commands.is_empty() {
+        println!("No commands found for session {}", args.session_id);
+        return Ok(());
+    }
+    let tokens = mgr.db.get_tokens_by_session(&args.session_id)?;
+    let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string());
+
+    println!(
+        "Replaying {} commands from session {}",
+        commands.len(),
+        original.label()
+    );
+
+    let mut input = io::stdin().lock();
+    let mut replay_session: Option<String> = None;
+    let mut ran = 0;
+    let total = commands.len();
+
+    'steps: for (i, cmd) in commands.iter().enumerate() {
+        let status = cmd
+            .exit_code
+            .map(|code| format!(", exit {}", code))
+            .unwrap_or_default();
+        println!(
+            "\n[{}/{}] {} ({}{})",
+            i + 1,
+            total,
+            cmd.directory,
+            cmd.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            status
+        );
+
+        let mut command = cmd.command.clone();
+        loop {
+            println!("$ {}", command);
+            let Some(answer) = prompt(
+                &mut input,
+                &format!("Run this command [{}]? ", ReplayAction::CHOICES),
+            )?
+            else {
+                break 'steps;
+            };
+            match ReplayAction::parse(&answer) {
+                Some(ReplayAction::Run) => break,
+                Some(ReplayAction::Skip) => continue 'steps,
+                Some(ReplayAction::Quit) => break 'steps,
+                Some(ReplayAction::Edit) => {
+                    let Some(edited) = prompt(&mut input, "New command (empty keeps it): ")? else {
+                        break 'steps;
+                    };
+                    if !edited.trim().is_empty() {
+                        command = edited.trim().to_string();
+                    }
+                }
+                Some(ReplayAction::Help) | None => println!("{}", ReplayAction::HELP),
+            }
+        }
+
+        // Secrets are only put back on the command line once confirmed
+        let secrets = tokens_for(&tokens, cmd.id, &command);
+        let mut to_run = command.clone();
+        if !secrets.is_empty() {
+            let question = format!(
+                "Fill {} redacted value(s) from the tokens table [y,n]? ",
+                secrets.len()
+            );
+            match prompt(&mut input, &question)? {
+                Some(answer) if matches!(answer.trim(), "y" | "Y" | "yes") => {
+                    to_run = fill_placeholders(&command, &secrets);
+                }
+                Some(_) => {}
+                None => break,
+            }
+        }
+
+        let directory = if Path::new(&cmd.directory).is_dir() {
+            cmd.directory.clone()
+        } else {
+            let current = std::env::current_dir()?.to_string_lossy().to_string();
+            eprintln!("{} does not exist, running in {}", cmd.directory, current);
+            current
+        };
+
+        let exit_code = Command::new(&shell)
+            .arg("-c")
+            .arg(&to_run)
+            .current_dir(&directory)
+            .status()?
+            .code();
+        ran += 1;
+        if let Some(code) = exit_code.filter(|&code| code != 0) {
+            println!("exit {}", code);
+        }
+
+        let session_id = match replay_session {
+            Some(ref id) => id.clone(),
+            None => {
+                let id = replay_session_id();
+                mgr.db.resume_session(&id)?;
+                mgr.db.set_session_replay_of(&id, &args.session_id)?;
+                replay_session = Some(id.clone());
+                id
+            }
+        };
+        mgr.set_session_id(&session_id)?;
+        mgr.log_command_in(&to_run, &directory, None, exit_code)?;
+    }
+
+    if let Some(ref id) = replay_session {
+        mgr.end_session(id)?;
+    }
+    if !app.quiet {
+        match replay_session {
+            Some(id) => println!(
+                "\nRan {} of {} commands, logged to session {}",
+                ran, total, id
+            ),
+            None => println!("\nRan 0 of {} commands", total),
+        }
+    }
+
+    Ok(())
+}
+
+/// Print `question` and read one answer line. Returns `None` at end of input.
+fn prompt(input: &mut impl BufRead, question: &str) -> Result<Option<String>> {
+    print!("{}", question);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        println!();
+        return Ok(None);
+    }
+    Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+}
@@ -7,8 +7,8 @@ use crate::error::Result;
 pub fn handle_shell(app: &CliApp, args: &ShellArgs) -> Result<()> {
     let shell_script = match args.shell {
         ShellType::Zsh => generate_zsh_integration(),
-        ShellType::Bash => generate_bash_integration(),
-        ShellType::Fish => generate_fish_integration(),
+        ShellType::Bash => generate_bash_integration(args.tui),
+        ShellType::Fish => generate_fish_integration(args.tui),
     };
 
     if let Some(output_file) = &args.output {
@@ -27,8 +27,13 @@ fn generate_zsh_integration() -> String {
     r#"# Zam Zsh Integration
 # Add this to your ~/.zshrc
 
-# One session per shell instance
+# One session per shell instance. A shell started inside another one (nested
+# shell, tmux pane) records the inherited session as its parent.
+export ZAM_PARENT_SESSION_ID="$ZAM_SESSION_ID"
 export ZAM_SESSION_ID="zsh-$$-$(date +%s)"
+export ZAM_SHELL_PID=$$
+export ZAM_TTY="$TTY"
+unset ZAM_PRIVATE
 
 # Log all commands with their exit code
 _zam_last_cmd=""
@@ -48,12 +53,14 @@ add-zsh-hook preexec _zam_preexec
 add-zsh-hook precmd _zam_precmd
 add-zsh-hook zshexit _zam_zshexit
 
-# Interactive TUI history browser (Ctrl+R)
+# Interactive TUI history browser (Ctrl+R). Enter runs the chosen command,
+# Alt+Enter leaves it on the prompt for editing.
 zam-widget() {
-    local cmd="$(zam tui)"
-    if [[ -n "$cmd" ]]; then
-        BUFFER="$cmd"
-        zle accept-line
+    local out="$(zam tui --widget)"
+    if [[ "$out" == *$'\n'* ]]; then
+        BUFFER="${out#*$'\n'}"
+        CURSOR=${#BUFFER}
+        [[ "${out%%$'\n'*}" == run ]] && zle accept-line
     fi
     zle reset-prompt
 }
@@ -69,41 +76,90 @@ zam-auth() {
     fi
 }
 
+# Private mode: `zam-private on` stops recording, `zam-private on -e` keeps
+# commands for this session's Ctrl-R only, `zam-private off` resumes.
+# $ZAM_PRIVATE is set while private, e.g. for a prompt indicator:
+#   setopt PROMPT_SUBST; PROMPT='${ZAM_PRIVATE:+[private] }'$PROMPT
+zam-private() {
+    local output
+    output="$(zam private "$@" --export --session-id "$ZAM_SESSION_ID")" || return
+    eval "$output"
+}
+
 # Load zam aliases into shell
 eval "$(zam alias list --shell 2>/dev/null)"
 "#
     .to_string()
 }
 
-fn generate_bash_integration() -> String {
-    r#"# Zam Bash Integration
+/// Ctrl+R bindings of `zam shell bash`: fzf search
+const BASH_FZF_BINDINGS: &str = r#"# Interactive history search with fzf (Ctrl+R)
+bind -x '"\C-r": "READLINE_LINE=$(zam fzf | fzf --height 50% --reverse --tac 2>/dev/tty); READLINE_POINT=${#READLINE_LINE}"'
+"#;
+
+/// Ctrl+R bindings of `zam shell bash --tui`
+const BASH_TUI_BINDINGS: &str = r#"# Interactive TUI history browser (Ctrl+R). Enter runs the chosen command,
+# Alt+Enter leaves it on the prompt for editing. `bind -x` cannot accept the
+# line itself, so Ctrl+R runs the widget and then \C-x\C-m, which the widget
+# rebinds to accept-line or to a no-op.
+_zam_widget() {
+    local out
+    out="$(zam tui --widget)"
+    bind '"\C-x\C-m": redraw-current-line'
+    if [[ "$out" == *$'\n'* ]]; then
+        READLINE_LINE="${out#*$'\n'}"
+        READLINE_POINT=${#READLINE_LINE}
+        [[ "${out%%$'\n'*}" == run ]] && bind '"\C-x\C-m": accept-line'
+    fi
+}
+bind -x '"\C-x\C-r": _zam_widget'
+bind '"\C-r": "\C-x\C-r\C-x\C-m"'
+
+# History search with fzf (Alt+R)
+bind -x '"\er": "READLINE_LINE=$(zam fzf | fzf --height 50% --reverse --tac 2>/dev/tty); READLINE_POINT=${#READLINE_LINE}"'
+"#;
+
+fn generate_bash_integration(tui: bool) -> String {
+    let mut script = r#"# Zam Bash Integration
 # Add this to your ~/.bashrc
 
-# One session per shell instance
+# One session per shell instance. A shell started inside another one (nested
+# shell, tmux pane) records the inherited session as its parent.
+export ZAM_PARENT_SESSION_ID="$ZAM_SESSION_ID"
 export ZAM_SESSION_ID="bash-$$-$(date +%s)"
+export ZAM_SHELL_PID=$$
+export ZAM_TTY="$(tty 2>/dev/null)"
+unset ZAM_PRIVATE
 
-# Log all commands with their exit code
-_zam_last_cmd=""
-_zam_preexec() {
-    _zam_last_cmd="$1"
-}
-trap '_zam_preexec "$BASH_COMMAND"' DEBUG
-
+# Log all commands with their exit code. The command line is read back from
+# bash's history so leading spaces survive ($BASH_COMMAND strips them);
+# lines bash itself does not record (HISTCONTROL) are not logged either.
+_zam_histnum=""
 _zam_precmd() {
     local rc=$?
-    if [[ -n "$_zam_last_cmd" ]]; then
-        zam log "$_zam_last_cmd" -E "$rc" --session-id "$ZAM_SESSION_ID"
+    local hist num line
+    hist="$(HISTTIMEFORMAT= builtin history 1)"
+    read -r num _ <<< "$hist"
+    line="${hist#"${hist%%[0-9]*}$num  "}"
+    if [[ -n "$_zam_histnum" && -n "$num" && "$num" != "$_zam_histnum" ]]; then
+        zam log "$line" -E "$rc" --session-id "$ZAM_SESSION_ID"
     fi
-    _zam_last_cmd=""
+    _zam_histnum="${num:-$_zam_histnum}"
 }
 PROMPT_COMMAND="_zam_precmd;${PROMPT_COMMAND:+ $PROMPT_COMMAND}"
 
 # Close session on shell exit
 trap 'zam end-session "$ZAM_SESSION_ID" 2>/dev/null' EXIT
 
-# Interactive history search with fzf (Ctrl+R)
-bind -x '"\C-r": "READLINE_LINE=$(zam fzf | fzf --height 50% --reverse --tac 2>/dev/tty); READLINE_POINT=${#READLINE_LINE}"'
-
+"#
+    .to_string();
+    script.push_str(if tui {
+        BASH_TUI_BINDINGS
+    } else {
+        BASH_FZF_BINDINGS
+    });
+    script.push_str(
+        r#"
 # Load 1Password secrets into session environment
 zam-auth() {
     local output
@@ -113,18 +169,77 @@ zam-auth() {
     fi
 }
 
+# Private mode: `zam-private on` stops recording, `zam-private on -e` keeps
+# commands for this session's Ctrl-R only, `zam-private off` resumes.
+# $ZAM_PRIVATE is set while private, e.g. for a prompt indicator:
+#   PS1='${ZAM_PRIVATE:+[private] }'$PS1
+zam-private() {
+    local output
+    output="$(zam private "$@" --export --session-id "$ZAM_SESSION_ID")" || return
+    eval "$output"
+}
+
 # Load zam aliases into shell
 eval "$(zam alias list --shell 2>/dev/null)"
-"#
-    .to_string()
+"#,
+    );
+    script
 }
 
-fn generate_fish_integration() -> String {
-    r#"# Zam Fish Integration
+/// Ctrl+R bindings of `zam shell fish`: fzf search
+const FISH_FZF_BINDINGS: &str = r#"# Interactive history search with fzf (Ctrl+R)
+function zam_fzf_search
+    set -l result (zam fzf | fzf --height 50% --reverse --tac 2>/dev/tty)
+    if test -n "$result"
+        commandline -r "$result"
+    end
+    commandline -f repaint
+end
+
+# Replace default Ctrl-R with fzf search
+bind \cr zam_fzf_search
+"#;
+
+/// Ctrl+R bindings of `zam shell fish --tui`
+const FISH_TUI_BINDINGS: &str = r#"# Interactive TUI history browser (Ctrl+R). Enter runs the chosen command,
+# Alt+Enter leaves it on the prompt for editing.
+function zam_tui_search
+    set -l out (zam tui --widget)
+    if test (count $out) -ge 2
+        commandline -r -- (string join \n -- $out[2..-1])
+        commandline -f end-of-line
+        if test "$out[1]" = run
+            commandline -f execute
+        end
+    end
+    commandline -f repaint
+end
+
+bind \cr zam_tui_search
+
+# History search with fzf (Alt+R)
+function zam_fzf_search
+    set -l result (zam fzf | fzf --height 50% --reverse --tac 2>/dev/tty)
+    if test -n "$result"
+        commandline -r "$result"
+    end
+    commandline -f repaint
+end
+
+bind \er zam_fzf_search
+"#;
+
+fn generate_fish_integration(tui: bool) -> String {
+    let mut script = r#"# Zam Fish Integration
 # Add this to your ~/.config/fish/config.fish
 
-# One session per shell instance
+# One session per shell instance. A shell started inside another one (nested
+# shell, tmux pane) records the inherited session as its parent.
+set -gx ZAM_PARENT_SESSION_ID "$ZAM_SESSION_ID"
 set -gx ZAM_SESSION_ID "fish-"(echo %self)"-"(date +%s)
+set -gx ZAM_SHELL_PID (echo %self)
+set -gx ZAM_TTY (tty 2>/dev/null)
+set -e ZAM_PRIVATE
 
 # Close session on shell exit
 function _zam_exit --on-event fish_exit
@@ -145,18 +260,15 @@ function _zam_postexec --on-event fish_postexec
     set -g _zam_last_cmd ""
 end
 
-# Interactive history search with fzf (Ctrl+R)
-function zam_fzf_search
-    set -l result (zam fzf | fzf --height 50% --reverse --tac 2>/dev/tty)
-    if test -n "$result"
-        commandline -r "$result"
-    end
-    commandline -f repaint
-end
-
-# Replace default Ctrl-R with fzf search
-bind \cr zam_fzf_search
-
+"#
+    .to_string();
+    script.push_str(if tui {
+        FISH_TUI_BINDINGS
+    } else {
+        FISH_FZF_BINDINGS
+    });
+    script.push_str(
+        r#"
 # Load 1Password secrets into session environment
 function zam-auth
     set -l output (zam auth $argv --export --session-id "$ZAM_SESSION_ID" 2>/dev/null)
@@ -165,8 +277,47 @@ function zam-auth
     end
 end
 
+# Private mode: `zam-private on` stops recording, `zam-private on -e` keeps
+# commands for this session's Ctrl-R only, `zam-private off` resumes.
+# $ZAM_PRIVATE is set while private, e.g. for a prompt indicator:
+#   set -q ZAM_PRIVATE; and echo -n "[private] "
+function zam-private
+    zam private $argv --session-id "$ZAM_SESSION_ID"; or return
+    set -l mode (zam private --export --session-id "$ZAM_SESSION_ID")
+    if string match -q "export *" -- "$mode"
+        set -gx ZAM_PRIVATE (string replace -r "^export ZAM_PRIVATE='(.*)'\$" '$1' -- "$mode")
+    else
+        set -e ZAM_PRIVATE
+    end
+end
+
 # Load zam aliases into shell
 eval (zam alias list --shell 2>/dev/null)
-"#
-    .to_string()
+"#,
+    );
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ctrl_r_bindings() {
+        // fzf keeps Ctrl-R in bash and fish unless the TUI is asked for
+        let bash = generate_bash_integration(false);
+        assert!(bash.contains(r#"bind -x '"\C-r": "READLINE_LINE=$(zam fzf"#));
+        assert!(!bash.contains("zam tui"));
+        assert!(!bash.contains(r"\C-x\C-m"));
+        let fish = generate_fish_integration(false);
+        assert!(fish.contains(r"bind \cr zam_fzf_search"));
+        assert!(!fish.contains("zam tui"));
+
+        let bash = generate_bash_integration(true);
+        assert!(bash.contains(r#"bind '"\C-r": "\C-x\C-r\C-x\C-m"'"#));
+        assert!(bash.contains(r#"bind -x '"\er": "READLINE_LINE=$(zam fzf"#));
+        let fish = generate_fish_integration(true);
+        assert!(fish.contains(r"bind \cr zam_tui_search"));
+        assert!(fish.contains(r"bind \er zam_fzf_search"));
+    }
 }
@@ -1,26 +1,40 @@
 //! TUI handler for zam CLI
 
+use crate::backend::HistoryQuery;
+use crate::cli::args::TuiArgs;
 use crate::cli::{CliApp, HistoryBackend};
-use crate::error::{Error, Result};
+use crate::error::Result;
 use crate::tui;
 use std::env;
 
-pub fn handle_tui(app: &mut CliApp) -> Result<()> {
-    let mgr = match &app.backend {
-        HistoryBackend::Database(mgr) => mgr,
-        HistoryBackend::File(_) => {
-            return Err(Error::custom(
-                "TUI requires the database backend. Remove --use-file flag to use the default database backend.",
-            ));
+pub fn handle_tui(app: &mut CliApp, args: &TuiArgs) -> Result<()> {
+    let history: &dyn HistoryQuery = match &app.backend {
+        HistoryBackend::Database(mgr) => {
+            // Close sessions of dead shells so the Sessions tab shows what
+            // is open. Best effort, like the reap when commands are logged.
+            if let Err(e) = mgr.db.maybe_reap_stale_sessions() {
+                tracing::debug!("Skipping stale session reap: {}", e);
+            }
+            &mgr.db
         }
+        HistoryBackend::File(mgr) => mgr,
     };
 
     let cwd = env::current_dir()
         .map(|p| p.to_string_lossy().into_owned())
         .unwrap_or_default();
 
-    if let Some(cmd) = tui::run_tui(&mgr.db, cwd)? {
-        println!("{cmd}");
+    let mut config = app.config.tui.clone();
+    if let Some(height) = args.height {
+        config.height = height;
+    }
+
+    if let Some(selection) = tui::run_tui(history, cwd, &config)? {
+        if args.widget {
+            print!("{}", selection.widget_output());
+        } else {
+            println!("{}", selection.command);
+        }
     }
     Ok(())
 }
@@ -0,0 +1,18 @@
+//! Utility functions for handlers
+
+use crate::error::{Error, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+
+/// Parse a `YYYY-MM-DD` date argument as the start of that day (UTC), or
+/// its last second when `end_of_day` is set so the day is included
+pub fn parse_date_arg(date: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| Error::InvalidTimestamp {
+        timestamp: date.to_string(),
+    })?;
+    let time = if end_of_day {
+        day.and_hms_opt(23, 59, 59)
+    } else {
+        day.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.unwrap().and_utc())
+}
@@ -12,7 +12,7 @@ pub use args::*;
 use handlers::*;
 
 use crate::config::Config;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::history::HistoryManager;
 use crate::history_db::HistoryManagerDb;
 use crate::search::SearchEngine;
@@ -86,16 +86,28 @@ pub enum Commands {
     Hosts(HostsArgs),
     /// List and manage sessions
     Sessions(SessionsArgs),
+    /// Replay a past session step by step
+    Replay(ReplayArgs),
+    /// Serve history and aliases to coding agents over MCP (stdio)
+    Mcp,
     /// Manage shell aliases
     Alias(AliasArgs),
+    /// Correct redaction mistakes (allowlist false positives)
+    Redact(RedactArgs),
+    /// Report what redaction has captured and what may have slipped through
+    Audit(AuditArgs),
+    /// Purge a leaked secret from the database and history file
+    Forget(ForgetArgs),
     /// Close an active session
     EndSession(EndSessionArgs),
+    /// Stop recording the current session (incognito mode)
+    Private(PrivateArgs),
     /// Load secrets from 1Password into shell environment
     Auth(AuthArgs),
     /// Vacuum the database and optionally prune old commands
     Vacuum(VacuumArgs),
     /// Interactive entity browser (database only)
-    Tui,
+    Tui(TuiArgs),
 }
 
 /// History backend type
@@ -107,6 +119,9 @@ pub(crate) enum HistoryBackend {
 /// Main CLI application
 pub struct CliApp {
     pub config: Config,
+    /// Why the default config file was not used, when `config` fell back
+    /// to the defaults
+    pub config_error: Option<Error>,
     pub(crate) backend: HistoryBackend,
     pub search_engine: SearchEngine,
     pub verbose: bool,
@@ -119,10 +134,21 @@ impl CliApp {
     /// Create a new CLI application
     pub fn new(cli: &Cli) -> Result<Self> {
         // Load configuration
-        let config = if let Some(config_path) = &cli.config {
-            Config::load_from_path(config_path)?
+        let (config, config_error) = if let Some(config_path) = &cli.config {
+            (Config::load_from_path(config_path)?, None)
         } else {
-            Config::load().unwrap_or_else(|_| Config::default())
+            match Config::load() {
+                Ok(config) => (config, None),
+                Err(e) => {
+                    // Say so: the defaults drop the user's redaction and
+                    // exclusion settings, not just the broken one
+                    eprintln!(
+                        "Warning: ignoring invalid configuration, using defaults: {}",
+                        e
+                    );
+                    (Config::default(), Some(e))
+                }
+            }
         };
 
         // Determine which backend to use
@@ -147,6 +173,7 @@ impl CliApp {
 
         Ok(Self {
             config,
+            config_error,
             backend,
             search_engine,
             verbose: cli.verbose,
@@ -185,11 +212,17 @@ impl CliApp {
             Commands::Tokens(args) => handle_tokens(self, args),
             Commands::Hosts(args) => handle_hosts(self, args),
             Commands::Sessions(args) => handle_sessions(self, args),
+            Commands::Replay(args) => handle_replay(self, args),
+            Commands::Mcp => handle_mcp(self),
             Commands::Alias(args) => handle_alias(self, args),
+            Commands::Redact(args) => handle_redact(self, args),
+            Commands::Audit(args) => handle_audit(self, args),
+            Commands::Forget(args) => handle_forget(self, args),
             Commands::EndSession(args) => handle_end_session(self, args),
+            Commands::Private(args) => handle_private(self, args),
             Commands::Auth(args) => handle_auth(self, args),
             Commands::Vacuum(args) => handle_vacuum(self, args),
-            Commands::Tui => handle_tui(self),
+            Commands::Tui(args) => handle_tui(self, args),
         }
     }
 
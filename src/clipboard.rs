@@ -0,0 +1,196 @@
+//! Clipboard access for zam
+//!
+//! The TUI's yank copies text with an OSC 52 terminal escape sequence
+//! when possible: the terminal emulator owns the clipboard, so this works
+//! over SSH and inside tmux. Otherwise it falls back to `wl-copy`,
+//! `xclip`, `xsel` or `pbcopy`. `tui.clipboard` in the configuration
+//! picks one method instead of trying them in order.
+
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// How text is put on the clipboard
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ClipboardMethod {
+    /// OSC 52, then each clipboard tool in turn
+    #[default]
+    Auto,
+    Osc52,
+    WlCopy,
+    Xclip,
+    Xsel,
+    Pbcopy,
+}
+
+impl ClipboardMethod {
+    /// Methods tried by [`ClipboardMethod::Auto`], in order
+    pub const FALLBACK_ORDER: [ClipboardMethod; 5] = [
+        ClipboardMethod::Osc52,
+        ClipboardMethod::WlCopy,
+        ClipboardMethod::Xclip,
+        ClipboardMethod::Xsel,
+        ClipboardMethod::Pbcopy,
+    ];
+
+    /// Name as written in the configuration
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClipboardMethod::Auto => "auto",
+            ClipboardMethod::Osc52 => "osc52",
+            ClipboardMethod::WlCopy => "wl-copy",
+            ClipboardMethod::Xclip => "xclip",
+            ClipboardMethod::Xsel => "xsel",
+            ClipboardMethod::Pbcopy => "pbcopy",
+        }
+    }
+
+    /// Program and arguments that read the text on stdin
+    fn command(&self) -> Option<(&'static str, &'static [&'static str])> {
+        match self {
+            ClipboardMethod::WlCopy => Some(("wl-copy", &[])),
+            ClipboardMethod::Xclip => Some(("xclip", &["-selection", "clipboard"])),
+            ClipboardMethod::Xsel => Some(("xsel", &["--clipboard", "--input"])),
+            ClipboardMethod::Pbcopy => Some(("pbcopy", &[])),
+            ClipboardMethod::Auto | ClipboardMethod::Osc52 => None,
+        }
+    }
+}
+
+/// Copies text with the configured [`ClipboardMethod`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Clipboard {
+    method: ClipboardMethod,
+}
+
+impl Clipboard {
+    pub fn new(method: ClipboardMethod) -> Self {
+        Self { method }
+    }
+
+    /// Copy `text`. Returns the method that worked.
+    pub fn copy(&self, text: &str) -> Result<ClipboardMethod> {
+        let candidates: &[ClipboardMethod] = match self.method {
+            ClipboardMethod::Auto => &ClipboardMethod::FALLBACK_ORDER,
+            ref method => std::slice::from_ref(method),
+        };
+        for method in candidates {
+            match copy_with(*method, text) {
+                Ok(()) => return Ok(*method),
+                Err(e) => tracing::debug!("Clipboard method {} failed: {}", method.name(), e),
+            }
+        }
+        let tried: Vec<&str> = candidates.iter().map(|m| m.name()).collect();
+        Err(Error::custom(format!(
+            "Failed to copy to clipboard (tried {})",
+            tried.join(", ")
+        )))
+    }
+}
+
+fn copy_with(method: ClipboardMethod, text: &str) -> io::Result<()> {
+    if method == ClipboardMethod::Osc52 {
+        // The TUI draws on /dev/tty, so stdout may be a pipe
+        let mut tty = File::options().write(true).open("/dev/tty")?;
+        return write_osc52(&mut tty, text, std::env::var_os("TMUX").is_some());
+    }
+
+    let Some((program, args)) = method.command() else {
+        return Err(io::Error::other("not a clipboard program"));
+    };
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!(
+            "{} exited with {}",
+            program, status
+        )))
+    }
+}
+
+/// Write the OSC 52 sequence that sets the clipboard to `text`. Inside
+/// tmux the sequence is wrapped in a DCS passthrough so it reaches the
+/// outer terminal.
+pub fn write_osc52(out: &mut impl Write, text: &str, in_tmux: bool) -> io::Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", encode_base64(text.as_bytes()));
+    if in_tmux {
+        write!(
+            out,
+            "\x1bPtmux;{}\x1b\\",
+            sequence.replace('\x1b', "\x1b\x1b")
+        )?;
+    } else {
+        out.write_all(sequence.as_bytes())?;
+    }
+    out.flush()
+}
+
+/// Standard base64 with padding
+fn encode_base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"git status"), "Z2l0IHN0YXR1cw==");
+    }
+
+    #[test]
+    fn test_osc52_bytes() {
+        let mut out = Vec::new();
+        write_osc52(&mut out, "git status", false).unwrap();
+        assert_eq!(out, b"\x1b]52;c;Z2l0IHN0YXR1cw==\x07");
+
+        let mut out = Vec::new();
+        write_osc52(&mut out, "ls", true).unwrap();
+        assert_eq!(out, b"\x1bPtmux;\x1b\x1b]52;c;bHM=\x07\x1b\\");
+    }
+
+    #[test]
+    fn test_method_config_names() {
+        for method in ClipboardMethod::FALLBACK_ORDER {
+            let json = serde_json::to_string(&method).unwrap();
+            assert_eq!(json, format!("\"{}\"", method.name()));
+        }
+        let method: ClipboardMethod = serde_json::from_str("\"wl-copy\"").unwrap();
+        assert_eq!(method, ClipboardMethod::WlCopy);
+    }
+}
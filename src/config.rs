@@ -4,9 +4,11 @@
 //! for the zam history manager, including redaction patterns,
 //! file paths, and behavior settings.
 
+use crate::clipboard::ClipboardMethod;
 use crate::error::{Error, Result};
+use crate::keymap::KeymapPreset;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::PathBuf;
 
@@ -48,6 +50,14 @@ pub struct Config {
 
     /// Custom environment variables to redact
     pub custom_env_vars: Vec<String>,
+
+    /// What `zam mcp` exposes to agents
+    #[serde(default)]
+    pub mcp: McpConfig,
+
+    /// Interactive TUI settings
+    #[serde(default)]
+    pub tui: TuiConfig,
 }
 
 /// Configuration for redaction behavior
@@ -74,6 +84,30 @@ pub struct RedactionConfig {
     /// Commands to skip redaction for (matched by prefix, e.g. "cargo", "make")
     #[serde(default)]
     pub skip_commands: Vec<String>,
+
+    /// Whether to apply per-program rule packs (built-in and user packs)
+    #[serde(default = "default_true")]
+    pub use_rule_packs: bool,
+
+    /// Directory holding user rule packs (defaults to `~/.config/zam/rules.d`)
+    #[serde(default)]
+    pub rules_dir: Option<PathBuf>,
+
+    /// Keys whose values are redacted in URL query strings, form bodies and
+    /// inline JSON (also matches `<prefix>_<key>`, e.g. `db_password`)
+    #[serde(default = "default_sensitive_keys")]
+    pub sensitive_keys: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_sensitive_keys() -> Vec<String> {
+    crate::structured::DEFAULT_SENSITIVE_KEYS
+        .iter()
+        .map(|k| k.to_string())
+        .collect()
 }
 
 /// Configuration for importing history from other shells
@@ -142,7 +176,8 @@ pub struct ShellIntegrationConfig {
     /// Whether to automatically log all commands
     pub auto_log: bool,
 
-    /// Commands to exclude from logging
+    /// Programs whose commands are not logged, matched against the whole
+    /// first word (`ls` excludes `ls -la` but not `lsof`)
     pub exclude_commands: Vec<String>,
 
     /// Whether to log commands that start with a space
@@ -153,6 +188,202 @@ pub struct ShellIntegrationConfig {
 
     /// Minimum command length to log
     pub min_command_length: usize,
+
+    /// Shell-style globs matching whole commands that are never logged
+    #[serde(default)]
+    pub exclude_globs: Vec<String>,
+
+    /// Regexes matching commands that are never logged
+    #[serde(default)]
+    pub exclude_regexes: Vec<String>,
+
+    /// Directories (and their subdirectories) whose commands are never logged
+    #[serde(default)]
+    pub exclude_directories: Vec<String>,
+
+    /// Print a tip when a logged command spells out an existing alias
+    #[serde(default)]
+    pub alias_hints: bool,
+}
+
+/// Configuration for the interactive TUI
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TuiConfig {
+    /// How yanked commands reach the clipboard
+    pub clipboard: ClipboardMethod,
+
+    /// How commands marked for multi-select are joined
+    pub join: CommandJoin,
+
+    /// Screen area the TUI draws in
+    pub height: TuiHeight,
+
+    /// Key binding preset: default, emacs or vi
+    pub keymap: KeymapPreset,
+
+    /// Keys per action (see [`crate::keymap::Action`]), replacing the
+    /// preset's keys for that action
+    pub keys: BTreeMap<String, Vec<String>>,
+
+    /// Keys per action in vi normal mode
+    pub normal_keys: BTreeMap<String, Vec<String>>,
+
+    /// Colour palette
+    pub theme: ThemeConfig,
+
+    /// Columns left out of the command tables: exit, time, id, directory,
+    /// redacted
+    pub hide_columns: Vec<String>,
+}
+
+/// Colours of the TUI: a built-in palette with some colours replaced
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Palette to start from
+    pub base: ThemeBase,
+
+    /// Colour per palette entry (e.g. `"header": "#4a9898"`, `"error": "red"`)
+    pub colors: BTreeMap<String, String>,
+}
+
+/// Built-in TUI palette
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThemeBase {
+    /// Dark or light from `ZAM_THEME` or the terminal's background
+    #[default]
+    Auto,
+    Dark,
+    Light,
+}
+
+/// Separator between commands chosen together in the TUI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandJoin {
+    /// `cmd1 && cmd2`: stop at the first failure
+    #[default]
+    And,
+    /// One command per line
+    Newline,
+}
+
+impl CommandJoin {
+    pub fn separator(self) -> &'static str {
+        match self {
+            CommandJoin::And => " && ",
+            CommandJoin::Newline => "\n",
+        }
+    }
+}
+
+/// Screen area of the TUI: the alternate screen, or an inline area below
+/// the prompt that leaves the scrollback in place. Written as `"full"`, a
+/// line count (`20`) or a percentage of the terminal (`"40%"`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "TuiHeightSpec", into = "String")]
+pub enum TuiHeight {
+    #[default]
+    Full,
+    Lines(u16),
+    Percent(u16),
+}
+
+/// Fewest lines the inline TUI is drawn in
+pub const MIN_INLINE_HEIGHT: u16 = 8;
+
+impl TuiHeight {
+    /// Lines to draw in on a terminal `terminal_rows` high, or `None` for
+    /// the alternate screen. The prompt line above always stays visible;
+    /// when that leaves fewer than [`MIN_INLINE_HEIGHT`] lines, the TUI
+    /// takes over the screen instead.
+    pub fn inline_rows(self, terminal_rows: u16) -> Option<u16> {
+        let rows = match self {
+            TuiHeight::Full => return None,
+            TuiHeight::Lines(lines) => lines,
+            TuiHeight::Percent(percent) => {
+                (u32::from(terminal_rows) * u32::from(percent) / 100) as u16
+            }
+        };
+        let available = terminal_rows.saturating_sub(1);
+        (available >= MIN_INLINE_HEIGHT).then(|| rows.clamp(MIN_INLINE_HEIGHT, available))
+    }
+}
+
+impl std::str::FromStr for TuiHeight {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let s = s.trim();
+        if s == "full" {
+            return Ok(TuiHeight::Full);
+        }
+        let invalid = || {
+            format!(
+                "invalid height '{}': use full, a line count or a percentage",
+                s
+            )
+        };
+        match s.strip_suffix('%') {
+            Some(percent) => match percent.parse() {
+                Ok(percent @ 1..=100) => Ok(TuiHeight::Percent(percent)),
+                _ => Err(invalid()),
+            },
+            None => match s.parse() {
+                Ok(lines @ 1..) => Ok(TuiHeight::Lines(lines)),
+                _ => Err(invalid()),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for TuiHeight {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TuiHeight::Full => write!(f, "full"),
+            TuiHeight::Lines(lines) => write!(f, "{}", lines),
+            TuiHeight::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl From<TuiHeight> for String {
+    fn from(height: TuiHeight) -> Self {
+        height.to_string()
+    }
+}
+
+/// `tui.height` as written in the configuration
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TuiHeightSpec {
+    Lines(u16),
+    Text(String),
+}
+
+impl TryFrom<TuiHeightSpec> for TuiHeight {
+    type Error = String;
+
+    fn try_from(spec: TuiHeightSpec) -> std::result::Result<Self, Self::Error> {
+        match spec {
+            TuiHeightSpec::Lines(lines) => lines.to_string().parse(),
+            TuiHeightSpec::Text(text) => text.parse(),
+        }
+    }
+}
+
+/// Configuration for the `zam mcp` server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct McpConfig {
+    /// Tools the server offers (see [`crate::mcp::TOOL_NAMES`])
+    pub tools: Vec<String>,
+
+    /// Hosts whose commands are exposed; `"*"` exposes every host and an
+    /// empty list only this host
+    pub hosts: Vec<String>,
 }
 
 impl Default for Config {
@@ -167,6 +398,8 @@ impl Default for Config {
             search: SearchConfig::default(),
             logging: LoggingConfig::default(),
             shell_integration: ShellIntegrationConfig::default(),
+            mcp: McpConfig::default(),
+            tui: TuiConfig::default(),
             custom_env_vars: vec![
                 "PASSWORD".to_string(),
                 "SECRET".to_string(),
@@ -199,6 +432,9 @@ impl Default for RedactionConfig {
                 "pip".into(),
                 "brew".into(),
             ],
+            use_rule_packs: true,
+            rules_dir: None,
+            sensitive_keys: default_sensitive_keys(),
         }
     }
 }
@@ -252,6 +488,18 @@ impl Default for LoggingConfig {
     }
 }
 
+impl Default for McpConfig {
+    fn default() -> Self {
+        Self {
+            tools: crate::mcp::TOOL_NAMES
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+            hosts: Vec::new(),
+        }
+    }
+}
+
 impl Default for ShellIntegrationConfig {
     fn default() -> Self {
         Self {
@@ -266,6 +514,10 @@ impl Default for ShellIntegrationConfig {
             log_space_prefixed: false,
             log_duplicates: false,
             min_command_length: 1,
+            exclude_globs: Vec::new(),
+            exclude_regexes: Vec::new(),
+            exclude_directories: Vec::new(),
+            alias_hints: false,
         }
     }
 }
@@ -334,6 +586,9 @@ impl Config {
             })?;
         }
 
+        // Validate shell integration exclude globs and regexes
+        crate::ignore::IgnorePolicy::from_config(self)?;
+
         // Validate max entries
         if self.max_entries == 0 {
             return Err(Error::config_validation(
@@ -353,6 +608,41 @@ impl Config {
             }
         }
 
+        // Validate MCP tool names
+        if let Some(tool) = self
+            .mcp
+            .tools
+            .iter()
+            .find(|tool| !crate::mcp::TOOL_NAMES.contains(&tool.as_str()))
+        {
+            let reason = format!(
+                "unknown tool '{}', expected one of: {}",
+                tool,
+                crate::mcp::TOOL_NAMES.join(", ")
+            );
+            return Err(Error::config_validation("mcp.tools", reason.as_str()));
+        }
+
+        // Validate TUI key bindings, colours and columns
+        crate::keymap::Keymap::from_config(&self.tui)?;
+        crate::tui::check_theme(&self.tui.theme)?;
+        if let Some(column) = self
+            .tui
+            .hide_columns
+            .iter()
+            .find(|column| !crate::tui::COLUMN_NAMES.contains(&column.as_str()))
+        {
+            let reason = format!(
+                "unknown column '{}', expected one of: {}",
+                column,
+                crate::tui::COLUMN_NAMES.join(", ")
+            );
+            return Err(Error::config_validation(
+                "tui.hide_columns",
+                reason.as_str(),
+            ));
+        }
+
         // Validate search max results
         if self.search.max_results == 0 {
             return Err(Error::config_validation(
@@ -375,6 +665,8 @@ impl Config {
         self.logging = other.logging.clone();
         self.shell_integration = other.shell_integration.clone();
         self.custom_env_vars = other.custom_env_vars.clone();
+        self.mcp = other.mcp.clone();
+        self.tui = other.tui.clone();
     }
 
     /// Get all redaction patterns (builtin + custom)
@@ -403,26 +695,13 @@ impl Config {
         self.redaction.skip_commands.iter().any(|s| cmd == s)
     }
 
-    /// Check if a command should be excluded from logging
-    pub fn should_exclude_command(&self, command: &str) -> bool {
-        // Check if command is in exclude list
-        for excluded in &self.shell_integration.exclude_commands {
-            if command.starts_with(excluded) {
-                return true;
-            }
-        }
-
-        // Check command length
-        if command.len() < self.shell_integration.min_command_length {
-            return true;
-        }
-
-        // Check if command starts with space and we're configured to exclude those
-        if !self.shell_integration.log_space_prefixed && command.starts_with(' ') {
-            return true;
-        }
-
-        false
+    /// Check if a command should be excluded from logging. This builds the
+    /// [`IgnorePolicy`](crate::ignore::IgnorePolicy) on every call, so
+    /// loggers keep one instead; directory excludes also need its `check`.
+    pub fn should_exclude_command(&self, command: &str) -> Result<bool> {
+        Ok(crate::ignore::IgnorePolicy::from_config(self)?
+            .check(command, "")
+            .is_some())
     }
 }
 
@@ -460,6 +739,36 @@ mod tests {
         config.max_entries = 1000;
         config.logging.level = "invalid".to_string();
         assert!(config.validate().is_err());
+
+        // Test unknown MCP tool
+        config.logging.level = "info".to_string();
+        config.mcp.tools.push("run_command".to_string());
+        assert!(config.validate().is_err());
+
+        // Test TUI keys, colours and columns
+        config.mcp = McpConfig::default();
+        config
+            .tui
+            .keys
+            .insert("yank".to_string(), vec!["alt-c".to_string()]);
+        config
+            .tui
+            .theme
+            .colors
+            .insert("header".to_string(), "#ff8700".to_string());
+        assert!(config.validate().is_ok());
+        config.tui.keys.insert("launch".to_string(), Vec::new());
+        assert!(config.validate().is_err());
+        config.tui.keys.clear();
+        config
+            .tui
+            .theme
+            .colors
+            .insert("border".to_string(), "red".to_string());
+        assert!(config.validate().is_err());
+        config.tui.theme.colors.clear();
+        config.tui.hide_columns.push("command".to_string());
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -491,12 +800,19 @@ mod tests {
         let config = Config::default();
 
         // Should exclude commands in the exclude list
-        assert!(config.should_exclude_command("ls -la"));
-        assert!(config.should_exclude_command("cd /tmp"));
+        assert!(config.should_exclude_command("ls -la").unwrap());
+        assert!(config.should_exclude_command("cd /tmp").unwrap());
 
         // Should not exclude other commands
-        assert!(!config.should_exclude_command("echo hello"));
-        assert!(!config.should_exclude_command("grep pattern file"));
+        assert!(!config.should_exclude_command("echo hello").unwrap());
+        assert!(!config.should_exclude_command("grep pattern file").unwrap());
+
+        // Never log the secret passed to `zam forget`
+        assert!(
+            config
+                .should_exclude_command("zam forget --value hunter2")
+                .unwrap()
+        );
     }
 
     #[test]
@@ -512,6 +828,37 @@ mod tests {
         assert!(patterns.contains(&"custom_pattern".to_string()));
     }
 
+    #[test]
+    fn test_tui_height() {
+        assert_eq!("full".parse(), Ok(TuiHeight::Full));
+        assert_eq!("20".parse(), Ok(TuiHeight::Lines(20)));
+        assert_eq!("40%".parse(), Ok(TuiHeight::Percent(40)));
+        assert!("0".parse::<TuiHeight>().is_err());
+        assert!("120%".parse::<TuiHeight>().is_err());
+        assert!("tall".parse::<TuiHeight>().is_err());
+
+        assert_eq!(TuiHeight::Full.inline_rows(50), None);
+        assert_eq!(TuiHeight::Percent(40).inline_rows(50), Some(20));
+        assert_eq!(TuiHeight::Lines(3).inline_rows(50), Some(MIN_INLINE_HEIGHT));
+        assert_eq!(TuiHeight::Lines(80).inline_rows(50), Some(49));
+        // Small heights are raised to the minimum; a terminal too short for
+        // it gets the alternate screen
+        assert_eq!(
+            TuiHeight::Percent(10).inline_rows(40),
+            Some(MIN_INLINE_HEIGHT)
+        );
+        assert_eq!(TuiHeight::Lines(3).inline_rows(9), Some(MIN_INLINE_HEIGHT));
+        assert_eq!(TuiHeight::Lines(3).inline_rows(8), None);
+        assert_eq!(TuiHeight::Percent(50).inline_rows(1), None);
+
+        let tui: TuiConfig = serde_json::from_str(r#"{ "height": 15 }"#).unwrap();
+        assert_eq!(tui.height, TuiHeight::Lines(15));
+        let tui: TuiConfig = serde_json::from_str(r#"{ "height": "40%" }"#).unwrap();
+        assert_eq!(tui.height, TuiHeight::Percent(40));
+        assert_eq!(serde_json::to_value(tui.height).unwrap(), "40%");
+        assert!(serde_json::from_str::<TuiConfig>(r#"{ "height": "-" }"#).is_err());
+    }
+
     #[test]
     fn test_config_merge() {
         let mut config1 = Config {
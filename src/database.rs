@@ -6,10 +6,18 @@
 //! - Token/password storage for retrieval
 //! - Import from shell history files
 
-use crate::error::Result;
+use crate::agent::{ActorFilter, AgentRun};
+use crate::backend::HistoryQuery;
+use crate::error::{Error, Result};
+use crate::forget::ForgetSummary;
+use crate::private::PrivateMode;
+use crate::redaction::value_fingerprint;
+use crate::session::{ShellProcess, inherited_parent_session, is_process_alive, validate_tag};
 use crate::types::{CommandId, HostId, SessionId};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, Local, NaiveDate, Timelike, Utc};
+use regex::Regex;
 use rusqlite::{Connection, OptionalExtension, params};
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
 use uuid::Uuid;
 
@@ -29,6 +37,207 @@ pub struct Session {
     pub hostname: String,
     pub started_at: DateTime<Utc>,
     pub ended_at: Option<DateTime<Utc>>,
+    /// PID of the shell that owns the session, if known
+    pub pid: Option<u32>,
+    /// Terminal the shell runs on, if known
+    pub tty: Option<String>,
+    /// Human-readable name given with `zam sessions --name`
+    pub name: Option<String>,
+    /// Free-form note
+    pub note: Option<String>,
+    /// Tags, sorted
+    pub tags: Vec<String>,
+    /// Session this one replays (`zam replay`)
+    pub replay_of: Option<String>,
+    /// Session this one was started from (nested shell, tmux pane, agent)
+    pub parent_id: Option<String>,
+}
+
+impl Session {
+    /// The session name if it has one, otherwise its ID
+    pub fn label(&self) -> &str {
+        self.name.as_deref().unwrap_or(self.id.as_str())
+    }
+}
+
+/// Criteria for [`Database::find_sessions`]. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    /// Glob (`*`, `?`) matched case-insensitively against the name or ID
+    pub name: Option<String>,
+    /// Tag the session must carry
+    pub tag: Option<String>,
+    /// Host the session ran on
+    pub host_id: Option<HostId>,
+    /// Only sessions started at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only sessions started at or before this time
+    pub before: Option<DateTime<Utc>>,
+    /// Only sessions that have not ended
+    pub active_only: bool,
+}
+
+/// Criteria for [`Database::find_commands`]. Unset fields match everything.
+#[derive(Debug, Clone, Default)]
+pub struct CommandFilter {
+    /// Substring the command must contain
+    pub text: Option<String>,
+    /// Directory the command ran in (exact match)
+    pub directory: Option<String>,
+    /// Session the command belongs to
+    pub session_id: Option<String>,
+    /// Hostnames the command's session ran on
+    pub hostnames: Option<Vec<String>>,
+    /// Only commands run at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Maximum number of commands to return
+    pub limit: Option<usize>,
+}
+
+/// Fewest runs (with a known exit code) before a command is ranked by its
+/// failure ratio
+pub const MIN_RUNS_FOR_FAILURE_RATIO: usize = 3;
+
+/// Command usage over time, as shown on the TUI stats tab. Days and hours
+/// are in local time; runs count collapsed repeats.
+#[derive(Debug, Clone, Default)]
+pub struct UsageStats {
+    pub total_runs: usize,
+    /// Runs per day, oldest first, with empty days included
+    pub per_day: Vec<(NaiveDate, usize)>,
+    /// Runs per week, keyed by the week's Monday, oldest first
+    pub per_week: Vec<(NaiveDate, usize)>,
+    /// Runs by weekday (Monday first) and hour of day
+    pub by_hour: [[usize; 24]; 7],
+    /// Directories with the most runs, busiest first
+    pub top_directories: Vec<(String, usize)>,
+    /// Commands that fail most often: (command, failures, runs with a known
+    /// exit code), highest failure ratio first
+    pub failing: Vec<(String, usize, usize)>,
+    /// Stored redactions per token type, most first
+    pub redactions: Vec<(String, usize)>,
+}
+
+/// Minimum time between opportunistic stale-session reaps
+const REAP_INTERVAL_SECS: i64 = 600;
+
+/// Columns selected for a [`Session`], in [`session_from_row`] order.
+/// Select them `FROM` [`SESSION_TABLES`].
+const SESSION_COLUMNS: &str = "s.id, s.host_id, COALESCE(h.hostname, '?'), s.started_at, \
+     s.ended_at, s.pid, s.tty, m.name, m.note, \
+     (SELECT group_concat(tag, ',') FROM \
+         (SELECT tag FROM session_tags WHERE session_id = s.id ORDER BY tag)), \
+     s.replay_of, s.parent_session_id";
+
+/// Sessions joined with their host and metadata
+const SESSION_TABLES: &str = "sessions s
+     LEFT JOIN hosts h ON s.host_id = h.id
+     LEFT JOIN session_metadata m ON m.session_id = s.id";
+
+/// `WHERE` condition matching the free-text session filter bound to `?n`
+/// against the ID, hostname, name, note and tags
+fn session_text_condition(n: usize) -> String {
+    format!(
+        "s.id LIKE ?{n} OR h.hostname LIKE ?{n} OR m.name LIKE ?{n} OR m.note LIKE ?{n}
+         OR EXISTS (SELECT 1 FROM session_tags t WHERE t.session_id = s.id AND t.tag LIKE ?{n})"
+    )
+}
+
+/// Translate a shell-style glob into a pattern for `LIKE ... ESCAPE '\\'`
+fn glob_to_like(glob: &str) -> String {
+    let mut like = String::with_capacity(glob.len());
+    for c in glob.chars() {
+        match c {
+            '*' => like.push('%'),
+            '?' => like.push('_'),
+            '%' | '_' | '\\' => {
+                like.push('\\');
+                like.push(c);
+            }
+            c => like.push(c),
+        }
+    }
+    like
+}
+
+/// Escape `text` for a substring match with `LIKE ... ESCAPE '\\'`, so
+/// `%` and `_` in it match themselves
+fn escape_like(text: &str) -> String {
+    let mut like = String::with_capacity(text.len() + 2);
+    like.push('%');
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            like.push('\\');
+        }
+        like.push(c);
+    }
+    like.push('%');
+    like
+}
+
+fn session_from_row(row: &rusqlite::Row) -> rusqlite::Result<Session> {
+    Ok(Session {
+        id: SessionId::new(row.get(0)?),
+        host_id: HostId::new(row.get(1)?),
+        hostname: row.get(2)?,
+        started_at: row
+            .get::<_, String>(3)?
+            .parse()
+            .unwrap_or_else(|_| Utc::now()),
+        ended_at: row
+            .get::<_, Option<String>>(4)?
+            .and_then(|s| s.parse().ok()),
+        pid: row.get(5)?,
+        tty: row.get(6)?,
+        name: row.get(7)?,
+        note: row.get(8)?,
+        tags: row
+            .get::<_, Option<String>>(9)?
+            .map(|t| t.split(',').map(String::from).collect())
+            .unwrap_or_default(),
+        replay_of: row.get(10)?,
+        parent_id: row.get(11)?,
+    })
+}
+
+/// WHERE clause and parameters of a [`CommandFilter`], over `commands c`
+/// joined with `sessions s` and `hosts h`
+fn command_filter_sql(filter: &CommandFilter) -> (String, Vec<Box<dyn rusqlite::types::ToSql>>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+    if let Some(ref text) = filter.text {
+        values.push(Box::new(escape_like(text)));
+        conditions.push(format!("c.command LIKE ?{} ESCAPE '\\'", values.len()));
+    }
+    if let Some(ref directory) = filter.directory {
+        values.push(Box::new(directory.clone()));
+        conditions.push(format!("c.directory = ?{}", values.len()));
+    }
+    if let Some(ref session_id) = filter.session_id {
+        values.push(Box::new(session_id.clone()));
+        conditions.push(format!("c.session_id = ?{}", values.len()));
+    }
+    if let Some(ref hostnames) = filter.hostnames {
+        let mut placeholders = Vec::new();
+        for hostname in hostnames {
+            values.push(Box::new(hostname.clone()));
+            placeholders.push(format!("?{}", values.len()));
+        }
+        conditions.push(format!("h.hostname IN ({})", placeholders.join(", ")));
+    }
+    if let Some(since) = filter.since {
+        values.push(Box::new(since.to_rfc3339()));
+        conditions.push(format!("c.timestamp >= ?{}", values.len()));
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+
+    (where_clause, values)
 }
 
 /// Represents a command entry in the database
@@ -54,6 +263,24 @@ pub struct Token {
     pub created_at: DateTime<Utc>,
 }
 
+/// Token counts for one (type, pattern, host, month) group, used by `zam audit`
+#[derive(Debug, Clone)]
+pub struct TokenAuditRow {
+    pub token_type: String,
+    pub pattern: Option<String>,
+    pub hostname: String,
+    pub month: String,
+    pub count: usize,
+}
+
+/// A token value that was captured in more than one command
+#[derive(Debug, Clone)]
+pub struct ReusedToken {
+    pub token_type: String,
+    pub original_value: String,
+    pub command_ids: Vec<i64>,
+}
+
 /// Represents a shell alias
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct Alias {
@@ -62,6 +289,9 @@ pub struct Alias {
     pub description: String,
     pub date_created: DateTime<Utc>,
     pub date_updated: DateTime<Utc>,
+    /// Logged commands that invoked the alias
+    pub use_count: usize,
+    pub last_used: Option<DateTime<Utc>>,
 }
 
 /// Represents a secret key loaded into a session (value is NOT stored)
@@ -117,6 +347,7 @@ impl Database {
         };
 
         db.initialize_schema()?;
+        db.migrate_schema()?;
         db.ensure_current_host()?;
 
         Ok(db)
@@ -245,6 +476,106 @@ impl Database {
             [],
         )?;
 
+        // Redaction allowlist - fingerprints of values marked as not-a-secret
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS redaction_allowlist (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                value_hash TEXT NOT NULL UNIQUE,
+                token_type TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Bring databases created by older versions up to date. Columns are
+    /// added in place so existing rows are preserved.
+    fn migrate_schema(&self) -> Result<()> {
+        // Rule, detector or pattern that found a token (NULL for older rows)
+        self.add_column_if_missing("tokens", "pattern", "TEXT")?;
+
+        // Number of consecutive runs collapsed into a command row
+        self.add_column_if_missing("commands", "repeat_count", "INTEGER NOT NULL DEFAULT 1")?;
+
+        // Shell process owning a session, used to reap dead sessions
+        self.add_column_if_missing("sessions", "pid", "INTEGER")?;
+        self.add_column_if_missing("sessions", "tty", "TEXT")?;
+
+        // Private mode of a session ("drop" or "ephemeral", NULL when off)
+        self.add_column_if_missing("sessions", "private_mode", "TEXT")?;
+
+        // Original session of a `zam replay` session
+        self.add_column_if_missing("sessions", "replay_of", "TEXT")?;
+
+        // Agent that ran a command (NULL for commands typed by the user)
+        self.add_column_if_missing("commands", "actor", "TEXT")?;
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS agent_runs (
+                command_id INTEGER PRIMARY KEY,
+                tool_name TEXT,
+                stdout_bytes INTEGER,
+                stderr_bytes INTEGER,
+                duration_ms INTEGER,
+                FOREIGN KEY (command_id) REFERENCES commands(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+
+        // Session a nested shell or agent sub-task was started from
+        self.add_column_if_missing("sessions", "parent_session_id", "TEXT")?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_sessions_parent ON sessions(parent_session_id)",
+            [],
+        )?;
+
+        // How often and when an alias was last invoked
+        self.add_column_if_missing("aliases", "use_count", "INTEGER NOT NULL DEFAULT 0")?;
+        self.add_column_if_missing("aliases", "last_used", "TEXT")?;
+
+        // Session names, notes and tags
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_metadata (
+                session_id TEXT PRIMARY KEY,
+                name TEXT,
+                note TEXT,
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS session_tags (
+                session_id TEXT NOT NULL,
+                tag TEXT NOT NULL,
+                PRIMARY KEY (session_id, tag),
+                FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+            )",
+            [],
+        )?;
+        self.conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_session_tags_tag ON session_tags(tag)",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    /// Add a column to a table unless it already exists
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM pragma_table_info(?1) WHERE name = ?2)",
+            params![table, column],
+            |row| row.get(0),
+        )?;
+
+        if !exists {
+            self.conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
+
         Ok(())
     }
 
@@ -310,6 +641,11 @@ impl Database {
 
     /// Get or create a session for the current shell
     pub fn ensure_session(&mut self) -> Result<String> {
+        // Opportunistic and best effort: logging must never fail because of it
+        if let Err(e) = self.maybe_reap_stale_sessions() {
+            tracing::debug!("Skipping stale session reap: {}", e);
+        }
+
         if let Some(ref session_id) = self.current_session_id {
             Ok(session_id.as_str().to_string())
         } else {
@@ -329,9 +665,18 @@ impl Database {
 
         if !exists {
             let now = Utc::now().to_rfc3339();
+            let shell = ShellProcess::for_session(session_id);
             self.conn.execute(
-                "INSERT INTO sessions (id, host_id, started_at) VALUES (?1, ?2, ?3)",
-                params![session_id, self.current_host_id.as_i64(), now],
+                "INSERT INTO sessions (id, host_id, started_at, pid, tty, parent_session_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    session_id,
+                    self.current_host_id.as_i64(),
+                    now,
+                    shell.as_ref().map(|s| s.pid),
+                    shell.and_then(|s| s.tty),
+                    inherited_parent_session(session_id)
+                ],
             )?;
         }
 
@@ -339,6 +684,434 @@ impl Database {
         Ok(())
     }
 
+    /// Turn private mode on or off for a session, creating the session if
+    /// it has not logged anything yet
+    pub fn set_session_private(
+        &mut self,
+        session_id: &str,
+        mode: Option<PrivateMode>,
+    ) -> Result<()> {
+        self.resume_session(session_id)?;
+        self.conn.execute(
+            "UPDATE sessions SET private_mode = ?1 WHERE id = ?2",
+            params![mode.map(PrivateMode::as_str), session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Get the private mode of a session (`None` when off or unknown)
+    pub fn get_session_private(&self, session_id: &str) -> Result<Option<PrivateMode>> {
+        let mode: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT private_mode FROM sessions WHERE id = ?1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten();
+        Ok(mode.as_deref().and_then(PrivateMode::parse))
+    }
+
+    /// Record that `session_id` replays `original`
+    pub fn set_session_replay_of(&self, session_id: &str, original: &str) -> Result<()> {
+        self.conn.execute(
+            "UPDATE sessions SET replay_of = ?1 WHERE id = ?2",
+            params![original, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// Make `parent` the parent of `session_id`. Fails if that would put a
+    /// session inside its own subtree.
+    pub fn set_session_parent(&self, session_id: &str, parent: &str) -> Result<()> {
+        if parent == session_id
+            || self
+                .get_session_descendants(session_id)?
+                .iter()
+                .any(|d| d == parent)
+        {
+            return Err(Error::invalid_arguments(format!(
+                "Session {} cannot be the parent of {}: it is the session itself or one of its descendants",
+                parent, session_id
+            )));
+        }
+        self.conn.execute(
+            "UPDATE sessions SET parent_session_id = ?1 WHERE id = ?2",
+            params![parent, session_id],
+        )?;
+        Ok(())
+    }
+
+    /// IDs of all sessions below `session_id` in the session tree, oldest
+    /// first
+    pub fn get_session_descendants(&self, session_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "WITH RECURSIVE tree(id) AS (
+                 SELECT id FROM sessions WHERE parent_session_id = ?1
+                 UNION
+                 SELECT s.id FROM sessions s JOIN tree ON s.parent_session_id = tree.id
+             )
+             SELECT s.id FROM sessions s JOIN tree ON s.id = tree.id
+             WHERE s.id != ?1
+             ORDER BY s.started_at",
+        )?;
+        let ids = stmt
+            .query_map([session_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(ids)
+    }
+
+    /// Commands of a session and all its descendants, newest first
+    pub fn get_commands_for_session_tree(&self, session_id: &str) -> Result<Vec<CommandEntry>> {
+        let mut commands = self.get_commands_for_session(session_id)?;
+        for descendant in self.get_session_descendants(session_id)? {
+            commands.extend(self.get_commands_for_session(&descendant)?);
+        }
+        commands.sort_by_key(|c| std::cmp::Reverse(c.timestamp));
+        Ok(commands)
+    }
+
+    /// Whether a session with this ID exists
+    pub fn session_exists(&self, session_id: &str) -> Result<bool> {
+        Ok(self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ?1)",
+            [session_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Get a session by ID
+    pub fn get_session(&self, session_id: &str) -> Result<Option<Session>> {
+        Ok(self
+            .conn
+            .query_row(
+                &format!("SELECT {SESSION_COLUMNS} FROM {SESSION_TABLES} WHERE s.id = ?1"),
+                [session_id],
+                session_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Set or clear (`None` or empty) the name of a session
+    pub fn set_session_name(&self, session_id: &str, name: Option<&str>) -> Result<()> {
+        let name = name.map(str::trim).filter(|n| !n.is_empty());
+        self.conn.execute(
+            "INSERT INTO session_metadata (session_id, name) VALUES (?1, ?2)
+             ON CONFLICT(session_id) DO UPDATE SET name = excluded.name",
+            params![session_id, name],
+        )?;
+        Ok(())
+    }
+
+    /// Set or clear (`None` or empty) the note of a session
+    pub fn set_session_note(&self, session_id: &str, note: Option<&str>) -> Result<()> {
+        let note = note.map(str::trim).filter(|n| !n.is_empty());
+        self.conn.execute(
+            "INSERT INTO session_metadata (session_id, note) VALUES (?1, ?2)
+             ON CONFLICT(session_id) DO UPDATE SET note = excluded.note",
+            params![session_id, note],
+        )?;
+        Ok(())
+    }
+
+    /// Add tags to a session. Tags already present are kept once.
+    pub fn add_session_tags(&mut self, session_id: &str, tags: &[String]) -> Result<()> {
+        for tag in tags {
+            validate_tag(tag)?;
+        }
+        let tx = self.conn.transaction()?;
+        for tag in tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO session_tags (session_id, tag) VALUES (?1, ?2)",
+                params![session_id, tag],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Remove tags from a session. Returns how many were removed.
+    pub fn remove_session_tags(&self, session_id: &str, tags: &[String]) -> Result<usize> {
+        let mut removed = 0;
+        for tag in tags {
+            removed += self.conn.execute(
+                "DELETE FROM session_tags WHERE session_id = ?1 AND tag = ?2",
+                params![session_id, tag],
+            )?;
+        }
+        Ok(removed)
+    }
+
+    /// Sessions matching a filter, most recent first
+    pub fn find_sessions(&self, filter: &SessionFilter) -> Result<Vec<Session>> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<Box<dyn rusqlite::types::ToSql>> = Vec::new();
+
+        if let Some(ref name) = filter.name {
+            values.push(Box::new(glob_to_like(name)));
+            let n = values.len();
+            conditions.push(format!(
+                "(m.name LIKE ?{n} ESCAPE '\\' OR s.id LIKE ?{n} ESCAPE '\\')"
+            ));
+        }
+        if let Some(ref tag) = filter.tag {
+            values.push(Box::new(tag.clone()));
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM session_tags t WHERE t.session_id = s.id AND t.tag = ?{})",
+                values.len()
+            ));
+        }
+        if let Some(host_id) = filter.host_id {
+            values.push(Box::new(host_id.as_i64()));
+            conditions.push(format!("s.host_id = ?{}", values.len()));
+        }
+        if let Some(since) = filter.since {
+            values.push(Box::new(since.to_rfc3339()));
+            conditions.push(format!("s.started_at >= ?{}", values.len()));
+        }
+        if let Some(before) = filter.before {
+            values.push(Box::new(before.to_rfc3339()));
+            conditions.push(format!("s.started_at <= ?{}", values.len()));
+        }
+        if filter.active_only {
+            conditions.push("s.ended_at IS NULL".to_string());
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS}
+             FROM {SESSION_TABLES}
+             {where_clause}
+             ORDER BY s.started_at DESC"
+        ))?;
+        let params_ref: Vec<&dyn rusqlite::types::ToSql> =
+            values.iter().map(|p| p.as_ref()).collect();
+        let sessions = stmt
+            .query_map(params_ref.as_slice(), session_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(sessions)
+    }
+
+    /// Find commands matching `filter`, most recent first, with the
+    /// hostname of the session each one ran in
+    pub fn find_commands(&self, filter: &CommandFilter) -> Result<Vec<(CommandEntry, String)>> {
+        let (where_clause, values) = command_filter_sql(filter);
+        let limit_clause = filter
+            .limit
+            .map(|limit| format!("LIMIT {limit}"))
+            .unwrap_or_default();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT c.id, c.session_id, c.command, c.timestamp, c.directory, c.redacted,
+                    c.exit_code, COALESCE(h.hostname, '?')
+             FROM commands c
+             LEFT JOIN sessions s ON c.session_id = s.id
+             LEFT JOIN hosts h ON s.host_id = h.id
+             {where_clause}
+             ORDER BY c.timestamp DESC, c.id DESC
+             {limit_clause}"
+        ))?;
+        let params_ref: Vec<&dyn rusqlite::types::ToSql> =
+            values.iter().map(|p| p.as_ref()).collect();
+        let commands = stmt
+            .query_map(params_ref.as_slice(), |row| {
+                Ok((
+                    CommandEntry {
+                        id: row.get(0)?,
+                        session_id: row.get(1)?,
+                        command: row.get(2)?,
+                        timestamp: row
+                            .get::<_, String>(3)?
+                            .parse()
+                            .unwrap_or_else(|_| Utc::now()),
+                        directory: row.get(4)?,
+                        redacted: row.get::<_, i32>(5)? != 0,
+                        exit_code: row.get(6)?,
+                    },
+                    row.get(7)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(commands)
+    }
+
+    /// Usage of the commands matching `filter`, for the TUI stats tab.
+    /// `limit` is ignored; `top` bounds each ranking.
+    pub fn usage_stats(&self, filter: &CommandFilter, top: usize) -> Result<UsageStats> {
+        let (where_clause, values) = command_filter_sql(filter);
+        let params_ref: Vec<&dyn rusqlite::types::ToSql> =
+            values.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT c.timestamp, c.directory, c.command, c.exit_code, c.repeat_count
+             FROM commands c
+             LEFT JOIN sessions s ON c.session_id = s.id
+             LEFT JOIN hosts h ON s.host_id = h.id
+             {where_clause}"
+        ))?;
+        let rows = stmt.query_map(params_ref.as_slice(), |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<i32>>(3)?,
+                row.get::<_, i64>(4)?,
+            ))
+        })?;
+
+        let mut stats = UsageStats::default();
+        let mut days: HashMap<NaiveDate, usize> = HashMap::new();
+        let mut directories: HashMap<String, usize> = HashMap::new();
+        let mut outcomes: HashMap<String, (usize, usize)> = HashMap::new();
+        for row in rows {
+            let (timestamp, directory, command, exit_code, runs) = row?;
+            let Ok(timestamp) = timestamp.parse::<DateTime<Utc>>() else {
+                continue;
+            };
+            let runs = runs.max(1) as usize;
+            let local = timestamp.with_timezone(&Local);
+            stats.total_runs += runs;
+            *days.entry(local.date_naive()).or_default() += runs;
+            stats.by_hour[local.weekday().num_days_from_monday() as usize]
+                [local.hour() as usize] += runs;
+            *directories.entry(directory).or_default() += runs;
+            if let Some(code) = exit_code {
+                let outcome = outcomes.entry(command).or_default();
+                outcome.1 += runs;
+                if code != 0 {
+                    outcome.0 += runs;
+                }
+            }
+        }
+
+        // Every day from the start of the window (or the first command) to today
+        let today = Local::now().date_naive();
+        let first = filter
+            .since
+            .map(|since| since.with_timezone(&Local).date_naive())
+            .or_else(|| days.keys().min().copied());
+        if let Some(first) = first {
+            stats.per_day = first
+                .iter_days()
+                .take_while(|day| *day <= today)
+                .map(|day| (day, days.get(&day).copied().unwrap_or(0)))
+                .collect();
+        }
+        for (day, runs) in &stats.per_day {
+            let monday = *day - chrono::Duration::days(day.weekday().num_days_from_monday() as i64);
+            match stats.per_week.last_mut() {
+                Some((week, total)) if *week == monday => *total += runs,
+                _ => stats.per_week.push((monday, *runs)),
+            }
+        }
+
+        let mut directories: Vec<(String, usize)> = directories.into_iter().collect();
+        directories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        directories.truncate(top);
+        stats.top_directories = directories;
+
+        let mut failing: Vec<(String, usize, usize)> = outcomes
+            .into_iter()
+            .filter(|(_, (failures, runs))| *failures > 0 && *runs >= MIN_RUNS_FOR_FAILURE_RATIO)
+            .map(|(command, (failures, runs))| (command, failures, runs))
+            .collect();
+        // Highest failures/runs first, compared without floating point
+        failing.sort_by(|a, b| {
+            (b.1 * a.2)
+                .cmp(&(a.1 * b.2))
+                .then_with(|| b.1.cmp(&a.1))
+                .then_with(|| a.0.cmp(&b.0))
+        });
+        failing.truncate(top);
+        stats.failing = failing;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT t.token_type, COUNT(*)
+             FROM tokens t
+             JOIN commands c ON t.command_id = c.id
+             LEFT JOIN sessions s ON c.session_id = s.id
+             LEFT JOIN hosts h ON s.host_id = h.id
+             {where_clause}
+             GROUP BY t.token_type
+             ORDER BY COUNT(*) DESC, t.token_type
+             LIMIT {top}"
+        ))?;
+        stats.redactions = stmt
+            .query_map(params_ref.as_slice(), |row| {
+                Ok((row.get(0)?, row.get::<_, i64>(1)? as usize))
+            })?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(stats)
+    }
+
+    /// Close open sessions on this host whose shell process is gone. Their
+    /// `ended_at` is set to the time of their last command (or their start
+    /// time if they never logged one). Returns the IDs of reaped sessions.
+    pub fn reap_stale_sessions(&self) -> Result<Vec<String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, pid FROM sessions
+             WHERE ended_at IS NULL AND pid IS NOT NULL AND host_id = ?1
+                 AND id IS NOT ?2",
+        )?;
+        // The session being logged to is alive by definition
+        let current = self.current_session_id.as_deref();
+        let candidates = stmt
+            .query_map(params![self.current_host_id.as_i64(), current], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, u32>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut reaped = Vec::new();
+        for (id, pid) in candidates {
+            if is_process_alive(pid) {
+                continue;
+            }
+            self.conn.execute(
+                "UPDATE sessions SET ended_at = COALESCE(
+                     (SELECT MAX(timestamp) FROM commands WHERE session_id = ?1),
+                     started_at)
+                 WHERE id = ?1",
+                [&id],
+            )?;
+            reaped.push(id);
+        }
+
+        Ok(reaped)
+    }
+
+    /// Run [`reap_stale_sessions`](Self::reap_stale_sessions) at most once
+    /// per [`REAP_INTERVAL_SECS`]
+    pub fn maybe_reap_stale_sessions(&self) -> Result<()> {
+        let now = Utc::now();
+        let due = self
+            .get_preference("last_reap_at")?
+            .and_then(|t| t.parse::<DateTime<Utc>>().ok())
+            .is_none_or(|last| (now - last).num_seconds() >= REAP_INTERVAL_SECS);
+        if due {
+            self.set_preference("last_reap_at", &now.to_rfc3339())?;
+            self.reap_stale_sessions()?;
+        }
+        Ok(())
+    }
+
+    /// Count open and closed sessions
+    pub fn count_sessions_by_state(&self) -> Result<(usize, usize)> {
+        let (open, closed): (i64, i64) = self.conn.query_row(
+            "SELECT COUNT(*) FILTER (WHERE ended_at IS NULL),
+                    COUNT(*) FILTER (WHERE ended_at IS NOT NULL)
+             FROM sessions",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((open as usize, closed as usize))
+    }
+
     /// Add a command to the database
     pub fn add_command(
         &mut self,
@@ -367,20 +1140,150 @@ impl Database {
         Ok(self.conn.last_insert_rowid())
     }
 
-    /// Store a redacted token for later retrieval
-    pub fn store_token(
-        &self,
-        command_id: i64,
-        token_type: &str,
-        placeholder: &str,
-        original_value: &str,
-    ) -> Result<i64> {
-        let now = Utc::now().to_rfc3339();
-
+    /// Record the agent that ran a command, with what its hook reported
+    pub fn store_agent_run(&self, command_id: i64, run: &AgentRun) -> Result<()> {
+        self.conn.execute(
+            "UPDATE commands SET actor = ?1 WHERE id = ?2",
+            params![run.actor, command_id],
+        )?;
         self.conn.execute(
-            "INSERT INTO tokens (command_id, token_type, placeholder, original_value, created_at)
+            "INSERT OR REPLACE INTO agent_runs
+                 (command_id, tool_name, stdout_bytes, stderr_bytes, duration_ms)
              VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![command_id, token_type, placeholder, original_value, now],
+            params![
+                command_id,
+                run.tool_name,
+                run.stdout_bytes.map(|n| n as i64),
+                run.stderr_bytes.map(|n| n as i64),
+                run.duration_ms.map(|n| n as i64)
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Agent that ran a command (`None` for commands typed by the user)
+    pub fn get_command_actor(&self, id: CommandId) -> Result<Option<String>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT actor FROM commands WHERE id = ?1",
+                [id.as_i64()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .flatten())
+    }
+
+    /// All commands run by the matching actors, oldest first
+    pub fn get_commands_by_actor(&self, actor: &ActorFilter) -> Result<Vec<CommandEntry>> {
+        let (condition, value) = actor.sql_condition(1);
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, session_id, command, timestamp, directory, redacted, exit_code
+             FROM commands
+             WHERE {condition}
+             ORDER BY timestamp ASC"
+        ))?;
+
+        let params: Vec<&dyn rusqlite::types::ToSql> = value
+            .as_ref()
+            .map(|v| vec![v as &dyn rusqlite::types::ToSql])
+            .unwrap_or_default();
+        let commands = stmt
+            .query_map(params.as_slice(), |row| {
+                Ok(CommandEntry {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    command: row.get(2)?,
+                    timestamp: row
+                        .get::<_, String>(3)?
+                        .parse()
+                        .unwrap_or_else(|_| Utc::now()),
+                    directory: row.get(4)?,
+                    redacted: row.get::<_, i32>(5)? != 0,
+                    exit_code: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(commands)
+    }
+
+    /// Collapse a consecutive duplicate: if the last command of the current
+    /// session has the same text and directory and carries no tokens, bump
+    /// its repeat counter and refresh its timestamp and exit code. Returns
+    /// the collapsed row's ID, or `None` if the command must be inserted.
+    pub fn collapse_duplicate(
+        &mut self,
+        command: &str,
+        directory: &str,
+        timestamp: DateTime<Utc>,
+        exit_code: Option<i32>,
+    ) -> Result<Option<i64>> {
+        let session_id = self.ensure_session()?;
+
+        let last: Option<(i64, String, String, bool)> = self
+            .conn
+            .query_row(
+                "SELECT id, command, directory,
+                     EXISTS(SELECT 1 FROM tokens WHERE command_id = commands.id)
+                 FROM commands WHERE session_id = ?1
+                 ORDER BY id DESC LIMIT 1",
+                [&session_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()?;
+
+        match last {
+            Some((id, last_command, last_directory, has_tokens))
+                if last_command == command && last_directory == directory && !has_tokens =>
+            {
+                self.conn.execute(
+                    "UPDATE commands SET repeat_count = repeat_count + 1,
+                         timestamp = ?1, exit_code = ?2
+                     WHERE id = ?3",
+                    params![timestamp.to_rfc3339(), exit_code, id],
+                )?;
+                Ok(Some(id))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Number of consecutive runs collapsed into a command row
+    pub fn get_repeat_count(&self, id: CommandId) -> Result<usize> {
+        let count: i64 = self.conn.query_row(
+            "SELECT repeat_count FROM commands WHERE id = ?1",
+            [id.as_i64()],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// How many times a command was run, counting collapsed repeats
+    pub fn count_command_runs(&self, command: &str) -> Result<usize> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COALESCE(SUM(repeat_count), 0) FROM commands WHERE command = ?1",
+            [command],
+            |row| row.get(0),
+        )?;
+        Ok(count as usize)
+    }
+
+    /// Store a redacted token for later retrieval
+    pub fn store_token(
+        &self,
+        command_id: i64,
+        token_type: &str,
+        placeholder: &str,
+        original_value: &str,
+        pattern: &str,
+    ) -> Result<i64> {
+        let now = Utc::now().to_rfc3339();
+
+        self.conn.execute(
+            "INSERT INTO tokens (command_id, token_type, placeholder, original_value, created_at, pattern)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![command_id, token_type, placeholder, original_value, now, pattern],
         )?;
 
         Ok(self.conn.last_insert_rowid())
@@ -626,7 +1529,7 @@ impl Database {
         offset: usize,
         limit: usize,
     ) -> Result<Vec<CommandEntry>> {
-        self.get_unique_commands_filtered(offset, limit, None)
+        self.get_unique_commands_filtered(offset, limit, None, &ActorFilter::All)
     }
 
     /// Get unique commands with optional filter, paginated
@@ -635,21 +1538,28 @@ impl Database {
         offset: usize,
         limit: usize,
         filter: Option<&str>,
+        actor: &ActorFilter,
     ) -> Result<Vec<CommandEntry>> {
-        let (where_clause, params): (&str, Vec<Box<dyn rusqlite::types::ToSql>>) = match filter {
-            Some(f) if !f.is_empty() => (
-                "WHERE directory != '<imported>' AND command LIKE ?3",
-                vec![
-                    Box::new(limit as i64),
-                    Box::new(offset as i64),
-                    Box::new(format!("%{}%", f)),
-                ],
-            ),
-            _ => (
-                "WHERE directory != '<imported>'",
-                vec![Box::new(limit as i64), Box::new(offset as i64)],
-            ),
-        };
+        let (mut where_clause, mut params): (String, Vec<Box<dyn rusqlite::types::ToSql>>) =
+            match filter {
+                Some(f) if !f.is_empty() => (
+                    "WHERE directory != '<imported>' AND command LIKE ?3".to_string(),
+                    vec![
+                        Box::new(limit as i64),
+                        Box::new(offset as i64),
+                        Box::new(format!("%{}%", f)),
+                    ],
+                ),
+                _ => (
+                    "WHERE directory != '<imported>'".to_string(),
+                    vec![Box::new(limit as i64), Box::new(offset as i64)],
+                ),
+            };
+        let (condition, value) = actor.sql_condition(params.len() + 1);
+        where_clause.push_str(&format!(" AND {condition}"));
+        if let Some(value) = value {
+            params.push(Box::new(value));
+        }
 
         let sql = format!(
             "SELECT id, session_id, command, timestamp, directory, redacted, exit_code
@@ -687,17 +1597,28 @@ impl Database {
 
     /// Count unique (command, directory) pairs excluding imported
     pub fn count_unique_commands(&self) -> Result<usize> {
-        self.count_unique_commands_filtered(None)
+        self.count_unique_commands_filtered(None, &ActorFilter::All)
     }
 
     /// Count unique commands with optional filter
-    pub fn count_unique_commands_filtered(&self, filter: Option<&str>) -> Result<usize> {
-        let (where_extra, params): (&str, Vec<Box<dyn rusqlite::types::ToSql>>) = match filter {
-            Some(f) if !f.is_empty() => {
-                (" AND command LIKE ?1", vec![Box::new(format!("%{}%", f))])
-            }
-            _ => ("", vec![]),
-        };
+    pub fn count_unique_commands_filtered(
+        &self,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<usize> {
+        let (mut where_extra, mut params): (String, Vec<Box<dyn rusqlite::types::ToSql>>) =
+            match filter {
+                Some(f) if !f.is_empty() => (
+                    " AND command LIKE ?1".to_string(),
+                    vec![Box::new(format!("%{}%", f))],
+                ),
+                _ => (String::new(), vec![]),
+            };
+        let (condition, value) = actor.sql_condition(params.len() + 1);
+        where_extra.push_str(&format!(" AND {condition}"));
+        if let Some(value) = value {
+            params.push(Box::new(value));
+        }
 
         let sql = format!(
             "SELECT COUNT(*) FROM (
@@ -807,7 +1728,7 @@ impl Database {
     /// Get the most frequently used unique commands globally
     pub fn get_frequent_commands(&self, limit: usize) -> Result<Vec<(String, usize)>> {
         let mut stmt = self.conn.prepare(
-            "SELECT command, COUNT(*) as cnt
+            "SELECT command, SUM(repeat_count) as cnt
              FROM commands
              WHERE directory != '<imported>'
              GROUP BY command
@@ -911,29 +1832,15 @@ impl Database {
 
     /// Get sessions for a host
     pub fn get_sessions_for_host(&self, host_id: HostId) -> Result<Vec<Session>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT s.id, s.host_id, COALESCE(h.hostname, '?'), s.started_at, s.ended_at
-             FROM sessions s
-             LEFT JOIN hosts h ON s.host_id = h.id
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS}
+             FROM {SESSION_TABLES}
              WHERE s.host_id = ?1
-             ORDER BY s.started_at DESC",
-        )?;
+             ORDER BY s.started_at DESC"
+        ))?;
 
         let sessions = stmt
-            .query_map(params![host_id.as_i64()], |row| {
-                Ok(Session {
-                    id: SessionId::new(row.get(0)?),
-                    host_id: HostId::new(row.get(1)?),
-                    hostname: row.get(2)?,
-                    started_at: row
-                        .get::<_, String>(3)?
-                        .parse()
-                        .unwrap_or_else(|_| Utc::now()),
-                    ended_at: row
-                        .get::<_, Option<String>>(4)?
-                        .and_then(|s| s.parse().ok()),
-                })
-            })?
+            .query_map(params![host_id.as_i64()], session_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(sessions)
@@ -1065,7 +1972,7 @@ impl Database {
     #[must_use = "Alias list should be used"]
     pub fn list_aliases(&self) -> Result<Vec<Alias>> {
         let mut stmt = self.conn.prepare(
-            "SELECT alias, command, description, date_created, date_updated
+            "SELECT alias, command, description, date_created, date_updated, use_count, last_used
              FROM aliases ORDER BY alias ASC",
         )?;
 
@@ -1083,6 +1990,10 @@ impl Database {
                         .get::<_, String>(4)?
                         .parse()
                         .unwrap_or_else(|_| Utc::now()),
+                    use_count: row.get::<_, i64>(5)? as usize,
+                    last_used: row
+                        .get::<_, Option<String>>(6)?
+                        .and_then(|t| t.parse().ok()),
                 })
             })?
             .collect::<rusqlite::Result<Vec<_>>>()?;
@@ -1090,6 +2001,18 @@ impl Database {
         Ok(aliases)
     }
 
+    /// Count a use of `alias` at `timestamp`. Returns false if there is no
+    /// such alias.
+    pub fn record_alias_use(&self, alias: &str, timestamp: DateTime<Utc>) -> Result<bool> {
+        let updated = self.conn.execute(
+            "UPDATE aliases SET use_count = use_count + 1,
+                 last_used = MAX(COALESCE(last_used, ''), ?1)
+             WHERE alias = ?2",
+            params![timestamp.to_rfc3339(), alias],
+        )?;
+        Ok(updated > 0)
+    }
+
     /// Upsert aliases from shell environment (sync)
     /// Returns the number of aliases upserted
     pub fn sync_aliases(&self, aliases: &[(String, String)]) -> Result<usize> {
@@ -1127,28 +2050,14 @@ impl Database {
 
     /// Get all sessions across all hosts
     pub fn get_all_sessions(&self) -> Result<Vec<Session>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT s.id, s.host_id, COALESCE(h.hostname, '?'), s.started_at, s.ended_at
-             FROM sessions s
-             LEFT JOIN hosts h ON s.host_id = h.id
-             ORDER BY s.started_at DESC",
-        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS}
+             FROM {SESSION_TABLES}
+             ORDER BY s.started_at DESC"
+        ))?;
 
         let sessions = stmt
-            .query_map([], |row| {
-                Ok(Session {
-                    id: SessionId::new(row.get(0)?),
-                    host_id: HostId::new(row.get(1)?),
-                    hostname: row.get(2)?,
-                    started_at: row
-                        .get::<_, String>(3)?
-                        .parse()
-                        .unwrap_or_else(|_| Utc::now()),
-                    ended_at: row
-                        .get::<_, Option<String>>(4)?
-                        .and_then(|s| s.parse().ok()),
-                })
-            })?
+            .query_map([], session_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(sessions)
@@ -1159,19 +2068,19 @@ impl Database {
         self.count_sessions_filtered(None)
     }
 
-    /// Count sessions with optional filter on session id or hostname
+    /// Count sessions with optional filter on session id, hostname, name,
+    /// note or tags
     pub fn count_sessions_filtered(&self, filter: Option<&str>) -> Result<usize> {
-        let (where_clause, params): (&str, Vec<Box<dyn rusqlite::types::ToSql>>) = match filter {
+        let (where_clause, params): (String, Vec<Box<dyn rusqlite::types::ToSql>>) = match filter {
             Some(f) if !f.is_empty() => (
-                "WHERE s.id LIKE ?1 OR h.hostname LIKE ?1",
+                format!("WHERE {}", session_text_condition(1)),
                 vec![Box::new(format!("%{}%", f))],
             ),
-            _ => ("", vec![]),
+            _ => (String::new(), vec![]),
         };
 
         let sql = format!(
-            "SELECT COUNT(*) FROM sessions s
-             LEFT JOIN hosts h ON s.host_id = h.id
+            "SELECT COUNT(*) FROM {SESSION_TABLES}
              {where_clause}"
         );
 
@@ -1195,22 +2104,24 @@ impl Database {
         limit: usize,
         filter: Option<&str>,
     ) -> Result<Vec<Session>> {
-        let (where_clause, params): (&str, Vec<Box<dyn rusqlite::types::ToSql>>) = match filter {
+        let (where_clause, params): (String, Vec<Box<dyn rusqlite::types::ToSql>>) = match filter {
             Some(f) if !f.is_empty() => (
-                "WHERE s.id LIKE ?3 OR h.hostname LIKE ?3",
+                format!("WHERE {}", session_text_condition(3)),
                 vec![
                     Box::new(limit as i64),
                     Box::new(offset as i64),
                     Box::new(format!("%{}%", f)),
                 ],
             ),
-            _ => ("", vec![Box::new(limit as i64), Box::new(offset as i64)]),
+            _ => (
+                String::new(),
+                vec![Box::new(limit as i64), Box::new(offset as i64)],
+            ),
         };
 
         let sql = format!(
-            "SELECT s.id, s.host_id, COALESCE(h.hostname, '?'), s.started_at, s.ended_at
-             FROM sessions s
-             LEFT JOIN hosts h ON s.host_id = h.id
+            "SELECT {SESSION_COLUMNS}
+             FROM {SESSION_TABLES}
              {where_clause}
              ORDER BY s.started_at DESC
              LIMIT ?1 OFFSET ?2"
@@ -1221,20 +2132,7 @@ impl Database {
             params.iter().map(|p| p.as_ref()).collect();
 
         let sessions = stmt
-            .query_map(params_ref.as_slice(), |row| {
-                Ok(Session {
-                    id: SessionId::new(row.get(0)?),
-                    host_id: HostId::new(row.get(1)?),
-                    hostname: row.get(2)?,
-                    started_at: row
-                        .get::<_, String>(3)?
-                        .parse()
-                        .unwrap_or_else(|_| Utc::now()),
-                    ended_at: row
-                        .get::<_, Option<String>>(4)?
-                        .and_then(|s| s.parse().ok()),
-                })
-            })?
+            .query_map(params_ref.as_slice(), session_from_row)?
             .collect::<rusqlite::Result<Vec<_>>>()?;
 
         Ok(sessions)
@@ -1267,6 +2165,57 @@ impl Database {
         Ok(tokens)
     }
 
+    /// Count tokens grouped by type, pattern, host and month of capture
+    pub fn get_token_audit_rows(&self) -> Result<Vec<TokenAuditRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT t.token_type, t.pattern, h.hostname, substr(c.timestamp, 1, 7), COUNT(*)
+             FROM tokens t
+             JOIN commands c ON c.id = t.command_id
+             JOIN sessions s ON s.id = c.session_id
+             JOIN hosts h ON h.id = s.host_id
+             GROUP BY 1, 2, 3, 4
+             ORDER BY 4, 1",
+        )?;
+
+        let rows = stmt
+            .query_map([], |row| {
+                Ok(TokenAuditRow {
+                    token_type: row.get(0)?,
+                    pattern: row.get(1)?,
+                    hostname: row.get(2)?,
+                    month: row.get(3)?,
+                    count: row.get::<_, i64>(4)? as usize,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(rows)
+    }
+
+    /// Find token values that appear in more than one command
+    pub fn get_reused_tokens(&self) -> Result<Vec<ReusedToken>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT MIN(token_type), original_value, GROUP_CONCAT(DISTINCT command_id)
+             FROM tokens
+             GROUP BY original_value
+             HAVING COUNT(DISTINCT command_id) > 1
+             ORDER BY COUNT(DISTINCT command_id) DESC",
+        )?;
+
+        let tokens = stmt
+            .query_map([], |row| {
+                let ids: String = row.get(2)?;
+                Ok(ReusedToken {
+                    token_type: row.get(0)?,
+                    original_value: row.get(1)?,
+                    command_ids: ids.split(',').filter_map(|id| id.parse().ok()).collect(),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(tokens)
+    }
+
     /// Delete a host and cascade to sessions/commands/tokens
     pub fn delete_host(&self, id: HostId) -> Result<()> {
         self.conn
@@ -1288,6 +2237,73 @@ impl Database {
         Ok(())
     }
 
+    /// Mark a token as not-a-secret: restore its original value in the
+    /// command text, delete the token row and allowlist the value's
+    /// fingerprint so it is never redacted again. Returns the removed token.
+    pub fn allow_token(&self, id: i64) -> Result<Token> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let token = tx
+            .query_row(
+                "SELECT id, command_id, token_type, placeholder, original_value, created_at
+                 FROM tokens WHERE id = ?1",
+                [id],
+                |row| {
+                    Ok(Token {
+                        id: row.get(0)?,
+                        command_id: CommandId::new(row.get(1)?),
+                        token_type: row.get(2)?,
+                        placeholder: row.get(3)?,
+                        original_value: row.get(4)?,
+                        created_at: row
+                            .get::<_, String>(5)?
+                            .parse()
+                            .unwrap_or_else(|_| Utc::now()),
+                    })
+                },
+            )
+            .optional()?
+            .ok_or_else(|| Error::custom(format!("Token {} not found", id)))?;
+
+        let command: String = tx.query_row(
+            "SELECT command FROM commands WHERE id = ?1",
+            [token.command_id.as_i64()],
+            |row| row.get(0),
+        )?;
+        let restored = command.replace(&token.placeholder, &token.original_value);
+
+        tx.execute("DELETE FROM tokens WHERE id = ?1", [id])?;
+        tx.execute(
+            "UPDATE commands SET command = ?1,
+                 redacted = EXISTS(SELECT 1 FROM tokens WHERE command_id = ?2)
+             WHERE id = ?2",
+            params![restored, token.command_id.as_i64()],
+        )?;
+        tx.execute(
+            "INSERT OR IGNORE INTO redaction_allowlist (value_hash, token_type, created_at)
+             VALUES (?1, ?2, ?3)",
+            params![
+                value_fingerprint(&token.original_value),
+                token.token_type,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+
+        tx.commit()?;
+        Ok(token)
+    }
+
+    /// Get the fingerprints of all allowlisted values
+    pub fn get_allowlist(&self) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT value_hash FROM redaction_allowlist")?;
+        let hashes = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(hashes)
+    }
+
     /// Store a session secret key name (value is NOT stored)
     pub fn store_session_secret(
         &self,
@@ -1381,6 +2397,111 @@ impl Database {
         Ok(())
     }
 
+    /// Purge every occurrence of `matcher` from the database.
+    ///
+    /// Matching text in `commands` is replaced with `replacement`, tokens
+    /// whose original value matches are deleted, and allowlist fingerprints
+    /// of matched values are dropped. The purge runs with `secure_delete`
+    /// enabled, restored afterwards even if it fails, then the database is vacuumed and the WAL checkpointed and
+    /// truncated so freed pages do not keep the secret on disk. With
+    /// `dry_run` nothing is written and the summary lists what would change.
+    pub fn forget_matching(
+        &self,
+        matcher: &Regex,
+        replacement: &str,
+        dry_run: bool,
+    ) -> Result<ForgetSummary> {
+        let mut summary = ForgetSummary::default();
+        let mut matched_values = HashSet::new();
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, command FROM commands ORDER BY id")?;
+        let commands = stmt
+            .query_map([], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, command) in commands {
+            if !matcher.is_match(&command) {
+                continue;
+            }
+            matched_values.extend(matcher.find_iter(&command).map(|m| m.as_str().to_string()));
+            let rewritten = matcher
+                .replace_all(&command, regex::NoExpand(replacement))
+                .into_owned();
+            summary.commands.push((id, rewritten));
+        }
+
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, token_type, original_value FROM tokens ORDER BY id")?;
+        let tokens = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        for (id, token_type, value) in tokens {
+            if matcher.is_match(&value) {
+                matched_values.extend(matcher.find_iter(&value).map(|m| m.as_str().to_string()));
+                matched_values.insert(value);
+                summary.tokens.push((id, token_type));
+            }
+        }
+
+        let allowlist: HashSet<String> = self.get_allowlist()?.into_iter().collect();
+        let fingerprints: Vec<String> = matched_values
+            .iter()
+            .map(|v| value_fingerprint(v))
+            .filter(|hash| allowlist.contains(hash))
+            .collect();
+        summary.allowlist_entries = fingerprints.len();
+
+        if dry_run || summary.is_empty() {
+            return Ok(summary);
+        }
+
+        // Whatever happens, put secure_delete back the way it was
+        let secure_delete: i64 = self
+            .conn
+            .query_row("PRAGMA secure_delete", [], |row| row.get(0))?;
+        self.conn.execute_batch("PRAGMA secure_delete = ON")?;
+        let purged = (|| -> Result<()> {
+            let tx = self.conn.unchecked_transaction()?;
+            for (id, command) in &summary.commands {
+                tx.execute(
+                    "UPDATE commands SET command = ?1, redacted = 1 WHERE id = ?2",
+                    params![command, id],
+                )?;
+            }
+            for (id, _) in &summary.tokens {
+                tx.execute("DELETE FROM tokens WHERE id = ?1", [id])?;
+            }
+            for hash in &fingerprints {
+                tx.execute(
+                    "DELETE FROM redaction_allowlist WHERE value_hash = ?1",
+                    [hash],
+                )?;
+            }
+            tx.commit()?;
+
+            self.vacuum()?;
+            self.conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE)")?;
+            Ok(())
+        })();
+        let restored = self
+            .conn
+            .execute_batch(&format!("PRAGMA secure_delete = {secure_delete}"));
+        purged?;
+        restored?;
+
+        Ok(summary)
+    }
+
     /// Delete the oldest commands beyond `max_entries`, keeping the most recent ones.
     /// Returns the number of deleted rows.
     pub fn prune_old_commands(&self, max_entries: usize) -> Result<usize> {
@@ -1394,6 +2515,131 @@ impl Database {
     }
 }
 
+impl HistoryQuery for Database {
+    fn count_unique_commands_filtered(
+        &self,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<usize> {
+        self.count_unique_commands_filtered(filter, actor)
+    }
+
+    fn get_unique_commands_filtered(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<Vec<CommandEntry>> {
+        self.get_unique_commands_filtered(offset, limit, filter, actor)
+    }
+
+    fn get_commands_for_directory(&self, directory: &str) -> Result<Vec<CommandEntry>> {
+        self.get_commands_for_directory(directory)
+    }
+
+    fn get_frequent_commands(&self, limit: usize) -> Result<Vec<(String, usize)>> {
+        self.get_frequent_commands(limit)
+    }
+
+    fn count_command_runs(&self, command: &str) -> Result<usize> {
+        self.count_command_runs(command)
+    }
+
+    fn delete_command(&self, id: CommandId) -> Result<()> {
+        self.delete_command(id)
+    }
+
+    fn get_stats(&self) -> Result<DatabaseStats> {
+        self.get_stats()
+    }
+
+    fn usage_stats(&self, filter: &CommandFilter, top: usize) -> Result<UsageStats> {
+        self.usage_stats(filter, top)
+    }
+
+    fn count_sessions_filtered(&self, filter: Option<&str>) -> Result<usize> {
+        self.count_sessions_filtered(filter)
+    }
+
+    fn get_sessions_filtered(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: Option<&str>,
+    ) -> Result<Vec<Session>> {
+        self.get_sessions_filtered(offset, limit, filter)
+    }
+
+    fn count_commands_for_sessions(&self, session_ids: &[&str]) -> Result<Vec<usize>> {
+        self.count_commands_for_sessions(session_ids)
+    }
+
+    fn get_session(&self, session_id: &str) -> Result<Option<Session>> {
+        self.get_session(session_id)
+    }
+
+    fn get_commands_for_session(&self, session_id: &str) -> Result<Vec<CommandEntry>> {
+        self.get_commands_for_session(session_id)
+    }
+
+    fn get_commands_for_session_tree(&self, session_id: &str) -> Result<Vec<CommandEntry>> {
+        self.get_commands_for_session_tree(session_id)
+    }
+
+    fn delete_session(&self, id: &str) -> Result<()> {
+        self.delete_session(id)
+    }
+
+    fn list_aliases(&self) -> Result<Vec<Alias>> {
+        self.list_aliases()
+    }
+
+    fn add_alias(&self, alias: &str, command: &str, description: &str) -> Result<()> {
+        self.add_alias(alias, command, description)
+    }
+
+    fn update_alias(&self, alias: &str, command: &str, description: Option<&str>) -> Result<()> {
+        self.update_alias(alias, command, description)
+    }
+
+    fn remove_alias(&self, alias: &str) -> Result<()> {
+        self.remove_alias(alias)
+    }
+
+    fn get_hosts(&self) -> Result<Vec<Host>> {
+        self.get_hosts()
+    }
+
+    fn delete_host(&self, id: HostId) -> Result<()> {
+        self.delete_host(id)
+    }
+
+    fn get_all_tokens(&self) -> Result<Vec<Token>> {
+        self.get_all_tokens()
+    }
+
+    fn get_tokens_for_command(&self, command_id: CommandId) -> Result<Vec<Token>> {
+        self.get_tokens_for_command(command_id)
+    }
+
+    fn allow_token(&self, id: i64) -> Result<Token> {
+        self.allow_token(id)
+    }
+
+    fn delete_token(&self, id: i64) -> Result<()> {
+        self.delete_token(id)
+    }
+
+    fn get_preference(&self, key: &str) -> Result<Option<String>> {
+        self.get_preference(key)
+    }
+
+    fn set_preference(&self, key: &str, value: &str) -> Result<()> {
+        self.set_preference(key, value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1430,7 +2676,7 @@ mod tests {
             .add_command("echo password123", "/home", Utc::now(), true, None)
             .unwrap();
 
-        db.store_token(cmd_id, "password", "<redacted>", "password123")
+        db.store_token(cmd_id, "password", "<redacted>", "password123", "test")
             .unwrap();
 
         let tokens = db.get_tokens_for_command(CommandId::new(cmd_id)).unwrap();
@@ -1438,6 +2684,384 @@ mod tests {
         assert_eq!(tokens[0].original_value, "password123");
     }
 
+    #[test]
+    fn test_allow_token() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        let cmd_id = db
+            .add_command("git show <api_key:1>", "/home", Utc::now(), true, None)
+            .unwrap();
+        let token_id = db
+            .store_token(cmd_id, "api_key", "<api_key:1>", "deadbeefcafe", "test")
+            .unwrap();
+
+        let token = db.allow_token(token_id).unwrap();
+        assert_eq!(token.original_value, "deadbeefcafe");
+
+        let commands = db.get_recent_commands(1).unwrap();
+        assert_eq!(commands[0].command, "git show deadbeefcafe");
+        assert!(!commands[0].redacted);
+        assert!(
+            db.get_tokens_for_command(CommandId::new(cmd_id))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            db.get_allowlist().unwrap(),
+            vec![value_fingerprint("deadbeefcafe")]
+        );
+
+        // The token row is gone, so it cannot be allowed twice
+        assert!(db.allow_token(token_id).is_err());
+    }
+
+    #[test]
+    fn test_reap_stale_sessions() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        db.resume_session("dead").unwrap();
+        let last = Utc::now() - chrono::Duration::hours(1);
+        db.add_command("echo last", "/", last, false, None).unwrap();
+        db.resume_session("alive").unwrap();
+        db.resume_session("untracked").unwrap();
+        db.conn
+            .execute(
+                "UPDATE sessions SET pid = ?1 WHERE id = 'dead'",
+                [u32::MAX - 1],
+            )
+            .unwrap();
+        db.conn
+            .execute(
+                "UPDATE sessions SET pid = ?1 WHERE id = 'alive'",
+                [std::process::id()],
+            )
+            .unwrap();
+
+        assert_eq!(db.reap_stale_sessions().unwrap(), vec!["dead".to_string()]);
+        assert_eq!(db.count_sessions_by_state().unwrap(), (2, 1));
+
+        let dead = db
+            .get_all_sessions()
+            .unwrap()
+            .into_iter()
+            .find(|s| s.id.as_ref() == "dead")
+            .unwrap();
+        assert_eq!(dead.ended_at.unwrap().timestamp(), last.timestamp());
+
+        // Already closed sessions are not reaped again
+        assert!(db.reap_stale_sessions().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_session_private_mode() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        assert_eq!(db.get_session_private("zsh-1").unwrap(), None);
+        db.set_session_private("zsh-1", Some(PrivateMode::Ephemeral))
+            .unwrap();
+        assert_eq!(
+            db.get_session_private("zsh-1").unwrap(),
+            Some(PrivateMode::Ephemeral)
+        );
+        db.set_session_private("zsh-1", None).unwrap();
+        assert_eq!(db.get_session_private("zsh-1").unwrap(), None);
+        assert_eq!(db.get_all_sessions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_session_metadata() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        db.resume_session("zsh-1").unwrap();
+        db.resume_session("zsh-2").unwrap();
+        db.set_session_name("zsh-1", Some("Incident 4211")).unwrap();
+        db.set_session_note("zsh-1", Some("db failover")).unwrap();
+        db.add_session_tags("zsh-1", &["oncall".into(), "db".into(), "oncall".into()])
+            .unwrap();
+        db.add_session_tags("zsh-2", &["oncall".into()]).unwrap();
+        assert!(db.add_session_tags("zsh-2", &["a,b".into()]).is_err());
+
+        let session = db.get_session("zsh-1").unwrap().unwrap();
+        assert_eq!(session.label(), "Incident 4211");
+        assert_eq!(session.note.as_deref(), Some("db failover"));
+        assert_eq!(session.tags, vec!["db", "oncall"]);
+
+        let named = SessionFilter {
+            name: Some("incident*".into()),
+            ..Default::default()
+        };
+        let found = db.find_sessions(&named).unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id.as_str(), "zsh-1");
+
+        let tagged = SessionFilter {
+            tag: Some("oncall".into()),
+            since: Some(Utc::now() - chrono::Duration::days(1)),
+            ..Default::default()
+        };
+        assert_eq!(db.find_sessions(&tagged).unwrap().len(), 2);
+        let tagged_later = SessionFilter {
+            since: Some(Utc::now() + chrono::Duration::days(1)),
+            ..tagged
+        };
+        assert!(db.find_sessions(&tagged_later).unwrap().is_empty());
+
+        // The TUI filter also matches names, notes and tags
+        assert_eq!(db.count_sessions_filtered(Some("failover")).unwrap(), 1);
+        assert_eq!(db.count_sessions_filtered(Some("oncall")).unwrap(), 2);
+
+        assert_eq!(db.remove_session_tags("zsh-1", &["db".into()]).unwrap(), 1);
+        db.set_session_name("zsh-1", Some("")).unwrap();
+        let session = db.get_session("zsh-1").unwrap().unwrap();
+        assert_eq!(session.label(), "zsh-1");
+        assert_eq!(session.tags, vec!["oncall"]);
+
+        db.delete_session("zsh-1").unwrap();
+        assert_eq!(db.count_sessions_filtered(Some("oncall")).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_session_tree() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        for id in ["zsh-1", "tmux-1", "agent-1", "zsh-2"] {
+            db.resume_session(id).unwrap();
+            db.add_command(&format!("echo {id}"), "/", Utc::now(), false, None)
+                .unwrap();
+        }
+        db.set_session_parent("tmux-1", "zsh-1").unwrap();
+        db.set_session_parent("agent-1", "tmux-1").unwrap();
+
+        assert_eq!(
+            db.get_session_descendants("zsh-1").unwrap(),
+            vec!["tmux-1", "agent-1"]
+        );
+        assert!(db.get_session_descendants("zsh-2").unwrap().is_empty());
+        assert_eq!(
+            db.get_session("agent-1")
+                .unwrap()
+                .unwrap()
+                .parent_id
+                .as_deref(),
+            Some("tmux-1")
+        );
+
+        let commands = db.get_commands_for_session_tree("zsh-1").unwrap();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[0].command, "echo agent-1");
+
+        // No cycles
+        assert!(db.set_session_parent("zsh-1", "agent-1").is_err());
+        assert!(db.set_session_parent("zsh-1", "zsh-1").is_err());
+    }
+
+    #[test]
+    fn test_agent_runs() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        db.add_command("ls", "/", Utc::now(), false, None).unwrap();
+        let id = db
+            .add_command("cargo test", "/", Utc::now(), false, Some(101))
+            .unwrap();
+        let mut run = AgentRun::new("cargo test", "claude-code");
+        run.tool_name = Some("Bash".to_string());
+        run.stdout_bytes = Some(42);
+        db.store_agent_run(id, &run).unwrap();
+
+        assert_eq!(
+            db.get_command_actor(CommandId::new(id)).unwrap().as_deref(),
+            Some("claude-code")
+        );
+        let by_agents = db.get_commands_by_actor(&ActorFilter::Agents).unwrap();
+        assert_eq!(by_agents.len(), 1);
+        assert_eq!(by_agents[0].command, "cargo test");
+        let by_human = db.get_commands_by_actor(&ActorFilter::Human).unwrap();
+        assert_eq!(by_human[0].command, "ls");
+        assert!(
+            db.get_commands_by_actor(&ActorFilter::parse("aider"))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            db.count_unique_commands_filtered(None, &ActorFilter::Named("claude-code".into()))
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_usage_stats() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+        let now = Utc::now();
+        let long_ago = now - chrono::Duration::days(40);
+        let week_ago = now - chrono::Duration::days(7);
+        let days_since = |t: DateTime<Utc>| {
+            let local = |t: DateTime<Utc>| t.with_timezone(&Local).date_naive();
+            (local(now) - local(t)).num_days() as usize + 1
+        };
+
+        for code in [1, 2, 0] {
+            db.add_command("make", "/src", now, false, Some(code))
+                .unwrap();
+        }
+        for _ in 0..3 {
+            db.add_command("ls", "/tmp", now, false, Some(0)).unwrap();
+        }
+        db.add_command("cargo", "/src", now, false, Some(101))
+            .unwrap();
+        let id = db
+            .add_command("mysql -p<password:1>", "/src", long_ago, true, Some(0))
+            .unwrap();
+        db.store_token(id, "password", "<password:1>", "hunter2", "")
+            .unwrap();
+
+        let stats = db.usage_stats(&CommandFilter::default(), 10).unwrap();
+        assert_eq!(stats.total_runs, 8);
+        assert_eq!(stats.per_day.len(), days_since(long_ago));
+        assert_eq!(stats.per_day.iter().map(|(_, n)| n).sum::<usize>(), 8);
+        assert_eq!(stats.per_week.iter().map(|(_, n)| n).sum::<usize>(), 8);
+        assert_eq!(stats.by_hour.iter().flatten().sum::<usize>(), 8);
+        assert_eq!(stats.top_directories[0], ("/src".to_string(), 5));
+        // cargo failed once but has too few runs to rank
+        assert_eq!(stats.failing, vec![("make".to_string(), 2, 3)]);
+        assert_eq!(stats.redactions, vec![("password".to_string(), 1)]);
+
+        let recent = CommandFilter {
+            since: Some(week_ago),
+            ..Default::default()
+        };
+        let stats = db.usage_stats(&recent, 10).unwrap();
+        assert_eq!(stats.total_runs, 7);
+        assert_eq!(stats.per_day.len(), days_since(week_ago));
+        assert!(stats.redactions.is_empty());
+
+        let elsewhere = CommandFilter {
+            hostnames: Some(vec!["some-other-host".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(db.usage_stats(&elsewhere, 10).unwrap().total_runs, 0);
+    }
+
+    #[test]
+    fn test_find_commands_text() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+        for command in [
+            "df -h 100%",
+            "df -h 1000",
+            "rm my_file",
+            "rm myxfile",
+            "ls C:\\tmp",
+        ] {
+            db.add_command(command, "/home", Utc::now(), false, Some(0))
+                .unwrap();
+        }
+
+        // Wildcards in the search text match only themselves
+        let find = |text: &str| -> Vec<String> {
+            let filter = CommandFilter {
+                text: Some(text.to_string()),
+                ..Default::default()
+            };
+            db.find_commands(&filter)
+                .unwrap()
+                .into_iter()
+                .map(|(c, _)| c.command)
+                .collect()
+        };
+        assert_eq!(find("0%"), ["df -h 100%"]);
+        assert_eq!(find("my_"), ["rm my_file"]);
+        assert_eq!(find("C:\\t"), ["ls C:\\tmp"]);
+        assert_eq!(find("rm my").len(), 2);
+    }
+
+    #[test]
+    fn test_forget_matching() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+
+        let leaked = db
+            .add_command("export KEY=sk_9f8e7d", "/", Utc::now(), false, None)
+            .unwrap();
+        let redacted = db
+            .add_command("psql <password:1>", "/", Utc::now(), true, None)
+            .unwrap();
+        db.store_token(redacted, "password", "<password:1>", "sk_9f8e7d", "flag-p")
+            .unwrap();
+        db.add_command("echo hello", "/", Utc::now(), false, None)
+            .unwrap();
+        db.conn
+            .execute(
+                "INSERT INTO redaction_allowlist (value_hash, token_type, created_at)
+                 VALUES (?1, 'api_key', '')",
+                [value_fingerprint("sk_9f8e7d")],
+            )
+            .unwrap();
+
+        let matcher = Regex::new(r"sk_\w+").unwrap();
+        let preview = db.forget_matching(&matcher, "<redacted>", true).unwrap();
+        assert_eq!(
+            preview.commands,
+            vec![(leaked, "export KEY=<redacted>".to_string())]
+        );
+        assert_eq!(preview.tokens.len(), 1);
+        assert_eq!(preview.allowlist_entries, 1);
+        assert_eq!(db.get_all_tokens().unwrap().len(), 1);
+
+        let summary = db.forget_matching(&matcher, "<redacted>", false).unwrap();
+        assert_eq!(summary.commands.len(), 1);
+        let commands = db.get_all_commands().unwrap();
+        assert_eq!(commands[0].command, "export KEY=<redacted>");
+        assert!(commands[0].redacted);
+        assert!(db.get_all_tokens().unwrap().is_empty());
+        assert!(db.get_allowlist().unwrap().is_empty());
+
+        assert!(
+            db.forget_matching(&matcher, "<redacted>", false)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_forget_matching_restores_secure_delete() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let mut db = Database::new(temp_file.path()).unwrap();
+        db.add_command("export KEY=sk_9f8e7d", "/", Utc::now(), false, None)
+            .unwrap();
+        let secure_delete = |db: &Database| -> i64 {
+            db.conn
+                .query_row("PRAGMA secure_delete", [], |row| row.get(0))
+                .unwrap()
+        };
+        let matcher = Regex::new(r"sk_\w+").unwrap();
+
+        // A failed purge leaves the setting as it found it
+        db.conn
+            .execute_batch(
+                "CREATE TRIGGER refuse BEFORE UPDATE ON commands
+                 BEGIN SELECT RAISE(ABORT, 'read only'); END",
+            )
+            .unwrap();
+        assert!(db.forget_matching(&matcher, "<redacted>", false).is_err());
+        assert_eq!(secure_delete(&db), 0);
+        assert_eq!(
+            db.get_all_commands().unwrap()[0].command,
+            "export KEY=sk_9f8e7d"
+        );
+
+        db.conn.execute_batch("DROP TRIGGER refuse").unwrap();
+        db.forget_matching(&matcher, "<redacted>", false).unwrap();
+        assert_eq!(secure_delete(&db), 0);
+    }
+
     #[test]
     fn test_alias_crud() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -1464,6 +3088,26 @@ mod tests {
         assert!(aliases.is_empty());
     }
 
+    #[test]
+    fn test_alias_usage() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let db = Database::new(temp_file.path()).unwrap();
+        db.add_alias("gs", "git status", "").unwrap();
+        assert_eq!(db.list_aliases().unwrap()[0].use_count, 0);
+        assert!(db.list_aliases().unwrap()[0].last_used.is_none());
+
+        let later = Utc::now();
+        let earlier = later - chrono::Duration::hours(1);
+        assert!(db.record_alias_use("gs", later).unwrap());
+        // An older use (e.g. an import) does not move last_used back
+        assert!(db.record_alias_use("gs", earlier).unwrap());
+        assert!(!db.record_alias_use("gd", later).unwrap());
+
+        let gs = &db.list_aliases().unwrap()[0];
+        assert_eq!(gs.use_count, 2);
+        assert_eq!(gs.last_used.unwrap().timestamp(), later.timestamp());
+    }
+
     #[test]
     fn test_alias_sync() {
         let temp_file = NamedTempFile::new().unwrap();
@@ -0,0 +1,96 @@
+//! Secure purge of leaked secrets for zam
+//!
+//! `zam forget` erases every trace of a secret: matching text in `commands`
+//! is rewritten, matching `tokens` rows and allowlist fingerprints are
+//! deleted, and the file backend log is rewritten. The database side runs
+//! with `PRAGMA secure_delete`, then checkpoints the WAL and vacuums so the
+//! old pages do not linger on disk.
+
+use crate::error::{Error, Result};
+use regex::Regex;
+
+/// What was (or, in dry-run mode, would be) removed
+#[derive(Debug, Clone, Default)]
+pub struct ForgetSummary {
+    /// Commands whose text was rewritten: (id, command after rewrite)
+    pub commands: Vec<(i64, String)>,
+    /// Deleted token rows: (id, token type)
+    pub tokens: Vec<(i64, String)>,
+    /// Deleted allowlist entries
+    pub allowlist_entries: usize,
+}
+
+impl ForgetSummary {
+    /// Whether nothing matched
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty() && self.tokens.is_empty() && self.allowlist_entries == 0
+    }
+}
+
+/// Build the matcher for `--value` (literal) or `--pattern` (regex)
+pub fn build_matcher(value: Option<&str>, pattern: Option<&str>) -> Result<Regex> {
+    match (value, pattern) {
+        (Some(value), None) if !value.is_empty() => Ok(Regex::new(&regex::escape(value))?),
+        (None, Some(pattern)) if !pattern.is_empty() => {
+            Regex::new(pattern).map_err(|_| Error::InvalidRedactionPattern {
+                pattern: pattern.to_string(),
+            })
+        }
+        _ => Err(Error::invalid_arguments(
+            "Specify exactly one non-empty --value or --pattern",
+        )),
+    }
+}
+
+/// Whether a command line runs `zam forget`. Such commands are never logged:
+/// their arguments are the secret being purged.
+pub fn is_forget_command(command: &str) -> bool {
+    let mut words = command
+        .split_whitespace()
+        .skip_while(|w| w.contains('=') || *w == "sudo" || *w == "env");
+    if words.next().map(|w| w.rsplit('/').next().unwrap_or(w)) != Some("zam") {
+        return false;
+    }
+    // The subcommand is the first word that is neither a global flag nor
+    // the value of `-c/--config`
+    while let Some(word) = words.next() {
+        match word {
+            "-c" | "--config" => {
+                words.next();
+            }
+            w if w.starts_with('-') => {}
+            w => return w == "forget",
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_matcher() {
+        let literal = build_matcher(Some("a.b*c"), None).unwrap();
+        assert!(literal.is_match("x a.b*c y"));
+        assert!(!literal.is_match("aXbbc"));
+
+        let pattern = build_matcher(None, Some(r"sk_live_\w+")).unwrap();
+        assert!(pattern.is_match("sk_live_abc"));
+
+        assert!(build_matcher(None, None).is_err());
+        assert!(build_matcher(Some(""), None).is_err());
+        assert!(build_matcher(None, Some("(")).is_err());
+    }
+
+    #[test]
+    fn test_is_forget_command() {
+        assert!(is_forget_command("zam forget --value hunter2"));
+        assert!(is_forget_command(
+            "ZAM_X=1 /usr/local/bin/zam -v forget --pattern x"
+        ));
+        assert!(is_forget_command("zam -c ~/zam.toml forget --value x"));
+        assert!(!is_forget_command("zam search forget"));
+        assert!(!is_forget_command("echo zam forget"));
+    }
+}
@@ -4,10 +4,15 @@
 //! including logging, importing, searching, and maintaining command history
 //! with automatic redaction and deduplication.
 
+use crate::agent::ActorFilter;
 use crate::config::Config;
+use crate::database::{CommandEntry, DatabaseStats};
 use crate::error::{Error, Result};
+use crate::ignore::IgnorePolicy;
 use crate::redaction::{RedactionEngine, RedactionStats};
+use crate::types::{CommandId, SessionId};
 use chrono::{DateTime, Utc};
+use regex::{NoExpand, Regex};
 use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::{File, OpenOptions};
@@ -52,6 +57,7 @@ pub struct HistoryStats {
 pub struct HistoryManager {
     config: Config,
     redaction_engine: RedactionEngine,
+    ignore_policy: IgnorePolicy,
     history_file: PathBuf,
     stats: HistoryStats,
 }
@@ -60,15 +66,8 @@ impl HistoryManager {
     /// Create a new history manager with the given configuration
     #[must_use = "History manager must be used to log commands"]
     pub fn new(config: Config) -> Result<Self> {
-        let redaction_engine = RedactionEngine::with_config(
-            config.redaction.use_builtin_patterns,
-            config.redaction.custom_patterns.clone(),
-            config.redaction.exclude_patterns.clone(),
-            config.redaction.placeholder.clone(),
-            config.redaction.min_redaction_length,
-            config.custom_env_vars.clone(),
-            config.redaction.redact_env_vars,
-        )?;
+        let redaction_engine = RedactionEngine::from_config(&config)?;
+        let ignore_policy = IgnorePolicy::from_config(&config)?;
 
         let history_file = config.history_file.clone();
 
@@ -83,6 +82,7 @@ impl HistoryManager {
         let mut manager = Self {
             config,
             redaction_engine,
+            ignore_policy,
             history_file,
             stats: HistoryStats::default(),
         };
@@ -104,17 +104,16 @@ impl HistoryManager {
         command: &str,
         timestamp: Option<DateTime<Utc>>,
     ) -> Result<()> {
-        // Check if we should exclude this command
-        if self.config.should_exclude_command(command) {
-            return Ok(());
-        }
-
         let timestamp = timestamp.unwrap_or_else(Utc::now);
         let directory = env::current_dir()
             .unwrap_or_else(|_| PathBuf::from("<unknown>"))
             .to_string_lossy()
             .to_string();
 
+        if self.ignore_policy.check(command, &directory).is_some() {
+            return Ok(());
+        }
+
         // Redact sensitive information
         let (redacted_command, was_redacted) =
             if self.config.enable_redaction && !self.config.should_skip_redaction(command) {
@@ -288,6 +287,82 @@ impl HistoryManager {
         Ok(commands)
     }
 
+    /// Entries that are not deleted, oldest first, as the TUI shows them
+    fn command_entries(&self) -> Result<Vec<CommandEntry>> {
+        Ok(self
+            .get_entries()?
+            .into_iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.deleted)
+            .map(|(line, entry)| CommandEntry {
+                id: CommandId::new(line as i64 + 1),
+                session_id: SessionId::new(String::new()),
+                command: entry.command,
+                timestamp: entry.timestamp,
+                directory: entry.directory,
+                redacted: entry.redacted,
+                exit_code: None,
+            })
+            .collect())
+    }
+
+    /// Newest entry of each command containing `filter` (ignoring ASCII
+    /// case, like the database), most recent first. The log records no
+    /// actors, so every entry counts as typed by the user.
+    fn unique_commands_matching(
+        &self,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<Vec<CommandEntry>> {
+        if !matches!(actor, ActorFilter::All | ActorFilter::Human) {
+            return Ok(Vec::new());
+        }
+        let filter = filter.unwrap_or_default().to_ascii_lowercase();
+        let mut seen = HashSet::new();
+        let mut commands: Vec<CommandEntry> = self
+            .command_entries()?
+            .into_iter()
+            .rev()
+            .filter(|e| seen.insert(e.command.clone()))
+            .filter(|e| e.command.to_ascii_lowercase().contains(&filter))
+            .collect();
+        commands.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        Ok(commands)
+    }
+
+    /// Mark the entries at `indices` (positions in `get_entries`) as deleted.
+    /// Returns how many were not deleted already.
+    fn mark_deleted(&self, indices: &[usize]) -> Result<usize> {
+        if indices.is_empty() {
+            return Ok(0);
+        }
+
+        // Read all entries
+        let mut entries = self.get_entries()?;
+        let mut deleted_count = 0;
+
+        // Mark entries as deleted
+        for &idx in indices {
+            if let Some(entry) = entries.get_mut(idx)
+                && !entry.deleted
+            {
+                entry.deleted = true;
+                deleted_count += 1;
+            }
+        }
+
+        // Rewrite the history file with deleted markers
+        let file = File::create(&self.history_file)?;
+        let mut writer = BufWriter::new(file);
+
+        for entry in &entries {
+            writeln!(writer, "{}", self.format_entry(entry))?;
+        }
+        writer.flush()?;
+
+        Ok(deleted_count)
+    }
+
     /// Get history statistics
     pub fn get_stats(&mut self) -> Result<&HistoryStats> {
         self.update_stats()?;
@@ -301,6 +376,64 @@ impl HistoryManager {
         Ok(())
     }
 
+    /// Replace every occurrence of `matcher` in the history file with
+    /// `replacement`, rewriting the file in place. Lines that cannot be
+    /// parsed are rewritten as raw text. Returns the affected commands after
+    /// rewriting; with `dry_run` the file is left untouched.
+    pub fn forget_matching(
+        &mut self,
+        matcher: &Regex,
+        replacement: &str,
+        dry_run: bool,
+    ) -> Result<Vec<String>> {
+        let replace = |text: &str| {
+            matcher
+                .replace_all(text, NoExpand(replacement))
+                .into_owned()
+        };
+        let content = std::fs::read_to_string(&self.history_file)?;
+        let mut affected = Vec::new();
+        let mut lines = Vec::new();
+
+        for line in content.lines() {
+            let rewritten = match self.parse_entry(line).ok().flatten() {
+                Some(mut entry)
+                    if matcher.is_match(&entry.command) || matcher.is_match(&entry.directory) =>
+                {
+                    entry.command = replace(&entry.command);
+                    entry.directory = replace(&entry.directory);
+                    affected.push(entry.command.clone());
+                    self.format_entry(&entry)
+                }
+                _ if matcher.is_match(line) => {
+                    let raw = replace(line);
+                    affected.push(raw.clone());
+                    raw
+                }
+                _ => line.to_string(),
+            };
+            lines.push(rewritten);
+        }
+
+        if dry_run || affected.is_empty() {
+            return Ok(affected);
+        }
+
+        let file = OpenOptions::new()
+            .write(true)
+            .truncate(true)
+            .open(&self.history_file)?;
+        let mut writer = BufWriter::new(file);
+        for line in &lines {
+            writeln!(writer, "{}", line)?;
+        }
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        self.update_stats()?;
+
+        Ok(affected)
+    }
+
     /// Trim history to max entries
     fn trim_history(&mut self) -> Result<()> {
         let entries = self.get_entries()?;
@@ -638,34 +771,81 @@ impl crate::backend::HistoryProvider for HistoryManager {
     }
 
     fn delete_entries(&mut self, indices: &[usize]) -> Result<usize> {
-        if indices.is_empty() {
-            return Ok(0);
-        }
+        self.mark_deleted(indices)
+    }
+}
 
-        // Read all entries
-        let mut entries = self.get_entries()?;
-        let mut deleted_count = 0;
+/// The TUI on the file backend. Entries get IDs from their line in the log
+/// (starting at 1) and deleted entries are left out.
+impl crate::backend::HistoryQuery for HistoryManager {
+    fn count_unique_commands_filtered(
+        &self,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<usize> {
+        Ok(self.unique_commands_matching(filter, actor)?.len())
+    }
 
-        // Mark entries as deleted
-        for &idx in indices {
-            if let Some(entry) = entries.get_mut(idx)
-                && !entry.deleted
-            {
-                entry.deleted = true;
-                deleted_count += 1;
-            }
-        }
+    fn get_unique_commands_filtered(
+        &self,
+        offset: usize,
+        limit: usize,
+        filter: Option<&str>,
+        actor: &ActorFilter,
+    ) -> Result<Vec<CommandEntry>> {
+        Ok(self
+            .unique_commands_matching(filter, actor)?
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect())
+    }
 
-        // Rewrite the history file with deleted markers
-        let file = File::create(&self.history_file)?;
-        let mut writer = BufWriter::new(file);
+    fn get_commands_for_directory(&self, directory: &str) -> Result<Vec<CommandEntry>> {
+        let mut seen = HashSet::new();
+        Ok(self
+            .command_entries()?
+            .into_iter()
+            .rev()
+            .filter(|e| e.directory == directory && seen.insert(e.command.clone()))
+            .collect())
+    }
 
-        for entry in &entries {
-            writeln!(writer, "{}", self.format_entry(entry))?;
+    fn get_frequent_commands(&self, limit: usize) -> Result<Vec<(String, usize)>> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for entry in self.command_entries()? {
+            *counts.entry(entry.command).or_default() += 1;
         }
-        writer.flush()?;
+        let mut frequent: Vec<(String, usize)> = counts.into_iter().collect();
+        frequent.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        frequent.truncate(limit);
+        Ok(frequent)
+    }
 
-        Ok(deleted_count)
+    fn count_command_runs(&self, command: &str) -> Result<usize> {
+        Ok(self
+            .command_entries()?
+            .iter()
+            .filter(|e| e.command == command)
+            .count())
+    }
+
+    fn delete_command(&self, id: CommandId) -> Result<()> {
+        let line = usize::try_from(id.as_i64() - 1)
+            .map_err(|_| Error::custom(format!("No history entry {}", id)))?;
+        self.mark_deleted(&[line])?;
+        Ok(())
+    }
+
+    fn get_stats(&self) -> Result<DatabaseStats> {
+        let entries = self.command_entries()?;
+        Ok(DatabaseStats {
+            total_commands: entries.len(),
+            redacted_commands: entries.iter().filter(|e| e.redacted).count(),
+            oldest_entry: entries.iter().map(|e| e.timestamp).min(),
+            newest_entry: entries.iter().map(|e| e.timestamp).max(),
+            ..Default::default()
+        })
     }
 }
 
@@ -807,4 +987,97 @@ mod tests {
         assert_eq!(entries[0].command, "command2");
         assert_eq!(entries[1].command, "command3");
     }
+
+    #[test]
+    fn test_forget_matching() {
+        let config = test_config();
+        let mut manager = HistoryManager::new(config).unwrap();
+        manager.log_command("deploy --key sk_9f8e7d").unwrap();
+        manager.log_command("echo hello").unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&manager.history_file)
+            .unwrap()
+            .write_all(b"garbage sk_9f8e7d\n")
+            .unwrap();
+
+        let matcher = Regex::new("sk_9f8e7d").unwrap();
+        let preview = manager
+            .forget_matching(&matcher, "<forgotten>", true)
+            .unwrap();
+        assert_eq!(preview.len(), 2);
+        let content = std::fs::read_to_string(&manager.history_file).unwrap();
+        assert!(content.contains("sk_9f8e7d"));
+
+        let affected = manager
+            .forget_matching(&matcher, "<forgotten>", false)
+            .unwrap();
+        assert_eq!(affected[0], "deploy --key <forgotten>");
+        let content = std::fs::read_to_string(&manager.history_file).unwrap();
+        assert!(!content.contains("sk_9f8e7d"));
+        assert!(content.contains("garbage <forgotten>"));
+        assert_eq!(manager.get_entries().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_history_query() {
+        use crate::backend::HistoryQuery;
+
+        let mut config = test_config();
+        config.shell_integration.log_duplicates = true;
+        let mut manager = HistoryManager::new(config).unwrap();
+        let start = Utc::now() - chrono::Duration::minutes(10);
+        for (minute, command) in ["make", "ls -la", "make", "git status", "make"]
+            .iter()
+            .enumerate()
+        {
+            manager
+                .log_command_with_timestamp(
+                    command,
+                    Some(start + chrono::Duration::minutes(minute as i64)),
+                )
+                .unwrap();
+        }
+        let all = ActorFilter::All;
+
+        let unique = manager
+            .get_unique_commands_filtered(0, 10, None, &all)
+            .unwrap();
+        let names: Vec<&str> = unique.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(names, vec!["make", "git status", "ls -la"]);
+        assert_eq!(unique[0].id, CommandId::new(5));
+        assert_eq!(
+            manager
+                .count_unique_commands_filtered(Some("S"), &all)
+                .unwrap(),
+            2
+        );
+        let page = manager
+            .get_unique_commands_filtered(1, 1, None, &all)
+            .unwrap();
+        assert_eq!(page[0].command, "git status");
+        assert!(
+            manager
+                .get_unique_commands_filtered(0, 10, None, &ActorFilter::Agents)
+                .unwrap()
+                .is_empty()
+        );
+
+        let cwd = env::current_dir().unwrap().to_string_lossy().to_string();
+        assert_eq!(manager.get_commands_for_directory(&cwd).unwrap().len(), 3);
+        assert_eq!(
+            manager.get_frequent_commands(1).unwrap(),
+            vec![("make".to_string(), 3)]
+        );
+
+        // Deleting the newest run brings back the one before it
+        manager.delete_command(CommandId::new(5)).unwrap();
+        assert_eq!(manager.count_command_runs("make").unwrap(), 2);
+        let unique = manager
+            .get_unique_commands_filtered(0, 10, None, &all)
+            .unwrap();
+        assert_eq!(unique[0].command, "git status");
+        assert_eq!(unique[1].id, CommandId::new(3));
+        assert_eq!(manager.get_stats().unwrap().total_commands, 4);
+    }
 }
@@ -6,9 +6,12 @@
 //! - Token extraction and storage
 //! - Import from shell history files
 
+use crate::agent::AgentRun;
+use crate::alias::alias_for_expansion;
 use crate::config::Config;
-use crate::database::{CommandEntry, Database, DatabaseStats};
+use crate::database::{Alias, CommandEntry, Database, DatabaseStats};
 use crate::error::{Error, Result};
+use crate::ignore::IgnorePolicy;
 use crate::redaction::RedactionEngine;
 use chrono::{DateTime, Utc};
 use regex::Regex;
@@ -21,12 +24,15 @@ pub struct HistoryManagerDb {
     config: Config,
     pub(crate) db: Database,
     redaction_engine: RedactionEngine,
+    ignore_policy: IgnorePolicy,
 }
 
 /// Represents a redacted token extracted from a command
 #[derive(Debug, Clone)]
 pub struct ExtractedToken {
     pub token_type: String,
+    /// Rule, detector or pattern that found the token
+    pub pattern: String,
     pub placeholder: String,
     pub original_value: String,
 }
@@ -34,25 +40,20 @@ pub struct ExtractedToken {
 impl HistoryManagerDb {
     /// Create a new database-backed history manager
     pub fn new(config: Config) -> Result<Self> {
-        let redaction_engine = RedactionEngine::with_config(
-            config.redaction.use_builtin_patterns,
-            config.redaction.custom_patterns.clone(),
-            config.redaction.exclude_patterns.clone(),
-            config.redaction.placeholder.clone(),
-            config.redaction.min_redaction_length,
-            config.custom_env_vars.clone(),
-            config.redaction.redact_env_vars,
-        )?;
+        let mut redaction_engine = RedactionEngine::from_config(&config)?;
+        let ignore_policy = IgnorePolicy::from_config(&config)?;
 
         // Get database path from config or default
         let db_path = config.history_file.with_extension("db");
 
         let db = Database::new(&db_path)?;
+        redaction_engine.set_allowlist(db.get_allowlist()?);
 
         Ok(Self {
             config,
             db,
             redaction_engine,
+            ignore_policy,
         })
     }
 
@@ -75,16 +76,86 @@ impl HistoryManagerDb {
         timestamp: Option<DateTime<Utc>>,
         exit_code: Option<i32>,
     ) -> Result<()> {
-        // Check if we should exclude this command
-        if self.config.should_exclude_command(command) {
-            return Ok(());
-        }
+        let directory = env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("<unknown>"))
+            .to_string_lossy()
+            .to_string();
+        self.log_command_in(command, &directory, timestamp, exit_code)
+    }
 
-        let timestamp = timestamp.unwrap_or_else(Utc::now);
+    /// Log a command that ran in `directory` rather than the current
+    /// directory (e.g. a replayed command)
+    pub fn log_command_in(
+        &mut self,
+        command: &str,
+        directory: &str,
+        timestamp: Option<DateTime<Utc>>,
+        exit_code: Option<i32>,
+    ) -> Result<()> {
+        self.record_command(command, directory, timestamp, exit_code, true)?;
+        Ok(())
+    }
+
+    /// The alias `command` could have used instead of spelling out its
+    /// expansion, for the `shell_integration.alias_hints` tip. Commands the
+    /// ignore policy keeps out of history get no tip either.
+    pub fn alias_hint(&self, command: &str) -> Result<Option<Alias>> {
         let directory = env::current_dir()
             .unwrap_or_else(|_| PathBuf::from("<unknown>"))
             .to_string_lossy()
             .to_string();
+        if self.ignore_policy.check(command, &directory).is_some() {
+            return Ok(None);
+        }
+        let aliases = self.db.list_aliases()?;
+        Ok(alias_for_expansion(command, &aliases).cloned())
+    }
+
+    /// Log a command run by an agent, with the details from its hook
+    /// payload. Agent commands are never collapsed into the previous row,
+    /// so each run keeps its own exit code and output sizes.
+    pub fn log_agent_run(&mut self, run: &AgentRun) -> Result<()> {
+        let directory = run.directory.clone().unwrap_or_else(|| {
+            env::current_dir()
+                .unwrap_or_else(|_| PathBuf::from("<unknown>"))
+                .to_string_lossy()
+                .to_string()
+        });
+        if let Some(command_id) = self.record_command(
+            &run.command,
+            &directory,
+            Some(run.timestamp),
+            run.exit_code,
+            false,
+        )? {
+            self.db.store_agent_run(command_id, run)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the ignore policy and redaction, then store the command.
+    /// Returns the new row's ID, or `None` if the command was ignored or
+    /// collapsed into the previous row.
+    fn record_command(
+        &mut self,
+        command: &str,
+        directory: &str,
+        timestamp: Option<DateTime<Utc>>,
+        exit_code: Option<i32>,
+        collapse: bool,
+    ) -> Result<Option<i64>> {
+        let timestamp = timestamp.unwrap_or_else(Utc::now);
+
+        if let Some(reason) = self.ignore_policy.check(command, directory) {
+            debug!("Not logging command: {}", reason);
+            return Ok(None);
+        }
+
+        // Shells log the line as typed, so an alias shows as the first word.
+        // Counted only if an alias by that name exists.
+        if let Some(first_word) = command.split_whitespace().next() {
+            self.db.record_alias_use(first_word, timestamp)?;
+        }
 
         // Redact sensitive information and extract tokens
         let (redacted_command, tokens) =
@@ -102,10 +173,22 @@ impl HistoryManagerDb {
                 (command.to_string(), vec![])
             };
 
+        // Collapse consecutive duplicates into the previous row
+        if collapse
+            && !self.config.shell_integration.log_duplicates
+            && tokens.is_empty()
+            && let Some(id) =
+                self.db
+                    .collapse_duplicate(&redacted_command, directory, timestamp, exit_code)?
+        {
+            debug!("Collapsed duplicate into command {}", id);
+            return Ok(None);
+        }
+
         // Add command to database
         let command_id = self.db.add_command(
             &redacted_command,
-            &directory,
+            directory,
             timestamp,
             !tokens.is_empty(),
             exit_code,
@@ -120,10 +203,21 @@ impl HistoryManagerDb {
                 &token.token_type,
                 &token.placeholder,
                 &token.original_value,
+                &token.pattern,
             )?;
         }
 
-        Ok(())
+        Ok(Some(command_id))
+    }
+
+    /// Redact a command that is kept outside the database (the ephemeral
+    /// buffer). Its tokens are dropped rather than stored.
+    pub fn redact_command(&self, command: &str) -> Result<String> {
+        if self.config.enable_redaction && !self.config.should_skip_redaction(command) {
+            Ok(self.redact_and_extract_tokens(command)?.0)
+        } else {
+            Ok(command.to_string())
+        }
     }
 
     /// Redact a command and extract tokens for storage
@@ -131,29 +225,52 @@ impl HistoryManagerDb {
         let mut tokens = Vec::new();
         let mut redacted = command.to_string();
 
+        // Rule packs and URL/body parsing know exactly where the secret
+        // sits, so they take precedence over the generic patterns below
+        let matches = self.redaction_engine.typed_matches(command);
+        for m in &matches {
+            tokens.push(ExtractedToken {
+                token_type: m.token_type.clone(),
+                pattern: m.rule.clone(),
+                placeholder: format!("<{}:{}>", m.token_type, tokens.len() + 1),
+                original_value: m.value.clone(),
+            });
+        }
+        for (m, token) in matches.iter().zip(&tokens).rev() {
+            redacted.replace_range(m.start..m.end, &token.placeholder);
+        }
+
         // Define patterns for token extraction
         let patterns = vec![
             (
                 r#"(?i)(?:password|passwd|pwd)[\s=:]+['"]?([^\s'"]{3,})['"]?"#,
                 "password",
+                "keyword-password",
             ),
             (
                 r#"(?i)(?:token|api_key|apikey|api-key)[\s=:]+['"]?([^\s'"]{10,})['"]?"#,
                 "api_key",
+                "keyword-api-key",
             ),
             (
                 r#"(?i)(?:secret|secret_key|secretkey)[\s=:]+['"]?([^\s'"]{10,})['"]?"#,
                 "secret",
+                "keyword-secret",
             ),
             (
                 r#"(?i)(?:bearer|authorization)[\s:]+['"]?([^\s'"]{10,})['"]?"#,
                 "bearer_token",
+                "keyword-bearer",
             ),
-            (r#"(?i)--password[=\s]+['"]?([^\s'"]{3,})['"]?"#, "password"),
-            (r#"(?i)-p\s+['"]?([^\s'"]{3,})['"]?"#, "password"),
+            (
+                r#"(?i)--password[=\s]+['"]?([^\s'"]{3,})['"]?"#,
+                "password",
+                "flag-password",
+            ),
+            (r#"(?i)-p\s+['"]?([^\s'"]{3,})['"]?"#, "password", "flag-p"),
         ];
 
-        for (pattern_str, token_type) in patterns {
+        for (pattern_str, token_type, pattern_name) in patterns {
             let re = Regex::new(pattern_str)?;
 
             for caps in re.captures_iter(&redacted.clone()) {
@@ -165,6 +282,19 @@ impl HistoryManagerDb {
                         continue;
                     }
 
+                    // Skip values the user marked as not-a-secret
+                    if self.redaction_engine.is_allowed(&original_value) {
+                        continue;
+                    }
+
+                    // Skip placeholders already inserted by a rule pack
+                    if tokens
+                        .iter()
+                        .any(|t: &ExtractedToken| original_value.contains(&t.placeholder))
+                    {
+                        continue;
+                    }
+
                     // Create placeholder
                     let placeholder = format!("<{}:{}>", token_type, tokens.len() + 1);
 
@@ -173,6 +303,7 @@ impl HistoryManagerDb {
 
                     tokens.push(ExtractedToken {
                         token_type: token_type.to_string(),
+                        pattern: pattern_name.to_string(),
                         placeholder,
                         original_value,
                     });
@@ -215,6 +346,28 @@ impl HistoryManagerDb {
         self.db.get_stats()
     }
 
+    /// Mark stored tokens as not-a-secret, restoring the original text.
+    /// With `placeholder`, only that token of the command is allowed.
+    pub fn allow_tokens(
+        &mut self,
+        command_id: i64,
+        placeholder: Option<&str>,
+    ) -> Result<Vec<crate::database::Token>> {
+        let tokens: Vec<_> = self
+            .get_tokens_for_command(command_id)?
+            .into_iter()
+            .filter(|t| placeholder.is_none_or(|p| t.placeholder == p))
+            .collect();
+
+        let mut allowed = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            allowed.push(self.db.allow_token(token.id)?);
+        }
+        self.redaction_engine
+            .set_allowlist(self.db.get_allowlist()?);
+        Ok(allowed)
+    }
+
     /// Get tokens for a specific command
     pub fn get_tokens_for_command(&self, command_id: i64) -> Result<Vec<crate::database::Token>> {
         self.db
@@ -441,6 +594,7 @@ mod tests {
             ..Default::default()
         };
         config.shell_integration.exclude_commands.clear();
+        config.redaction.rules_dir = Some(temp_dir.path().join("rules.d"));
         (config, temp_dir)
     }
 
@@ -463,6 +617,31 @@ mod tests {
         assert_eq!(stats.total_commands, 1);
     }
 
+    #[test]
+    fn test_alias_use_and_hint() {
+        let (mut config, _temp_dir) = test_config();
+        config.shell_integration.exclude_globs = vec!["git status --porcelain*".to_string()];
+        let mut manager = HistoryManagerDb::new(config).unwrap();
+        manager.db.add_alias("gs", "git status", "").unwrap();
+
+        manager.log_command("gs").unwrap();
+        manager.log_command("gs -b").unwrap();
+        manager.log_command("git status").unwrap();
+        assert_eq!(manager.db.list_aliases().unwrap()[0].use_count, 2);
+
+        let hint = manager.alias_hint("git status --short").unwrap();
+        assert_eq!(hint.map(|a| a.alias), Some("gs".to_string()));
+        assert!(manager.alias_hint("gs").unwrap().is_none());
+        // Ignored commands are not recorded, so they get no tip
+        assert!(manager.alias_hint(" git status").unwrap().is_none());
+        assert!(
+            manager
+                .alias_hint("git status --porcelain")
+                .unwrap()
+                .is_none()
+        );
+    }
+
     #[test]
     fn test_redaction_with_tokens() {
         let (config, _temp_dir) = test_config();
@@ -491,6 +670,136 @@ mod tests {
         assert!(!redacted.contains("abc123xyz456"));
     }
 
+    #[test]
+    fn test_rule_pack_token_extraction() {
+        let (config, _temp_dir) = test_config();
+        let manager = HistoryManagerDb::new(config).unwrap();
+
+        let (redacted, tokens) = manager
+            .redact_and_extract_tokens("openssl rsa -in key.pem -passin pass:changeit")
+            .unwrap();
+
+        assert_eq!(
+            redacted,
+            "openssl rsa -in key.pem -passin pass:<password:1>"
+        );
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].original_value, "changeit");
+    }
+
+    #[test]
+    fn test_url_and_body_tokens_roundtrip() {
+        let (config, _temp_dir) = test_config();
+        let mut manager = HistoryManagerDb::new(config).unwrap();
+
+        let original = r#"curl -d '{"password":"hunter22"}' 'https://x.io/api?access_token=abc123xyz&X-Amz-Signature=f00d'"#;
+        manager.log_command(original).unwrap();
+
+        let commands = manager.get_recent(1).unwrap();
+        assert!(commands[0].command.contains("<password:1>"));
+        assert!(commands[0].command.contains("access_token=<api_key:2>"));
+        assert!(
+            commands[0]
+                .command
+                .contains("X-Amz-Signature=<signature:3>")
+        );
+
+        // Replacing placeholders with stored tokens reconstructs the command
+        let mut restored = commands[0].command.clone();
+        for token in manager
+            .get_tokens_for_command(commands[0].id.as_i64())
+            .unwrap()
+        {
+            restored = restored.replace(&token.placeholder, &token.original_value);
+        }
+        assert_eq!(restored, original);
+    }
+
+    #[test]
+    fn test_allow_tokens_stops_future_redaction() {
+        let (config, _temp_dir) = test_config();
+        let mut manager = HistoryManagerDb::new(config).unwrap();
+
+        manager.log_command("deploy --token=build1234567").unwrap();
+        let id = manager.get_recent(1).unwrap()[0].id.as_i64();
+        let allowed = manager.allow_tokens(id, None).unwrap();
+        assert_eq!(allowed.len(), 1);
+        assert_eq!(
+            manager.get_recent(1).unwrap()[0].command,
+            "deploy --token=build1234567"
+        );
+
+        manager.log_command("rerun --token=build1234567").unwrap();
+        let latest = &manager.get_recent(1).unwrap()[0];
+        assert_eq!(latest.command, "rerun --token=build1234567");
+        assert!(!latest.redacted);
+    }
+
+    #[test]
+    fn test_duplicate_collapsing() {
+        let (mut config, _temp_dir) = test_config();
+        config.shell_integration.log_duplicates = false;
+        let mut manager = HistoryManagerDb::new(config).unwrap();
+
+        manager.log_command("echo hello").unwrap();
+        manager.log_command("echo hello").unwrap(); // Duplicate
+        manager.log_command("echo world").unwrap();
+        manager.log_command("echo hello").unwrap(); // Not consecutive
+
+        let commands = manager.get_all_commands().unwrap();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(manager.db.get_repeat_count(commands[0].id).unwrap(), 2);
+        assert_eq!(manager.db.get_repeat_count(commands[2].id).unwrap(), 1);
+
+        // Collapsed runs still count towards frequency
+        assert_eq!(
+            manager.db.get_frequent_commands(10).unwrap(),
+            vec![("echo hello".to_string(), 3), ("echo world".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_duplicates_kept_when_enabled() {
+        let (mut config, _temp_dir) = test_config();
+        config.shell_integration.log_duplicates = true;
+        let mut manager = HistoryManagerDb::new(config).unwrap();
+
+        manager.log_command("echo hello").unwrap();
+        manager.log_command("echo hello").unwrap();
+
+        assert_eq!(manager.get_all_commands().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_commands_with_secrets_not_collapsed() {
+        let (mut config, _temp_dir) = test_config();
+        config.shell_integration.log_duplicates = false;
+        let mut manager = HistoryManagerDb::new(config).unwrap();
+
+        manager.log_command("mysql -p secret1").unwrap();
+        manager.log_command("mysql -p secret1").unwrap();
+
+        assert_eq!(manager.get_all_commands().unwrap().len(), 2);
+        assert_eq!(manager.db.get_all_tokens().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_ignore_policy() {
+        let (mut config, _temp_dir) = test_config();
+        config.shell_integration.exclude_globs = vec!["vault *".to_string()];
+        config.shell_integration.min_command_length = 3;
+        let mut manager = HistoryManagerDb::new(config).unwrap();
+
+        manager.log_command(" echo private").unwrap();
+        manager.log_command("vault read secret/x").unwrap();
+        manager.log_command("vi").unwrap();
+        manager.log_command("echo public").unwrap();
+
+        let commands = manager.get_all_commands().unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].command, "echo public");
+    }
+
     #[test]
     fn test_search() {
         let (config, _temp_dir) = test_config();
@@ -0,0 +1,279 @@
+//! Ignore policy for zam
+//!
+//! Decides which commands are not recorded: space-prefixed commands (shell
+//! privacy convention), commands shorter than the configured minimum,
+//! excluded programs, glob and regex exclude lists, and commands run inside
+//! excluded directories. Consecutive duplicates are collapsed by the
+//! backends themselves since that needs the previous entry.
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::forget::is_forget_command;
+use regex::Regex;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Why a command was not recorded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IgnoreReason {
+    /// Starts with a space and `log_space_prefixed` is off
+    SpacePrefixed,
+    /// Shorter than `min_command_length`
+    TooShort,
+    /// Runs a program from `exclude_commands`
+    ExcludedCommand(String),
+    /// Matches an entry of `exclude_globs` or `exclude_regexes`
+    ExcludedPattern(String),
+    /// Run inside one of `exclude_directories`
+    ExcludedDirectory(PathBuf),
+    /// `zam forget` carries the secret being purged
+    Forget,
+}
+
+impl fmt::Display for IgnoreReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IgnoreReason::SpacePrefixed => write!(f, "space-prefixed"),
+            IgnoreReason::TooShort => write!(f, "shorter than minimum length"),
+            IgnoreReason::ExcludedCommand(c) => write!(f, "excluded command '{}'", c),
+            IgnoreReason::ExcludedPattern(p) => write!(f, "matches exclude pattern '{}'", p),
+            IgnoreReason::ExcludedDirectory(d) => {
+                write!(f, "in excluded directory {}", d.display())
+            }
+            IgnoreReason::Forget => write!(f, "zam forget invocation"),
+        }
+    }
+}
+
+/// Compiled ignore policy built from the shell integration settings
+#[derive(Debug, Clone)]
+pub struct IgnorePolicy {
+    log_space_prefixed: bool,
+    min_command_length: usize,
+    exclude_commands: Vec<String>,
+    exclude_patterns: Vec<(String, Regex)>,
+    exclude_directories: Vec<PathBuf>,
+}
+
+impl IgnorePolicy {
+    /// Build the policy from configuration, compiling globs and regexes
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let settings = &config.shell_integration;
+
+        let mut exclude_patterns = Vec::new();
+        for glob in &settings.exclude_globs {
+            exclude_patterns.push((glob.clone(), glob_to_regex(glob)?));
+        }
+        for pattern in &settings.exclude_regexes {
+            let regex = Regex::new(pattern).map_err(|_| {
+                Error::config_validation(
+                    "shell_integration.exclude_regexes",
+                    &format!("invalid regex '{}'", pattern),
+                )
+            })?;
+            exclude_patterns.push((pattern.clone(), regex));
+        }
+
+        Ok(Self {
+            log_space_prefixed: settings.log_space_prefixed,
+            min_command_length: settings.min_command_length,
+            exclude_commands: settings.exclude_commands.clone(),
+            exclude_patterns,
+            exclude_directories: settings
+                .exclude_directories
+                .iter()
+                .map(|d| expand_home(d))
+                .collect(),
+        })
+    }
+
+    /// Return why `command` run in `directory` must not be recorded, or
+    /// `None` to record it
+    pub fn check(&self, command: &str, directory: &str) -> Option<IgnoreReason> {
+        if is_forget_command(command) {
+            return Some(IgnoreReason::Forget);
+        }
+
+        if !self.log_space_prefixed && command.starts_with(' ') {
+            return Some(IgnoreReason::SpacePrefixed);
+        }
+
+        let trimmed = command.trim();
+        if trimmed.len() < self.min_command_length {
+            return Some(IgnoreReason::TooShort);
+        }
+
+        if let Some(excluded) = self
+            .exclude_commands
+            .iter()
+            .find(|e| trimmed == e.as_str() || trimmed.starts_with(&format!("{e} ")))
+        {
+            return Some(IgnoreReason::ExcludedCommand(excluded.clone()));
+        }
+
+        if let Some((source, _)) = self
+            .exclude_patterns
+            .iter()
+            .find(|(_, regex)| regex.is_match(trimmed))
+        {
+            return Some(IgnoreReason::ExcludedPattern(source.clone()));
+        }
+
+        let directory = Path::new(directory);
+        if let Some(excluded) = self
+            .exclude_directories
+            .iter()
+            .find(|d| directory.starts_with(d))
+        {
+            return Some(IgnoreReason::ExcludedDirectory(excluded.clone()));
+        }
+
+        None
+    }
+}
+
+/// Translate a shell-style glob (`*`, `?`, `[...]`) into an anchored regex
+/// matching the whole command
+pub fn glob_to_regex(glob: &str) -> Result<Regex> {
+    let mut pattern = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '[' => {
+                pattern.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    pattern.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        pattern.push('\\');
+                    }
+                    pattern.push(c);
+                }
+                pattern.push(']');
+            }
+            c => pattern.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    pattern.push('$');
+    Regex::new(&pattern).map_err(|_| {
+        Error::config_validation(
+            "shell_integration.exclude_globs",
+            &format!("invalid glob '{}'", glob),
+        )
+    })
+}
+
+/// Expand a leading `~` to the home directory
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix('~'), home::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest.trim_start_matches('/')),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(configure: impl FnOnce(&mut Config)) -> IgnorePolicy {
+        let mut config = Config::default();
+        configure(&mut config);
+        IgnorePolicy::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn test_space_prefixed() {
+        let p = policy(|_| {});
+        assert_eq!(
+            p.check(" echo secret", "/"),
+            Some(IgnoreReason::SpacePrefixed)
+        );
+        assert_eq!(p.check("echo secret", "/"), None);
+
+        let p = policy(|c| c.shell_integration.log_space_prefixed = true);
+        assert_eq!(p.check(" echo secret", "/"), None);
+    }
+
+    #[test]
+    fn test_default_excludes() {
+        let p = policy(|_| {});
+
+        // Should exclude commands in the exclude list
+        assert!(p.check("ls -la", "").is_some());
+        assert!(p.check("cd /tmp", "").is_some());
+
+        // Should not exclude other commands
+        assert!(p.check("echo hello", "").is_none());
+        assert!(p.check("grep pattern file", "").is_none());
+
+        // Never log the secret passed to `zam forget`
+        assert!(p.check("zam forget --value hunter2", "").is_some());
+    }
+
+    #[test]
+    fn test_min_length_and_excluded_commands() {
+        let p = policy(|c| c.shell_integration.min_command_length = 3);
+        assert_eq!(p.check("vi", "/"), Some(IgnoreReason::TooShort));
+        assert_eq!(
+            p.check("ls -la", "/"),
+            Some(IgnoreReason::ExcludedCommand("ls".to_string()))
+        );
+        // Only whole program names are excluded
+        assert_eq!(p.check("lsof -i", "/"), None);
+        assert_eq!(
+            p.check("zam forget --value x", "/"),
+            Some(IgnoreReason::Forget)
+        );
+    }
+
+    #[test]
+    fn test_glob_and_regex_excludes() {
+        let p = policy(|c| {
+            c.shell_integration.exclude_globs = vec!["git st*".to_string(), "echo ?".to_string()];
+            c.shell_integration.exclude_regexes = vec![r"^vault\s+write".to_string()];
+        });
+        assert!(matches!(
+            p.check("git status -s", "/"),
+            Some(IgnoreReason::ExcludedPattern(_))
+        ));
+        assert!(p.check("echo a", "/").is_some());
+        assert_eq!(p.check("echo ab", "/"), None);
+        assert!(p.check("vault write secret/x v=1", "/").is_some());
+        assert_eq!(p.check("git commit", "/"), None);
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let re = glob_to_regex("kubectl [!g]*").unwrap();
+        assert!(re.is_match("kubectl apply -f x.yaml"));
+        assert!(!re.is_match("kubectl get pods"));
+        assert!(glob_to_regex("a.b").unwrap().is_match("a.b"));
+        assert!(!glob_to_regex("a.b").unwrap().is_match("axb"));
+    }
+
+    #[test]
+    fn test_excluded_directories() {
+        let p = policy(|c| c.shell_integration.exclude_directories = vec!["/srv/secrets".into()]);
+        assert_eq!(
+            p.check("cat key.pem", "/srv/secrets/prod"),
+            Some(IgnoreReason::ExcludedDirectory(PathBuf::from(
+                "/srv/secrets"
+            )))
+        );
+        assert_eq!(p.check("cat key.pem", "/srv/secrets-public"), None);
+    }
+
+    #[test]
+    fn test_invalid_patterns_rejected() {
+        let mut config = Config::default();
+        config.shell_integration.exclude_regexes = vec!["(".to_string()];
+        assert!(IgnorePolicy::from_config(&config).is_err());
+    }
+}
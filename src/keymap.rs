@@ -0,0 +1,688 @@
+//! Key bindings for the TUI
+//!
+//! Every shortcut is an [`Action`] bound to one or more keys. A preset
+//! gives the starting layout: `default` is zam's own, `emacs` adds readline
+//! motions and `vi` adds a normal mode entered with Esc, where plain keys
+//! are commands instead of filter text. `tui.keys` and `tui.normal_keys` in
+//! the configuration rebind single actions on top of the preset.
+//!
+//! Keys are written like `ctrl-d`, `alt-enter`, `shift-tab`, `f2`, `space`
+//! or a single character. A binding on a plain character only fires while
+//! the filter is empty; otherwise the character is typed.
+
+use crate::config::TuiConfig;
+use crate::error::{Error, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Starting layout of the key bindings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeymapPreset {
+    #[default]
+    Default,
+    Emacs,
+    Vi,
+}
+
+/// Something a key can do in the TUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Run,
+    Insert,
+    Edit,
+    Mark,
+    Back,
+    Quit,
+    NormalMode,
+    InsertMode,
+    Up,
+    Down,
+    First,
+    Last,
+    PrevPage,
+    NextPage,
+    PrevTab,
+    NextTab,
+    /// Jump to the tab with this index
+    JumpTab(usize),
+    History,
+    Local,
+    Session,
+    Help,
+    RelativeTime,
+    Delete,
+    Actors,
+    Descendants,
+    Reveal,
+    AllowToken,
+    Yank,
+    Preview,
+    NewAlias,
+    Suggestions,
+}
+
+/// Group an action is listed under on the help tab
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Search,
+    Navigation,
+    QuickJump,
+    Actions,
+}
+
+impl Section {
+    pub const ALL: [Section; 4] = [
+        Section::Search,
+        Section::Navigation,
+        Section::QuickJump,
+        Section::Actions,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            Section::Search => "search",
+            Section::Navigation => "navigation",
+            Section::QuickJump => "quick jump",
+            Section::Actions => "actions",
+        }
+    }
+}
+
+impl Action {
+    /// Every action, in help tab order
+    pub const ALL: [Action; 38] = [
+        Action::Run,
+        Action::Insert,
+        Action::Edit,
+        Action::Mark,
+        Action::Back,
+        Action::Quit,
+        Action::NormalMode,
+        Action::InsertMode,
+        Action::Up,
+        Action::Down,
+        Action::First,
+        Action::Last,
+        Action::PrevPage,
+        Action::NextPage,
+        Action::PrevTab,
+        Action::NextTab,
+        Action::JumpTab(0),
+        Action::JumpTab(1),
+        Action::JumpTab(2),
+        Action::JumpTab(3),
+        Action::JumpTab(4),
+        Action::JumpTab(5),
+        Action::JumpTab(6),
+        Action::JumpTab(7),
+        Action::History,
+        Action::Local,
+        Action::Session,
+        Action::Help,
+        Action::RelativeTime,
+        Action::Delete,
+        Action::Actors,
+        Action::Descendants,
+        Action::Reveal,
+        Action::AllowToken,
+        Action::Yank,
+        Action::Preview,
+        Action::NewAlias,
+        Action::Suggestions,
+    ];
+
+    /// Name used in `tui.keys`
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Run => "run",
+            Action::Insert => "insert",
+            Action::Edit => "edit",
+            Action::Mark => "mark",
+            Action::Back => "back",
+            Action::Quit => "quit",
+            Action::NormalMode => "normal-mode",
+            Action::InsertMode => "insert-mode",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::PrevPage => "prev-page",
+            Action::NextPage => "next-page",
+            Action::PrevTab => "prev-tab",
+            Action::NextTab => "next-tab",
+            Action::JumpTab(0) => "tab-1",
+            Action::JumpTab(1) => "tab-2",
+            Action::JumpTab(2) => "tab-3",
+            Action::JumpTab(3) => "tab-4",
+            Action::JumpTab(4) => "tab-5",
+            Action::JumpTab(5) => "tab-6",
+            Action::JumpTab(6) => "tab-7",
+            Action::JumpTab(_) => "tab-8",
+            Action::History => "history",
+            Action::Local => "local",
+            Action::Session => "session",
+            Action::Help => "help",
+            Action::RelativeTime => "relative-time",
+            Action::Delete => "delete",
+            Action::Actors => "actors",
+            Action::Descendants => "descendants",
+            Action::Reveal => "reveal",
+            Action::AllowToken => "allow-token",
+            Action::Yank => "yank",
+            Action::Preview => "preview",
+            Action::NewAlias => "new-alias",
+            Action::Suggestions => "suggestions",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// What the action does, for the help tab
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::Run => "run command (open session on Sessions tab)",
+            Action::Insert => "insert into prompt without running",
+            Action::Edit => "edit command before running (edit alias on Aliases tab)",
+            Action::Mark => "mark for multi-select",
+            Action::Back => "clear filter / close view / quit",
+            Action::Quit => "quit",
+            Action::NormalMode => "vi normal mode",
+            Action::InsertMode => "back to typing the filter",
+            Action::Up => "move up",
+            Action::Down => "move down",
+            Action::First => "first row",
+            Action::Last => "last row",
+            Action::PrevPage => "previous page (previous tab if not paged)",
+            Action::NextPage => "next page (next tab if not paged)",
+            Action::PrevTab => "previous tab",
+            Action::NextTab => "next tab",
+            Action::JumpTab(_) => "jump to tab",
+            Action::History => "History",
+            Action::Local => "Local (cwd)",
+            Action::Session => "current session",
+            Action::Help => "this help",
+            Action::RelativeTime => "toggle relative time",
+            Action::Delete => "delete selected",
+            Action::Actors => "cycle all / human / agent commands (History tab)",
+            Action::Descendants => "include child sessions (session view)",
+            Action::Reveal => "reveal token values (Tokens tab)",
+            Action::AllowToken => "mark token as not a secret (Tokens tab)",
+            Action::Yank => "copy selected command to clipboard",
+            Action::Preview => "toggle preview pane (History, Local, session view)",
+            Action::NewAlias => "create an alias for the selected command",
+            Action::Suggestions => "toggle suggested aliases (Aliases tab)",
+        }
+    }
+
+    pub fn section(self) -> Section {
+        match self {
+            Action::Run
+            | Action::Insert
+            | Action::Edit
+            | Action::Mark
+            | Action::Back
+            | Action::Quit
+            | Action::NormalMode
+            | Action::InsertMode => Section::Search,
+            Action::Up
+            | Action::Down
+            | Action::First
+            | Action::Last
+            | Action::PrevPage
+            | Action::NextPage
+            | Action::PrevTab
+            | Action::NextTab
+            | Action::JumpTab(_) => Section::Navigation,
+            Action::History | Action::Local | Action::Session | Action::Help => Section::QuickJump,
+            _ => Section::Actions,
+        }
+    }
+}
+
+/// A key with its modifiers, as bound in a [`Keymap`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        // Shift is already in the character (`G`) or the code (BackTab)
+        let modifiers = match code {
+            KeyCode::Char(_) | KeyCode::BackTab => modifiers - KeyModifiers::SHIFT,
+            _ => modifiers,
+        };
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => {
+                KeyCode::Char(c.to_ascii_lowercase())
+            }
+            code => code,
+        };
+        Self {
+            code,
+            modifiers: modifiers
+                & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT),
+        }
+    }
+
+    pub fn from_event(event: &KeyEvent) -> Self {
+        Self::new(event.code, event.modifiers)
+    }
+
+    /// Parse a key as written in the configuration (`ctrl-d`, `alt-enter`)
+    pub fn parse(spec: &str) -> Option<Key> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec;
+        loop {
+            let lower = rest.to_ascii_lowercase();
+            let (modifier, len) = if lower.starts_with("ctrl-") {
+                (KeyModifiers::CONTROL, 5)
+            } else if lower.starts_with("alt-") {
+                (KeyModifiers::ALT, 4)
+            } else if lower.starts_with("shift-") {
+                (KeyModifiers::SHIFT, 6)
+            } else {
+                break;
+            };
+            if rest.len() == len {
+                break;
+            }
+            modifiers |= modifier;
+            rest = &rest[len..];
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match rest.to_ascii_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                "delete" | "del" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                name => {
+                    let n: u8 = name.strip_prefix('f')?.parse().ok()?;
+                    if !(1..=12).contains(&n) {
+                        return None;
+                    }
+                    KeyCode::F(n)
+                }
+            },
+        };
+        Some(Key::new(code, modifiers))
+    }
+
+    /// Typed text rather than a shortcut: bound plain characters only act
+    /// while the filter is empty
+    pub fn is_printable(&self) -> bool {
+        matches!(self.code, KeyCode::Char(_)) && self.modifiers.is_empty()
+    }
+
+    /// How the key is shown on the help tab (`^D`, `Alt+Enter`)
+    pub fn label(&self) -> String {
+        let name = match self.code {
+            KeyCode::Char(' ') => "Space".to_string(),
+            KeyCode::Char(c) if self.modifiers == KeyModifiers::CONTROL => {
+                return format!("^{}", c.to_ascii_uppercase());
+            }
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => "Enter".into(),
+            KeyCode::Esc => "Esc".into(),
+            KeyCode::Tab => "Tab".into(),
+            KeyCode::BackTab => "Shift+Tab".into(),
+            KeyCode::Backspace => "Bksp".into(),
+            KeyCode::Delete => "Del".into(),
+            KeyCode::Insert => "Ins".into(),
+            KeyCode::Up => "↑".into(),
+            KeyCode::Down => "↓".into(),
+            KeyCode::Left => "←".into(),
+            KeyCode::Right => "→".into(),
+            KeyCode::Home => "Home".into(),
+            KeyCode::End => "End".into(),
+            KeyCode::PageUp => "PgUp".into(),
+            KeyCode::PageDown => "PgDn".into(),
+            KeyCode::F(n) => format!("F{}", n),
+            _ => "?".into(),
+        };
+        if name == "Space" && self.modifiers == KeyModifiers::CONTROL {
+            return "^Space".into();
+        }
+        let mut label = String::new();
+        for (modifier, prefix) in [
+            (KeyModifiers::CONTROL, "Ctrl+"),
+            (KeyModifiers::ALT, "Alt+"),
+            (KeyModifiers::SHIFT, "Shift+"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                label.push_str(prefix);
+            }
+        }
+        label + &name
+    }
+}
+
+/// Bindings of the plain layout
+const DEFAULT_KEYS: &[(Action, &[&str])] = &[
+    (Action::Run, &["enter"]),
+    (Action::Insert, &["alt-enter"]),
+    (Action::Edit, &["ctrl-e"]),
+    (Action::Mark, &["space", "ctrl-space"]),
+    (Action::Back, &["esc"]),
+    (Action::Quit, &["ctrl-c"]),
+    (Action::Up, &["up"]),
+    (Action::Down, &["down"]),
+    (Action::First, &["home"]),
+    (Action::Last, &["end"]),
+    (Action::PrevPage, &["left"]),
+    (Action::NextPage, &["right"]),
+    (Action::PrevTab, &["shift-tab"]),
+    (Action::NextTab, &["tab"]),
+    (Action::JumpTab(0), &["alt-1"]),
+    (Action::JumpTab(1), &["alt-2"]),
+    (Action::JumpTab(2), &["alt-3"]),
+    (Action::JumpTab(3), &["alt-4"]),
+    (Action::JumpTab(4), &["alt-5"]),
+    (Action::JumpTab(5), &["alt-6"]),
+    (Action::JumpTab(6), &["alt-7"]),
+    (Action::JumpTab(7), &["alt-8"]),
+    (Action::History, &["ctrl-h"]),
+    (Action::Local, &["ctrl-l"]),
+    (Action::Session, &["ctrl-s"]),
+    (Action::Help, &["?"]),
+    (Action::RelativeTime, &["ctrl-t"]),
+    (Action::Delete, &["ctrl-d", "delete"]),
+    (Action::Actors, &["ctrl-g"]),
+    (Action::Descendants, &["ctrl-o"]),
+    (Action::Reveal, &["ctrl-v"]),
+    (Action::AllowToken, &["ctrl-a"]),
+    (Action::Yank, &["ctrl-y"]),
+    (Action::Preview, &["ctrl-p"]),
+    (Action::NewAlias, &["alt-a"]),
+    (Action::Suggestions, &["alt-s"]),
+];
+
+/// Changes of the emacs preset: readline motions, ^G to back out
+const EMACS_KEYS: &[(Action, &[&str])] = &[
+    (Action::Up, &["up", "ctrl-p"]),
+    (Action::Down, &["down", "ctrl-n"]),
+    (Action::First, &["home", "alt-<"]),
+    (Action::Last, &["end", "alt->"]),
+    (Action::Back, &["esc", "ctrl-g"]),
+    (Action::Actors, &["alt-g"]),
+    (Action::Preview, &["alt-p"]),
+];
+
+/// Changes of the vi preset while typing the filter
+const VI_INSERT_KEYS: &[(Action, &[&str])] = &[
+    (Action::NormalMode, &["esc"]),
+    (Action::Up, &["up", "ctrl-k"]),
+    (Action::Down, &["down", "ctrl-j"]),
+];
+
+/// vi normal mode
+const VI_NORMAL_KEYS: &[(Action, &[&str])] = &[
+    (Action::InsertMode, &["i", "a", "/"]),
+    (Action::Back, &["esc"]),
+    (Action::Quit, &["q", "ctrl-c"]),
+    (Action::Run, &["enter"]),
+    (Action::Insert, &["alt-enter"]),
+    (Action::Edit, &["e"]),
+    (Action::Mark, &["space", "m"]),
+    (Action::Up, &["k", "up"]),
+    (Action::Down, &["j", "down"]),
+    (Action::First, &["g", "home"]),
+    (Action::Last, &["G", "end"]),
+    (Action::PrevPage, &["h", "left"]),
+    (Action::NextPage, &["l", "right"]),
+    (Action::PrevTab, &["shift-tab"]),
+    (Action::NextTab, &["tab"]),
+    (Action::JumpTab(0), &["1"]),
+    (Action::JumpTab(1), &["2"]),
+    (Action::JumpTab(2), &["3"]),
+    (Action::JumpTab(3), &["4"]),
+    (Action::JumpTab(4), &["5"]),
+    (Action::JumpTab(5), &["6"]),
+    (Action::JumpTab(6), &["7"]),
+    (Action::JumpTab(7), &["8"]),
+    (Action::Help, &["?"]),
+    (Action::Delete, &["d"]),
+    (Action::Yank, &["y"]),
+    (Action::Preview, &["p"]),
+    (Action::RelativeTime, &["t"]),
+    (Action::NewAlias, &["A"]),
+    (Action::Suggestions, &["S"]),
+];
+
+/// Active key bindings
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    /// Bindings while typing the filter, in help order
+    bindings: Vec<(Key, Action)>,
+    /// Bindings of vi normal mode (empty without it)
+    normal: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::from_config(&TuiConfig::default()).expect("built-in keymap is valid")
+    }
+}
+
+impl Keymap {
+    /// Build the keymap of `config`: its preset, then `tui.keys` and
+    /// `tui.normal_keys`
+    pub fn from_config(config: &TuiConfig) -> Result<Self> {
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            normal: Vec::new(),
+        };
+        let mut bind = |normal: bool, table: &[(Action, &[&str])]| {
+            for (action, keys) in table {
+                let keys: Vec<Key> = keys.iter().filter_map(|k| Key::parse(k)).collect();
+                keymap.bind(normal, *action, &keys);
+            }
+        };
+        bind(false, DEFAULT_KEYS);
+        match config.keymap {
+            KeymapPreset::Default => {}
+            KeymapPreset::Emacs => bind(false, EMACS_KEYS),
+            KeymapPreset::Vi => {
+                bind(false, VI_INSERT_KEYS);
+                bind(true, VI_NORMAL_KEYS);
+            }
+        }
+
+        keymap.bind_config("tui.keys", false, &config.keys)?;
+        if !config.normal_keys.is_empty() && config.keymap != KeymapPreset::Vi {
+            return Err(Error::config_validation(
+                "tui.normal_keys",
+                "normal mode needs the vi keymap",
+            ));
+        }
+        keymap.bind_config("tui.normal_keys", true, &config.normal_keys)?;
+        Ok(keymap)
+    }
+
+    fn bind_config(
+        &mut self,
+        field: &str,
+        normal: bool,
+        keys: &BTreeMap<String, Vec<String>>,
+    ) -> Result<()> {
+        for (name, specs) in keys {
+            let action = Action::from_name(name).ok_or_else(|| {
+                Error::config_validation(field, format!("unknown action '{}'", name).as_str())
+            })?;
+            let keys = specs
+                .iter()
+                .map(|spec| {
+                    Key::parse(spec).ok_or_else(|| {
+                        Error::config_validation(
+                            field,
+                            format!("invalid key '{}' for {}", spec, name).as_str(),
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            self.bind(normal, action, &keys);
+        }
+        Ok(())
+    }
+
+    /// Make `keys` the keys of `action`, taking them from other actions
+    fn bind(&mut self, normal: bool, action: Action, keys: &[Key]) {
+        let table = if normal {
+            &mut self.normal
+        } else {
+            &mut self.bindings
+        };
+        table.retain(|(key, bound)| *bound != action && !keys.contains(key));
+        table.extend(keys.iter().map(|key| (*key, action)));
+    }
+
+    /// Whether the keymap has a vi normal mode
+    pub fn has_normal_mode(&self) -> bool {
+        !self.normal.is_empty()
+    }
+
+    /// Action bound to `key`. Normal mode falls back to the shortcuts that
+    /// are not plain characters.
+    pub fn lookup(&self, key: Key, normal: bool) -> Option<Action> {
+        let find = |table: &[(Key, Action)]| {
+            table
+                .iter()
+                .find(|(bound, _)| *bound == key)
+                .map(|(_, action)| *action)
+        };
+        if normal {
+            find(&self.normal).or_else(|| {
+                find(&self.bindings).filter(|a| !key.is_printable() && *a != Action::NormalMode)
+            })
+        } else {
+            find(&self.bindings)
+        }
+    }
+
+    /// Keys bound to `action`, in binding order
+    pub fn keys_for(&self, action: Action, normal: bool) -> Vec<Key> {
+        let table = if normal { &self.normal } else { &self.bindings };
+        table
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(code: KeyCode, modifiers: KeyModifiers) -> Key {
+        Key::from_event(&KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            Key::parse("ctrl-d"),
+            Some(event(KeyCode::Char('d'), KeyModifiers::CONTROL))
+        );
+        assert_eq!(
+            Key::parse("alt-enter"),
+            Some(event(KeyCode::Enter, KeyModifiers::ALT))
+        );
+        assert_eq!(
+            Key::parse("shift-tab"),
+            Some(event(KeyCode::BackTab, KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            Key::parse("G"),
+            Some(event(KeyCode::Char('G'), KeyModifiers::SHIFT))
+        );
+        assert_eq!(
+            Key::parse("alt--"),
+            Some(event(KeyCode::Char('-'), KeyModifiers::ALT))
+        );
+        assert_eq!(Key::parse("f5").unwrap().label(), "F5");
+        assert_eq!(Key::parse("ctrl-space").unwrap().label(), "^Space");
+        assert_eq!(Key::parse("ctrl-x").unwrap().label(), "^X");
+        assert_eq!(Key::parse("alt-enter").unwrap().label(), "Alt+Enter");
+        assert_eq!(Key::parse("hyper-x"), None);
+        assert_eq!(Key::parse("f13"), None);
+    }
+
+    #[test]
+    fn test_presets_and_overrides() {
+        let ctrl = |c| event(KeyCode::Char(c), KeyModifiers::CONTROL);
+        let plain = |c| event(KeyCode::Char(c), KeyModifiers::NONE);
+
+        let keymap = Keymap::default();
+        assert_eq!(keymap.lookup(ctrl('d'), false), Some(Action::Delete));
+        assert_eq!(keymap.lookup(ctrl('p'), false), Some(Action::Preview));
+        assert!(!keymap.has_normal_mode());
+
+        let mut config = TuiConfig {
+            keymap: KeymapPreset::Emacs,
+            ..Default::default()
+        };
+        config
+            .keys
+            .insert("yank".into(), vec!["ctrl-d".into(), "alt-y".into()]);
+        let keymap = Keymap::from_config(&config).unwrap();
+        assert_eq!(keymap.lookup(ctrl('p'), false), Some(Action::Up));
+        assert_eq!(keymap.lookup(ctrl('d'), false), Some(Action::Yank));
+        assert_eq!(keymap.lookup(ctrl('y'), false), None);
+        let delete: Vec<String> = keymap
+            .keys_for(Action::Delete, false)
+            .iter()
+            .map(Key::label)
+            .collect();
+        assert_eq!(delete, vec!["Del"]);
+
+        config.keymap = KeymapPreset::Vi;
+        config.keys.clear();
+        let keymap = Keymap::from_config(&config).unwrap();
+        let esc = event(KeyCode::Esc, KeyModifiers::NONE);
+        assert_eq!(keymap.lookup(esc, false), Some(Action::NormalMode));
+        assert_eq!(keymap.lookup(esc, true), Some(Action::Back));
+        assert_eq!(keymap.lookup(plain('j'), true), Some(Action::Down));
+        assert_eq!(keymap.lookup(plain('j'), false), None);
+        assert_eq!(keymap.lookup(ctrl('t'), true), Some(Action::RelativeTime));
+        assert_eq!(keymap.lookup(plain('z'), true), None);
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let mut config = TuiConfig::default();
+        config.keys.insert("launch".into(), vec!["ctrl-x".into()]);
+        assert!(Keymap::from_config(&config).is_err());
+
+        config.keys.clear();
+        config.keys.insert("yank".into(), vec!["ctrl-".into()]);
+        assert!(Keymap::from_config(&config).is_err());
+
+        config.keys.clear();
+        config.normal_keys.insert("yank".into(), vec!["Y".into()]);
+        assert!(Keymap::from_config(&config).is_err());
+        config.keymap = KeymapPreset::Vi;
+        assert!(Keymap::from_config(&config).is_ok());
+    }
+}
@@ -19,16 +19,30 @@
 
 use std::path::PathBuf;
 
+pub mod agent;
+pub mod alias;
+pub mod audit;
 pub mod backend;
 pub mod cli;
+pub mod clipboard;
 pub mod config;
 pub mod database;
 pub mod error;
+pub mod forget;
 pub mod history;
 pub mod history_db;
+pub mod ignore;
+pub mod keymap;
+pub mod mcp;
 pub mod prelude;
+pub mod private;
 pub mod redaction;
+pub mod replay;
+pub mod rules;
 pub mod search;
+pub mod session;
+pub mod structured;
+pub mod transcript;
 pub mod tui;
 pub mod types;
 
@@ -0,0 +1,309 @@
+//! Model Context Protocol server for zam
+//!
+//! `zam mcp` lets coding agents query shell history and aliases instead of
+//! guessing. It speaks JSON-RPC 2.0 over stdio, one message per line, and
+//! offers the tools in [`TOOL_NAMES`]. Commands are returned as stored, so
+//! redaction placeholders stay in place: the tokens table is never read.
+//! `mcp.tools` and `mcp.hosts` in the configuration limit what is exposed.
+
+use crate::config::McpConfig;
+use crate::database::{CommandEntry, CommandFilter, Database};
+use crate::error::Result;
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+
+/// Tools the server can offer
+pub const TOOL_NAMES: &[&str] = &[
+    "search_history",
+    "recent_in_directory",
+    "list_aliases",
+    "session_commands",
+];
+
+/// Protocol revisions the server understands, newest first
+pub const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Commands returned when a tool call has no `limit`
+const DEFAULT_LIMIT: usize = 20;
+
+/// Upper bound on `limit`
+const MAX_LIMIT: usize = 500;
+
+// JSON-RPC error codes
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// A JSON-RPC error response
+#[derive(Debug)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// MCP server over a history database
+pub struct McpServer<'a> {
+    db: &'a Database,
+    tools: Vec<String>,
+    /// Hostnames whose commands are exposed (`None` for all)
+    hostnames: Option<Vec<String>>,
+}
+
+impl<'a> McpServer<'a> {
+    pub fn new(db: &'a Database, config: &McpConfig) -> Self {
+        let hostnames = if config.hosts.iter().any(|h| h == "*") {
+            None
+        } else if config.hosts.is_empty() {
+            Some(vec![
+                hostname::get()
+                    .map(|h| h.to_string_lossy().to_string())
+                    .unwrap_or_else(|_| "unknown".to_string()),
+            ])
+        } else {
+            Some(config.hosts.clone())
+        };
+        Self {
+            db,
+            tools: config.tools.clone(),
+            hostnames,
+        }
+    }
+
+    /// Answer messages read from `input` until it closes
+    pub fn serve(&self, input: impl BufRead, mut output: impl Write) -> Result<()> {
+        for line in input.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle_message(&line) {
+                writeln!(output, "{}", response)?;
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle one JSON-RPC message. Notifications get no response.
+    pub fn handle_message(&self, line: &str) -> Option<Value> {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => {
+                return Some(error_response(
+                    Value::Null,
+                    RpcError::new(PARSE_ERROR, e.to_string()),
+                ));
+            }
+        };
+        let id = message.get("id").cloned();
+
+        let Some(method) = message["method"].as_str() else {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                RpcError::new(INVALID_REQUEST, "Expected a request with a method"),
+            ));
+        };
+        let result = self.dispatch(method, &message["params"]);
+
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => error_response(id, error),
+        })
+    }
+
+    fn dispatch(&self, method: &str, params: &Value) -> std::result::Result<Value, RpcError> {
+        match method {
+            "initialize" => {
+                let requested = params["protocolVersion"].as_str().unwrap_or_default();
+                let version = PROTOCOL_VERSIONS
+                    .iter()
+                    .find(|v| **v == requested)
+                    .unwrap_or(&PROTOCOL_VERSIONS[0]);
+                Ok(json!({
+                    "protocolVersion": version,
+                    "capabilities": { "tools": { "listChanged": false } },
+                    "serverInfo": { "name": "zam", "version": env!("CARGO_PKG_VERSION") },
+                    "instructions": "Shell history recorded by zam. Secrets appear as \
+                        redaction placeholders such as <password:1> and cannot be revealed.",
+                }))
+            }
+            "ping" => Ok(json!({})),
+            "tools/list" => Ok(json!({
+                "tools": self
+                    .tools
+                    .iter()
+                    .filter_map(|name| tool_definition(name))
+                    .collect::<Vec<_>>(),
+            })),
+            "tools/call" => {
+                let name = params["name"].as_str().unwrap_or_default();
+                if !self.tools.iter().any(|tool| tool == name) || tool_definition(name).is_none() {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        format!("Unknown tool: {}", name),
+                    ));
+                }
+                // Tool failures are reported to the agent, not as protocol errors
+                Ok(match self.call_tool(name, &params["arguments"]) {
+                    Ok(content) => json!({
+                        "content": [{ "type": "text", "text": content.to_string() }],
+                        "structuredContent": content,
+                        "isError": false,
+                    }),
+                    Err(message) => json!({
+                        "content": [{ "type": "text", "text": message }],
+                        "isError": true,
+                    }),
+                })
+            }
+            _ if method.starts_with("notifications/") => Ok(Value::Null),
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            )),
+        }
+    }
+
+    fn call_tool(&self, name: &str, args: &Value) -> std::result::Result<Value, String> {
+        let limit = args["limit"]
+            .as_u64()
+            .map_or(DEFAULT_LIMIT, |n| (n as usize).clamp(1, MAX_LIMIT));
+        let required = |field: &str| {
+            args[field]
+                .as_str()
+                .filter(|s| !s.is_empty())
+                .map(String::from)
+                .ok_or_else(|| format!("Missing required argument: {}", field))
+        };
+
+        let filter = match name {
+            "search_history" => CommandFilter {
+                text: Some(required("query")?),
+                directory: args["directory"].as_str().map(String::from),
+                ..Default::default()
+            },
+            "recent_in_directory" => CommandFilter {
+                directory: Some(required("directory")?),
+                ..Default::default()
+            },
+            "session_commands" => CommandFilter {
+                session_id: Some(required("session_id")?),
+                ..Default::default()
+            },
+            "list_aliases" => {
+                let aliases = self.db.list_aliases().map_err(|e| e.to_string())?;
+                return Ok(json!({
+                    "aliases": aliases
+                        .iter()
+                        .map(|a| json!({
+                            "alias": a.alias,
+                            "command": a.command,
+                            "description": a.description,
+                        }))
+                        .collect::<Vec<_>>(),
+                }));
+            }
+            _ => return Err(format!("Unknown tool: {}", name)),
+        };
+
+        let mut commands = self
+            .db
+            .find_commands(&CommandFilter {
+                hostnames: self.hostnames.clone(),
+                limit: Some(limit),
+                ..filter
+            })
+            .map_err(|e| e.to_string())?;
+        // A session reads best in the order it was typed
+        if name == "session_commands" {
+            commands.reverse();
+        }
+
+        Ok(json!({
+            "commands": commands
+                .iter()
+                .map(|(cmd, host)| command_json(cmd, host))
+                .collect::<Vec<_>>(),
+        }))
+    }
+}
+
+/// Name, description and input schema of a tool
+fn tool_definition(name: &str) -> Option<Value> {
+    let limit = json!({
+        "type": "integer",
+        "minimum": 1,
+        "maximum": MAX_LIMIT,
+        "description": format!("Maximum number of commands (default {})", DEFAULT_LIMIT),
+    });
+    let (description, properties, required) = match name {
+        "search_history" => (
+            "Search shell history for commands containing a substring, most recent first",
+            json!({
+                "query": { "type": "string", "description": "Text the command must contain" },
+                "directory": { "type": "string", "description": "Only commands run in this directory" },
+                "limit": limit,
+            }),
+            vec!["query"],
+        ),
+        "recent_in_directory" => (
+            "Most recent commands run in a directory",
+            json!({
+                "directory": { "type": "string", "description": "Absolute directory path" },
+                "limit": limit,
+            }),
+            vec!["directory"],
+        ),
+        "list_aliases" => ("Shell aliases managed by zam", json!({}), vec![]),
+        "session_commands" => (
+            "Commands of one shell session, in the order they ran",
+            json!({
+                "session_id": { "type": "string", "description": "Session ID" },
+                "limit": limit,
+            }),
+            vec!["session_id"],
+        ),
+        _ => return None,
+    };
+    Some(json!({
+        "name": name,
+        "description": description,
+        "inputSchema": {
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        },
+    }))
+}
+
+fn command_json(cmd: &CommandEntry, host: &str) -> Value {
+    json!({
+        "id": cmd.id.as_i64(),
+        "command": cmd.command,
+        "directory": cmd.directory,
+        "timestamp": cmd.timestamp.to_rfc3339(),
+        "exit_code": cmd.exit_code,
+        "session_id": cmd.session_id.as_str(),
+        "host": host,
+        "redacted": cmd.redacted,
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
@@ -0,0 +1,226 @@
+//! Private (incognito) sessions for zam
+//!
+//! A session in private mode either drops every command (`drop`) or keeps
+//! them in an ephemeral buffer (`ephemeral`) that only the session itself
+//! can recall. Buffered commands are redacted like stored ones. The buffer
+//! lives in the runtime directory (`$XDG_RUNTIME_DIR`, usually
+//! memory-backed) and is wiped when the session ends; without a runtime
+//! directory there is no ephemeral mode, since the only other place would
+//! be on disk.
+
+use crate::database::CommandEntry;
+use crate::error::Result;
+use crate::types::{CommandId, SessionId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+/// How a private session treats logged commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivateMode {
+    /// Commands are not recorded at all
+    Drop,
+    /// Commands are kept in the session's ephemeral buffer only
+    Ephemeral,
+}
+
+impl PrivateMode {
+    /// Value stored in `sessions.private_mode`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PrivateMode::Drop => "drop",
+            PrivateMode::Ephemeral => "ephemeral",
+        }
+    }
+
+    /// Parse a stored `sessions.private_mode` value
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "drop" => Some(PrivateMode::Drop),
+            "ephemeral" => Some(PrivateMode::Ephemeral),
+            _ => None,
+        }
+    }
+}
+
+/// A command held in an ephemeral buffer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferedCommand {
+    pub command: String,
+    pub directory: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Per-session buffer of commands logged while in ephemeral private mode
+#[derive(Debug, Clone)]
+pub struct EphemeralBuffer {
+    session_id: String,
+    path: PathBuf,
+}
+
+impl EphemeralBuffer {
+    /// Buffer for a session in the runtime directory, or `None` when the
+    /// system has none (e.g. macOS)
+    pub fn for_session(session_id: &str) -> Option<Self> {
+        let base = dirs::runtime_dir()?;
+        Some(Self::in_dir(base.join("zam").join("ephemeral"), session_id))
+    }
+
+    /// Whether ephemeral buffers can be kept off disk on this system
+    pub fn is_available() -> bool {
+        dirs::runtime_dir().is_some()
+    }
+
+    /// Buffer for a session under `dir`
+    pub fn in_dir(dir: PathBuf, session_id: &str) -> Self {
+        let file_name: String = session_id
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        Self {
+            session_id: session_id.to_string(),
+            path: dir.join(format!("{file_name}.jsonl")),
+        }
+    }
+
+    /// Append a command, already redacted, to the buffer. Directories
+    /// created on the way are private to the user, as is the file.
+    pub fn push(&self, command: &str, directory: &str, timestamp: DateTime<Utc>) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            let mut builder = fs::DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::DirBuilderExt;
+                builder.mode(0o700);
+            }
+            builder.create(parent)?;
+            // Tighten the zam directories in case they predate the buffer
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::PermissionsExt;
+                for dir in [Some(parent), parent.parent()].into_iter().flatten() {
+                    fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+                }
+            }
+        }
+
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&self.path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        }
+
+        let entry = BufferedCommand {
+            command: command.to_string(),
+            directory: directory.to_string(),
+            timestamp,
+        };
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        Ok(())
+    }
+
+    /// Buffered commands, oldest first
+    pub fn entries(&self) -> Result<Vec<BufferedCommand>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        Ok(fs::read_to_string(&self.path)?
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect())
+    }
+
+    /// Buffered commands as unsaved command entries (id 0), newest first
+    pub fn command_entries(&self) -> Result<Vec<CommandEntry>> {
+        Ok(self
+            .entries()?
+            .into_iter()
+            .rev()
+            .map(|e| CommandEntry {
+                id: CommandId::new(0),
+                session_id: SessionId::new(self.session_id.clone()),
+                command: e.command,
+                timestamp: e.timestamp,
+                directory: e.directory,
+                redacted: false,
+                exit_code: None,
+            })
+            .collect())
+    }
+
+    /// Delete the buffer. Returns whether one existed.
+    pub fn wipe(&self) -> Result<bool> {
+        if !self.path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(&self.path)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_private_mode_round_trip() {
+        for mode in [PrivateMode::Drop, PrivateMode::Ephemeral] {
+            assert_eq!(PrivateMode::parse(mode.as_str()), Some(mode));
+        }
+        assert_eq!(PrivateMode::parse("public"), None);
+    }
+
+    #[test]
+    fn test_ephemeral_buffer() {
+        let dir = TempDir::new().unwrap();
+        let buffer = EphemeralBuffer::in_dir(dir.path().to_path_buf(), "zsh-1/../2");
+        assert!(buffer.path.starts_with(dir.path()));
+        assert!(buffer.entries().unwrap().is_empty());
+
+        buffer.push("echo one", "/tmp", Utc::now()).unwrap();
+        buffer.push("echo two", "/tmp", Utc::now()).unwrap();
+        let entries = buffer.command_entries().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].command, "echo two");
+        assert_eq!(entries[0].id.as_i64(), 0);
+
+        assert!(buffer.wipe().unwrap());
+        assert!(!buffer.wipe().unwrap());
+        assert!(buffer.entries().unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ephemeral_buffer_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let zam_dir = dir.path().join("zam");
+        let buffer = EphemeralBuffer::in_dir(zam_dir.join("ephemeral"), "zsh-1");
+        buffer.push("echo one", "/tmp", Utc::now()).unwrap();
+
+        let mode =
+            |path: &std::path::Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(&zam_dir), 0o700);
+        assert_eq!(mode(&zam_dir.join("ephemeral")), 0o700);
+        assert_eq!(mode(&buffer.path), 0o600);
+    }
+}
@@ -3,10 +3,13 @@
 //! This module provides sophisticated redaction capabilities for sensitive data
 //! in shell commands, including passwords, tokens, API keys, and other secrets.
 
+use crate::config::Config;
 use crate::error::{Error, Result};
+use crate::rules::{self, RuleMatch, RuleSet, remove_overlaps};
+use crate::structured::SensitiveKeys;
 use regex::Regex;
-use std::collections::HashMap;
-use std::sync::{Mutex, Once};
+use std::collections::{HashMap, HashSet};
+use std::sync::{LazyLock, Mutex, Once};
 
 /// Built-in redaction patterns for common sensitive data
 pub const BUILTIN_PATTERNS: &[&str] = &[
@@ -62,6 +65,133 @@ pub const BUILTIN_PATTERNS: &[&str] = &[
     r"[a-zA-Z0-9]{40,}", // Long alphanumeric strings (potential hashes/tokens)
 ];
 
+/// Provider-specific credential detectors: (name, token type, pattern).
+/// Capture group 1, when present, holds the secret; otherwise the whole match.
+pub const BUILTIN_DETECTORS: &[(&str, &str, &str)] = &[
+    ("slack", "slack_token", r"\bxox[bpas]-[0-9A-Za-z-]{10,}"),
+    ("stripe", "stripe_key", r"\b(?:sk|rk)_live_[0-9A-Za-z]{16,}"),
+    ("gitlab", "gitlab_token", r"\bglpat-[0-9A-Za-z_-]{20,}"),
+    ("npm", "npm_token", r"\bnpm_[0-9A-Za-z]{36}\b"),
+    ("openai", "openai_key", r"\bsk-(?:proj-)?[0-9A-Za-z_-]{20,}"),
+    ("google-api", "google_api_key", r"\bAIza[0-9A-Za-z_-]{35}"),
+    (
+        "aws-access-key-id",
+        "aws_access_key",
+        r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+    ),
+    (
+        "azure-connection-string",
+        "azure_key",
+        r#"(?i)\b(?:AccountKey|SharedAccessKey|SharedAccessSignature)=([^;\s'"]+)"#,
+    ),
+    (
+        "jwt",
+        "jwt",
+        r"\beyJ[0-9A-Za-z_-]+\.[0-9A-Za-z_-]+\.[0-9A-Za-z_-]+",
+    ),
+    (
+        "pem-private-key",
+        "private_key",
+        r"(?s)-----BEGIN (?:[A-Z0-9]+ )*PRIVATE KEY-----.*?-----END (?:[A-Z0-9]+ )*PRIVATE KEY-----",
+    ),
+    (
+        "docker-auth",
+        "docker_auth",
+        r#"\\?"auth\\?"\s*:\s*\\?"([A-Za-z0-9+/=]{8,})\\?""#,
+    ),
+];
+
+static COMPILED_DETECTORS: LazyLock<Vec<(&str, &str, Regex)>> = LazyLock::new(|| {
+    BUILTIN_DETECTORS
+        .iter()
+        .filter_map(|(name, token_type, pattern)| {
+            Regex::new(pattern).ok().map(|re| (*name, *token_type, re))
+        })
+        .collect()
+});
+
+/// Stable fingerprint of a value for the redaction allowlist (FNV-1a, 64-bit),
+/// so allowlisted values are never stored in clear text
+pub fn value_fingerprint(value: &str) -> String {
+    let hash = value.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+/// Find provider-specific credentials in a command
+pub fn detect_credentials(command: &str) -> Vec<RuleMatch> {
+    let mut matches = Vec::new();
+    for (name, token_type, regex) in COMPILED_DETECTORS.iter() {
+        for caps in regex.captures_iter(command) {
+            let Some(m) = caps.get(1).or_else(|| caps.get(0)) else {
+                continue;
+            };
+            let verified = match *name {
+                "jwt" => is_jwt(m.as_str()),
+                "docker-auth" => is_docker_auth(m.as_str()),
+                _ => true,
+            };
+            if verified {
+                matches.push(RuleMatch {
+                    rule: name.to_string(),
+                    token_type: token_type.to_string(),
+                    start: m.start(),
+                    end: m.end(),
+                    value: m.as_str().to_string(),
+                });
+            }
+        }
+    }
+    remove_overlaps(matches)
+}
+
+/// A JWT's first segment must decode to a JSON object with an `alg` field
+fn is_jwt(token: &str) -> bool {
+    token
+        .split('.')
+        .next()
+        .and_then(decode_base64)
+        .and_then(|header| serde_json::from_slice::<serde_json::Value>(&header).ok())
+        .is_some_and(|header| header.get("alg").is_some())
+}
+
+/// A Docker `config.json` auth blob is base64 of `user:password`
+fn is_docker_auth(blob: &str) -> bool {
+    decode_base64(blob)
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .is_some_and(|creds| {
+            creds
+                .split_once(':')
+                .is_some_and(|(u, p)| !u.is_empty() && !p.is_empty())
+        })
+}
+
+/// Decode standard or URL-safe base64, with or without padding
+fn decode_base64(input: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+    for c in input.trim_end_matches('=').bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(out)
+}
+
 /// Redaction engine for processing commands and removing sensitive data
 #[derive(Debug, Clone)]
 pub struct RedactionEngine {
@@ -71,6 +201,10 @@ pub struct RedactionEngine {
     min_length: usize,
     env_vars: Vec<String>,
     redact_env_vars: bool,
+    rules: RuleSet,
+    use_detectors: bool,
+    sensitive_keys: SensitiveKeys,
+    allowlist: HashSet<String>,
 }
 
 /// A compiled regex pattern with metadata
@@ -163,9 +297,42 @@ impl RedactionEngine {
             min_length,
             env_vars,
             redact_env_vars,
+            rules: RuleSet::new(),
+            use_detectors: use_builtin,
+            allowlist: HashSet::new(),
+            sensitive_keys: if use_builtin {
+                SensitiveKeys::builtin()
+            } else {
+                SensitiveKeys::default()
+            },
         })
     }
 
+    /// Create a redaction engine from the application configuration,
+    /// including built-in and user rule packs when enabled
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut engine = Self::with_config(
+            config.redaction.use_builtin_patterns,
+            config.redaction.custom_patterns.clone(),
+            config.redaction.exclude_patterns.clone(),
+            config.redaction.placeholder.clone(),
+            config.redaction.min_redaction_length,
+            config.custom_env_vars.clone(),
+            config.redaction.redact_env_vars,
+        )?;
+        engine.set_sensitive_keys(&config.redaction.sensitive_keys);
+
+        if config.redaction.use_rule_packs {
+            let rules_dir = match &config.redaction.rules_dir {
+                Some(dir) => Some(dir.clone()),
+                None => rules::default_rules_dir().ok(),
+            };
+            engine.set_rules(RuleSet::load(rules_dir.as_deref())?);
+        }
+
+        Ok(engine)
+    }
+
     /// Get compiled built-in patterns (cached)
     fn get_builtin_patterns() -> Result<Vec<CompiledPattern>> {
         COMPILED_BUILTIN_PATTERNS.call_once(|| {
@@ -215,7 +382,7 @@ impl RedactionEngine {
     /// Redact sensitive information from a command
     #[must_use = "Redacted command should be used"]
     pub fn redact(&self, command: &str) -> Result<String> {
-        let mut result = command.to_string();
+        let mut result = self.apply_rules(command);
 
         // First, redact environment variables if enabled
         if self.redact_env_vars {
@@ -237,12 +404,20 @@ impl RedactionEngine {
 
     /// Redact with statistics tracking
     pub fn redact_with_stats(&self, command: &str, stats: &mut RedactionStats) -> Result<String> {
-        let _original = command.to_string();
-        let mut result = command.to_string();
         let mut was_redacted = false;
 
         stats.total_commands += 1;
 
+        // Per-program rules run first, on the untouched command line
+        let matches = self.typed_matches(command);
+        for m in &matches {
+            *stats.patterns_matched.entry(m.rule.clone()).or_insert(0) += 1;
+        }
+        if !matches.is_empty() {
+            was_redacted = true;
+        }
+        let mut result = self.replace_matches(command, &matches);
+
         // First, redact environment variables if enabled
         if self.redact_env_vars {
             let env_redacted = self.redact_env_variables(&result)?;
@@ -279,17 +454,70 @@ impl RedactionEngine {
         Ok(result)
     }
 
+    /// Find secrets captured by the per-program rules, the provider
+    /// detectors and URL query / request body parsing, honouring exclude patterns and the minimum
+    /// redaction length
+    pub fn typed_matches(&self, command: &str) -> Vec<RuleMatch> {
+        if self
+            .exclude_patterns
+            .iter()
+            .any(|p| p.regex.is_match(command))
+        {
+            return Vec::new();
+        }
+
+        let mut matches = self.rules.find_matches(command);
+        if self.use_detectors {
+            matches.extend(detect_credentials(command));
+        }
+        matches.extend(self.sensitive_keys.find_secrets(command));
+        matches.retain(|m| m.value.len() >= self.min_length && !self.is_allowed(&m.value));
+        remove_overlaps(matches)
+    }
+
+    /// Apply typed matches to the command
+    fn apply_rules(&self, command: &str) -> String {
+        self.replace_matches(command, &self.typed_matches(command))
+    }
+
+    /// Replace non-overlapping rule matches with the placeholder
+    fn replace_matches(&self, command: &str, matches: &[RuleMatch]) -> String {
+        let mut result = command.to_string();
+        for m in matches.iter().rev() {
+            result.replace_range(m.start..m.end, &self.placeholder);
+        }
+        result
+    }
+
     /// Apply a single pattern to the command
     fn apply_pattern(&self, command: &str, pattern: &CompiledPattern) -> Result<String> {
         match &pattern.replacement_type {
             ReplacementType::Full => Ok(pattern
                 .regex
-                .replace_all(command, &self.placeholder)
+                .replace_all(command, |caps: &regex::Captures| {
+                    if self.is_allowed(&caps[0]) {
+                        caps[0].to_string()
+                    } else {
+                        self.placeholder.clone()
+                    }
+                })
                 .to_string()),
             ReplacementType::Partial { keep_groups } => {
                 let result = pattern
                     .regex
                     .replace_all(command, |caps: &regex::Captures| {
+                        // The sensitive part sits between the first kept group
+                        // and the next one (or the end of the match)
+                        let whole = caps.get(0).map_or(0..0, |m| m.range());
+                        let start = caps.get(keep_groups[0]).map_or(whole.start, |g| g.end());
+                        let end = keep_groups
+                            .get(1)
+                            .and_then(|&g| caps.get(g))
+                            .map_or(whole.end, |g| g.start());
+                        if self.is_allowed(command.get(start..end).unwrap_or_default()) {
+                            return caps[0].to_string();
+                        }
+
                         let mut replacement = String::new();
                         for &group_idx in keep_groups {
                             if let Some(group) = caps.get(group_idx) {
@@ -381,6 +609,31 @@ impl RedactionEngine {
         self.placeholder = placeholder;
     }
 
+    /// Replace the per-program rule set
+    pub fn set_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+    }
+
+    /// Replace the sensitive-key list used for URL and body redaction
+    pub fn set_sensitive_keys<S: AsRef<str>>(&mut self, keys: &[S]) {
+        self.sensitive_keys = SensitiveKeys::new(keys);
+    }
+
+    /// Replace the allowlist of value fingerprints that are never redacted
+    pub fn set_allowlist<I: IntoIterator<Item = String>>(&mut self, fingerprints: I) {
+        self.allowlist = fingerprints.into_iter().collect();
+    }
+
+    /// Whether a value has been marked as not-a-secret
+    pub fn is_allowed(&self, value: &str) -> bool {
+        !self.allowlist.is_empty() && self.allowlist.contains(&value_fingerprint(value))
+    }
+
+    /// Get the per-program rule set
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
     /// Set minimum length for redaction
     pub fn set_min_length(&mut self, min_length: usize) {
         self.min_length = min_length;
@@ -393,6 +646,9 @@ impl RedactionEngine {
 
     /// Check if a command contains sensitive data (without redacting)
     pub fn contains_sensitive_data(&self, command: &str) -> bool {
+        if !self.typed_matches(command).is_empty() {
+            return true;
+        }
         for pattern in &self.patterns {
             if pattern.regex.is_match(command) && !self.should_exclude(command, pattern) {
                 return true;
@@ -573,6 +829,210 @@ mod tests {
         assert!(!engine.contains_sensitive_data("echo hello world"));
     }
 
+    #[test]
+    fn test_rule_pack_redaction() {
+        let mut engine = RedactionEngine::with_config(
+            false,
+            vec![],
+            vec![r"--user demo:".to_string()],
+            "<redacted>".to_string(),
+            3,
+            vec![],
+            false,
+        )
+        .unwrap();
+        engine.set_rules(RuleSet::builtin().unwrap());
+
+        let result = engine
+            .redact("curl -u admin:hunter22 https://example.com")
+            .unwrap();
+        assert_eq!(result, "curl -u admin:<redacted> https://example.com");
+        assert!(engine.contains_sensitive_data("openssl enc -pass pass:s3cret"));
+
+        // Exclude patterns also apply to rule packs
+        let excluded = "curl --user demo:demo123 https://example.com";
+        assert_eq!(engine.redact(excluded).unwrap(), excluded);
+    }
+
+    #[test]
+    fn test_url_and_body_redaction() {
+        let engine = RedactionEngine::new().unwrap();
+
+        let result = engine
+            .redact("curl 'https://x.io/api?page=2&access_token=abc123&sig=99ff'")
+            .unwrap();
+        assert!(result.contains("page=2"));
+        assert!(!result.contains("abc123"));
+        assert!(!result.contains("99ff"));
+
+        let result = engine
+            .redact(r#"curl -d '{"user":"bob","password":"hunter22"}' https://x.io"#)
+            .unwrap();
+        assert_eq!(
+            result,
+            r#"curl -d '{"user":"bob","password":"<redacted>"}' https://x.io"#
+        );
+    }
+
+    fn detected(command: &str) -> Vec<(String, String)> {
+        detect_credentials(command)
+            .into_iter()
+            .map(|m| (m.token_type, m.value))
+            .collect()
+    }
+
+    // Vectors are assembled at runtime so the source never contains
+    // anything shaped like a live credential.
+    #[test]
+    fn test_provider_detectors_positive() {
+        let cases = vec![
+            ("slack_token", format!("xoxb-{}", "1234567890-abcdefghijkl")),
+            (
+                "stripe_key",
+                format!("sk_{}_{}", "live", "4eC39HqLyjWDarjtT1zdp7dc"),
+            ),
+            (
+                "stripe_key",
+                format!("rk_{}_{}", "live", "51HxyzABCDEF1234567890"),
+            ),
+            ("gitlab_token", format!("glpat-{}", "xxxxxxxxxxxxxxxxxxxx")),
+            ("npm_token", format!("npm_{}", "a".repeat(36))),
+            (
+                "openai_key",
+                format!("sk-{}", "proj-abcdefghijklmnopqrstuvwx"),
+            ),
+            (
+                "google_api_key",
+                format!("AIza{}", "SyA1234567890abcdefghijklmnopqrstuv"),
+            ),
+            ("aws_access_key", format!("AKIA{}", "IOSFODNN7EXAMPLE")),
+        ];
+
+        for (token_type, secret) in cases {
+            let command = format!("deploy --credential {} --verbose", secret);
+            assert_eq!(
+                detected(&command),
+                vec![(token_type.to_string(), secret.clone())],
+                "failed for {}",
+                token_type
+            );
+        }
+    }
+
+    #[test]
+    fn test_provider_detectors_negative() {
+        let commands = vec![
+            "xoxb-short",
+            "sk_test_4eC39HqLyjWDarjtT1zdp7dc",
+            "glpat-short",
+            "npm_install_packages",
+            "task-runner-with-a-very-long-name-here",
+            "AIzaShort",
+            "AKIAlowercase1234567",
+            "git checkout -b feature/sk-learn",
+        ];
+
+        for command in commands {
+            assert!(
+                detected(command).is_empty(),
+                "false positive for {}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn test_azure_connection_string() {
+        let key = format!("{}==", "q1w2e3r4t5y6u7i8o9p0".repeat(3));
+        let command = format!(
+            "az storage blob list --connection-string 'DefaultEndpointsProtocol=https;AccountName=acct;AccountKey={};EndpointSuffix=core.windows.net'",
+            key
+        );
+        assert_eq!(detected(&command), vec![("azure_key".to_string(), key)]);
+        assert!(detected("az storage account show --name AccountName=acct").is_empty());
+    }
+
+    #[test]
+    fn test_jwt_detection() {
+        // {"alg":"HS256","typ":"JWT"}
+        let jwt = format!(
+            "{}.{}.{}",
+            "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9", "eyJzdWIiOiIxMjM0In0", "c2lnbmF0dXJl"
+        );
+        let command = format!("http GET api.local X-Session:{}", jwt);
+        assert_eq!(detected(&command), vec![("jwt".to_string(), jwt)]);
+
+        // {"foo":"bar"} has no alg, so it is not a JWT header
+        assert!(detected("echo eyJmb28iOiJiYXIifQ.eyJzdWIiOiIxIn0.c2ln").is_empty());
+    }
+
+    #[test]
+    fn test_pem_private_key_multiline() {
+        let block = format!(
+            "-----BEGIN {0}-----\nMIIEpAIBAAKCAQEA\nx1y2z3\n-----END {0}-----",
+            "RSA PRIVATE KEY"
+        );
+        let command = format!("cat > id_rsa <<EOF\n{}\nEOF", block);
+        assert_eq!(detected(&command), vec![("private_key".to_string(), block)]);
+
+        let cert = "cat <<EOF\n-----BEGIN CERTIFICATE-----\nMIIB\n-----END CERTIFICATE-----\nEOF";
+        assert!(detected(cert).is_empty());
+    }
+
+    #[test]
+    fn test_docker_auth_blob() {
+        // base64("user:pass")
+        let command =
+            r#"echo '{"auths":{"ghcr.io":{"auth":"dXNlcjpwYXNz"}}}' > ~/.docker/config.json"#;
+        assert_eq!(
+            detected(command),
+            vec![("docker_auth".to_string(), "dXNlcjpwYXNz".to_string())]
+        );
+
+        // base64("justtext") has no user:password separator
+        assert!(detected(r#"echo '{"auth":"anVzdHRleHQ="}'"#).is_empty());
+    }
+
+    #[test]
+    fn test_detectors_disabled_without_builtin() {
+        let engine = RedactionEngine::with_config(
+            false,
+            vec![],
+            vec![],
+            "<redacted>".to_string(),
+            3,
+            vec![],
+            false,
+        )
+        .unwrap();
+        let command = format!("export KEY=AKIA{}", "IOSFODNN7EXAMPLE");
+        assert_eq!(engine.redact(&command).unwrap(), command);
+    }
+
+    #[test]
+    fn test_allowlisted_values_are_kept() {
+        let mut engine = RedactionEngine::new().unwrap();
+        let sha = "3f786850e387550fdab836ed7e6dc881de23001b";
+        let command = format!("git show {}", sha);
+        assert!(!engine.redact(&command).unwrap().contains(sha));
+
+        engine.set_allowlist([value_fingerprint(sha)]);
+        assert_eq!(engine.redact(&command).unwrap(), command);
+
+        // Only the allowlisted value is spared
+        let redacted = engine
+            .redact(&format!("{} password=hunter22", command))
+            .unwrap();
+        assert_eq!(redacted, format!("{} password=<redacted>", command));
+        engine.set_allowlist([value_fingerprint("hunter22")]);
+        assert!(
+            engine
+                .redact("password=hunter22")
+                .unwrap()
+                .contains("hunter22")
+        );
+    }
+
     #[test]
     fn test_redaction_stats() {
         let engine = RedactionEngine::new().unwrap();
@@ -0,0 +1,105 @@
+//! Interactive session replay for zam
+//!
+//! `zam replay` walks a past session's commands in order and asks, per
+//! step, whether to run, edit or skip it (like `git add -p`). Redaction
+//! placeholders can be filled from the `tokens` table once the user agrees.
+//! Replayed commands are logged into a new session linked to the original.
+
+use crate::database::Token;
+use crate::types::CommandId;
+
+/// What to do with a replay step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayAction {
+    Run,
+    Edit,
+    Skip,
+    Quit,
+    Help,
+}
+
+impl ReplayAction {
+    /// Prompt choices, in the order shown
+    pub const CHOICES: &'static str = "y,n,e,q,?";
+
+    /// Help text listing the choices
+    pub const HELP: &'static str = "y - run this command\n\
+         n - skip this command\n\
+         e - edit this command before running it\n\
+         q - quit; do not run this or any remaining command\n\
+         ? - print help";
+
+    /// Parse a prompt answer. Returns `None` for anything unrecognised.
+    pub fn parse(answer: &str) -> Option<Self> {
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => Some(ReplayAction::Run),
+            "n" | "no" => Some(ReplayAction::Skip),
+            "e" | "edit" => Some(ReplayAction::Edit),
+            "q" | "quit" => Some(ReplayAction::Quit),
+            "?" | "h" | "help" => Some(ReplayAction::Help),
+            _ => None,
+        }
+    }
+}
+
+/// Tokens whose placeholder appears in `command` logged as `command_id`
+pub fn tokens_for<'a>(tokens: &'a [Token], command_id: CommandId, command: &str) -> Vec<&'a Token> {
+    tokens
+        .iter()
+        .filter(|t| t.command_id == command_id && command.contains(&t.placeholder))
+        .collect()
+}
+
+/// Replace placeholders in `command` with the original token values
+pub fn fill_placeholders(command: &str, tokens: &[&Token]) -> String {
+    tokens.iter().fold(command.to_string(), |cmd, token| {
+        cmd.replace(&token.placeholder, &token.original_value)
+    })
+}
+
+/// Session ID for a new replay session
+pub fn replay_session_id() -> String {
+    format!(
+        "replay-{}-{}",
+        std::process::id(),
+        chrono::Utc::now().timestamp()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_parse_action() {
+        assert_eq!(ReplayAction::parse("y\n"), Some(ReplayAction::Run));
+        assert_eq!(ReplayAction::parse(" E "), Some(ReplayAction::Edit));
+        assert_eq!(ReplayAction::parse("n"), Some(ReplayAction::Skip));
+        assert_eq!(ReplayAction::parse("q"), Some(ReplayAction::Quit));
+        assert_eq!(ReplayAction::parse("?"), Some(ReplayAction::Help));
+        assert_eq!(ReplayAction::parse("x"), None);
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let token = |command_id: i64, placeholder: &str, value: &str| Token {
+            id: 0,
+            command_id: CommandId::new(command_id),
+            token_type: "password".to_string(),
+            placeholder: placeholder.to_string(),
+            original_value: value.to_string(),
+            created_at: Utc::now(),
+        };
+        let tokens = vec![
+            token(1, "<password:1>", "hunter2"),
+            token(2, "<password:1>", "other"),
+            token(1, "<token:2>", "unused"),
+        ];
+
+        let command = "mysql -p <password:1>";
+        let matching = tokens_for(&tokens, CommandId::new(1), command);
+        assert_eq!(matching.len(), 1);
+        assert_eq!(fill_placeholders(command, &matching), "mysql -p hunter2");
+    }
+}
@@ -0,0 +1,462 @@
+//! Per-program redaction rule packs for zam
+//!
+//! Rule packs are declarative JSON documents keyed by executable name.
+//! Each rule carries a regex whose capture group marks the secret part of
+//! the command, the token type it is stored under, and example vectors that
+//! `zam validate --rule <name>` can replay.
+//!
+//! Built-in packs are embedded in the binary; extra packs are loaded from
+//! `~/.config/zam/rules.d/*.json`.
+
+use crate::error::{Error, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// Built-in rule packs embedded at compile time
+const BUILTIN_PACKS: &[(&str, &str)] = &[
+    ("aws", include_str!("rules/aws.json")),
+    ("curl", include_str!("rules/curl.json")),
+    ("gh", include_str!("rules/gh.json")),
+    ("htpasswd", include_str!("rules/htpasswd.json")),
+    ("kubectl", include_str!("rules/kubectl.json")),
+    ("openssl", include_str!("rules/openssl.json")),
+    ("psql", include_str!("rules/psql.json")),
+];
+
+/// Wrappers that are skipped when resolving the program a command runs
+const COMMAND_WRAPPERS: &[&str] = &["sudo", "env", "time", "exec", "nohup", "command"];
+
+/// A rule pack as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RulePack {
+    /// Executable names this pack applies to
+    pub programs: Vec<String>,
+
+    /// Rules in this pack
+    pub rules: Vec<RuleSpec>,
+}
+
+/// A single redaction rule as stored on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleSpec {
+    /// Unique rule name (used by `zam validate --rule`)
+    pub name: String,
+
+    /// Human-readable description
+    #[serde(default)]
+    pub description: String,
+
+    /// Token type recorded for matched secrets
+    pub token_type: String,
+
+    /// Regex matching the sensitive part of a command
+    pub pattern: String,
+
+    /// Capture group holding the secret value
+    #[serde(default = "default_group")]
+    pub group: usize,
+
+    /// Example vectors for validation
+    #[serde(default)]
+    pub examples: Vec<RuleExample>,
+}
+
+/// An example input for a rule, with the secret it should capture
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleExample {
+    /// Command line to test
+    pub input: String,
+
+    /// Expected secret, or `None` if the rule must not match
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+fn default_group() -> usize {
+    1
+}
+
+/// A compiled rule ready to be applied to commands
+#[derive(Debug, Clone)]
+pub struct CompiledRule {
+    pub name: String,
+    pub description: String,
+    pub programs: Vec<String>,
+    pub token_type: String,
+    pub pattern: String,
+    pub examples: Vec<RuleExample>,
+    /// Where the rule was loaded from ("builtin" or a file path)
+    pub source: String,
+    regex: Regex,
+    group: usize,
+}
+
+/// A secret found by a rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleMatch {
+    pub rule: String,
+    pub token_type: String,
+    pub start: usize,
+    pub end: usize,
+    pub value: String,
+}
+
+/// Outcome of replaying one rule example
+#[derive(Debug, Clone)]
+pub struct ExampleResult {
+    pub input: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+impl ExampleResult {
+    /// Whether the rule captured exactly what the example expected
+    pub fn passed(&self) -> bool {
+        self.expected == self.actual
+    }
+}
+
+/// A collection of compiled rules from one or more packs
+#[derive(Debug, Clone, Default)]
+pub struct RuleSet {
+    rules: Vec<CompiledRule>,
+    skipped: Vec<SkippedPack>,
+}
+
+/// A user rule pack that [`RuleSet::load`] could not read or compile
+#[derive(Debug, Clone)]
+pub struct SkippedPack {
+    pub source: String,
+    pub error: String,
+}
+
+impl CompiledRule {
+    /// Find all secrets this rule captures in `command`
+    pub fn find_matches(&self, command: &str) -> Vec<RuleMatch> {
+        self.regex
+            .captures_iter(command)
+            .filter_map(|caps| caps.get(self.group))
+            .filter(|m| !m.as_str().is_empty())
+            .map(|m| RuleMatch {
+                rule: self.name.clone(),
+                token_type: self.token_type.clone(),
+                start: m.start(),
+                end: m.end(),
+                value: m.as_str().to_string(),
+            })
+            .collect()
+    }
+
+    /// Whether this rule applies to a command running `program`
+    pub fn applies_to(&self, programs: &[String]) -> bool {
+        self.programs.iter().any(|p| programs.contains(p))
+    }
+
+    /// Replay the rule's example vectors
+    pub fn run_examples(&self) -> Vec<ExampleResult> {
+        self.examples
+            .iter()
+            .map(|ex| ExampleResult {
+                input: ex.input.clone(),
+                expected: ex.secret.clone(),
+                actual: self
+                    .find_matches(&ex.input)
+                    .first()
+                    .map(|m| m.value.clone()),
+            })
+            .collect()
+    }
+}
+
+impl RuleSet {
+    /// Create an empty rule set
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the built-in rule packs
+    pub fn builtin() -> Result<Self> {
+        let mut set = Self::new();
+        for (name, content) in BUILTIN_PACKS {
+            set.add_pack_str(content, "builtin")
+                .map_err(|e| Error::custom(format!("Invalid built-in rule pack {name}: {e}")))?;
+        }
+        Ok(set)
+    }
+
+    /// Load the built-in packs plus every `*.json` pack in `user_dir`. A
+    /// user pack that cannot be read or compiled is skipped and listed in
+    /// [`skipped`](Self::skipped), so one bad file does not stop commands
+    /// from being logged.
+    pub fn load(user_dir: Option<&Path>) -> Result<Self> {
+        let mut set = Self::builtin()?;
+        if let Some(dir) = user_dir
+            && dir.is_dir()
+        {
+            let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            paths.sort();
+            for path in paths {
+                let source = path.display().to_string();
+                if let Err(e) = fs::read_to_string(&path)
+                    .map_err(Error::from)
+                    .and_then(|content| set.add_pack_str(&content, &source))
+                {
+                    debug!("Skipping rule pack {}: {}", source, e);
+                    set.skipped.push(SkippedPack {
+                        source,
+                        error: e.to_string(),
+                    });
+                }
+            }
+        }
+        Ok(set)
+    }
+
+    /// Parse and add a JSON rule pack. User rules replace built-in rules with the same name.
+    pub fn add_pack_str(&mut self, content: &str, source: &str) -> Result<()> {
+        let pack: RulePack = serde_json::from_str(content)?;
+        self.add_pack(pack, source)
+    }
+
+    /// Compile and add a rule pack. Nothing is added unless every rule
+    /// compiles.
+    pub fn add_pack(&mut self, pack: RulePack, source: &str) -> Result<()> {
+        let mut compiled = Vec::with_capacity(pack.rules.len());
+        for spec in pack.rules {
+            let regex = Regex::new(&spec.pattern).map_err(|_| Error::InvalidRedactionPattern {
+                pattern: spec.pattern.clone(),
+            })?;
+            if spec.group > regex.captures_len() - 1 {
+                return Err(Error::config_validation(
+                    format!("rule {}", spec.name),
+                    format!("capture group {} does not exist in pattern", spec.group),
+                ));
+            }
+            compiled.push(CompiledRule {
+                name: spec.name,
+                description: spec.description,
+                programs: pack.programs.clone(),
+                token_type: spec.token_type,
+                pattern: spec.pattern,
+                examples: spec.examples,
+                source: source.to_string(),
+                regex,
+                group: spec.group,
+            });
+        }
+        for rule in compiled {
+            self.rules.retain(|r| r.name != rule.name);
+            self.rules.push(rule);
+        }
+        Ok(())
+    }
+
+    /// Look up a rule by name
+    pub fn get(&self, name: &str) -> Option<&CompiledRule> {
+        self.rules.iter().find(|r| r.name == name)
+    }
+
+    /// All rules, in load order
+    pub fn rules(&self) -> &[CompiledRule] {
+        &self.rules
+    }
+
+    /// User packs that were left out by [`load`](Self::load)
+    pub fn skipped(&self) -> &[SkippedPack] {
+        &self.skipped
+    }
+
+    /// Whether the set holds no rules
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Find secrets in a command using the rules for the programs it runs.
+    /// Overlapping matches are dropped in favour of the earliest one.
+    pub fn find_matches(&self, command: &str) -> Vec<RuleMatch> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        let programs = command_programs(command);
+        let matches = self
+            .rules
+            .iter()
+            .filter(|r| r.applies_to(&programs))
+            .flat_map(|r| r.find_matches(command))
+            .collect();
+        remove_overlaps(matches)
+    }
+}
+
+/// Sort matches by position and drop any that overlap an earlier (or, at the
+/// same start, longer) match
+pub fn remove_overlaps(mut matches: Vec<RuleMatch>) -> Vec<RuleMatch> {
+    matches.sort_by_key(|m| (m.start, std::cmp::Reverse(m.end)));
+    let mut result: Vec<RuleMatch> = Vec::with_capacity(matches.len());
+    for m in matches {
+        if result.last().is_none_or(|prev| m.start >= prev.end) {
+            result.push(m);
+        }
+    }
+    result
+}
+
+/// Resolve the programs a command line runs, one per pipeline/list segment.
+///
+/// Leading `VAR=value` assignments and wrappers such as `sudo` are skipped,
+/// and paths are reduced to their basename (`/usr/bin/curl` → `curl`).
+pub fn command_programs(command: &str) -> Vec<String> {
+    command
+        .split(['|', ';', '&', '(', ')', '\n'])
+        .filter_map(|segment| {
+            segment
+                .split_whitespace()
+                .find(|word| !word.contains('=') && !COMMAND_WRAPPERS.contains(word))
+                .map(|word| {
+                    let word = word.trim_matches(|c| c == '\'' || c == '"');
+                    word.rsplit('/').next().unwrap_or(word).to_string()
+                })
+        })
+        .collect()
+}
+
+/// Default directory for user rule packs (`~/.config/zam/rules.d`)
+pub fn default_rules_dir() -> Result<PathBuf> {
+    let home = home::home_dir().ok_or(Error::HomeDirectoryNotFound)?;
+    Ok(home.join(".config").join("zam").join("rules.d"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_builtin_packs_load() {
+        let set = RuleSet::builtin().unwrap();
+        assert!(!set.is_empty());
+        assert!(set.get("curl-authorization-header").is_some());
+        assert!(set.get("openssl-pass-arg").is_some());
+    }
+
+    #[test]
+    fn test_builtin_rule_examples() {
+        let set = RuleSet::builtin().unwrap();
+        for rule in set.rules() {
+            assert!(
+                !rule.examples.is_empty(),
+                "rule {} has no examples",
+                rule.name
+            );
+            for result in rule.run_examples() {
+                assert!(
+                    result.passed(),
+                    "rule {} failed on {:?}: expected {:?}, got {:?}",
+                    rule.name,
+                    result.input,
+                    result.expected,
+                    result.actual
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_command_programs() {
+        assert_eq!(command_programs("curl -s x"), vec!["curl"]);
+        assert_eq!(
+            command_programs("PGPASSWORD=x sudo /usr/bin/psql -h db"),
+            vec!["psql"]
+        );
+        assert_eq!(
+            command_programs("echo tok | gh auth login && ls"),
+            vec!["echo", "gh", "ls"]
+        );
+    }
+
+    #[test]
+    fn test_rules_only_apply_to_their_program() {
+        let set = RuleSet::builtin().unwrap();
+        // --token belongs to the kubectl pack, so other programs are untouched
+        assert!(set.find_matches("mytool --token=abcdef123456").is_empty());
+        let matches = set.find_matches("kubectl get pods --token=abcdef123456");
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].value, "abcdef123456");
+        assert_eq!(matches[0].token_type, "bearer_token");
+    }
+
+    #[test]
+    fn test_user_pack_overrides_builtin() {
+        let dir = TempDir::new().unwrap();
+        fs::write(
+            dir.path().join("vault.json"),
+            r#"{
+                "programs": ["vault"],
+                "rules": [{
+                    "name": "vault-login-token",
+                    "token_type": "api_key",
+                    "pattern": "vault login\\s+(\\S+)",
+                    "examples": [{ "input": "vault login s.abc123", "secret": "s.abc123" }]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        let set = RuleSet::load(Some(dir.path())).unwrap();
+        let rule = set.get("vault-login-token").unwrap();
+        assert!(rule.run_examples().iter().all(ExampleResult::passed));
+        assert!(rule.source.ends_with("vault.json"));
+        assert!(set.get("curl-user").is_some());
+    }
+
+    #[test]
+    fn test_bad_user_pack_skipped() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("broken.json"), "{ not json").unwrap();
+        fs::write(
+            dir.path().join("mixed.json"),
+            r#"{
+                "programs": ["tool"],
+                "rules": [
+                    { "name": "tool-ok", "token_type": "password", "pattern": "-p(\\S+)" },
+                    { "name": "tool-bad", "token_type": "password", "pattern": "(" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("vault.json"),
+            r#"{
+                "programs": ["vault"],
+                "rules": [{ "name": "vault-x", "token_type": "api_key", "pattern": "vault x (\\S+)" }]
+            }"#,
+        )
+        .unwrap();
+
+        let set = RuleSet::load(Some(dir.path())).unwrap();
+        assert!(set.get("vault-x").is_some());
+        // A pack with one bad rule adds none of its rules
+        assert!(set.get("tool-ok").is_none());
+        assert!(set.get("curl-user").is_some());
+        let skipped: Vec<_> = set.skipped().iter().map(|p| p.source.as_str()).collect();
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped[0].ends_with("broken.json"));
+        assert!(skipped[1].ends_with("mixed.json"));
+    }
+
+    #[test]
+    fn test_invalid_group_rejected() {
+        let mut set = RuleSet::new();
+        let err = set.add_pack_str(
+            r#"{"programs": ["x"], "rules": [{"name": "bad", "token_type": "t", "pattern": "x(y)", "group": 2}]}"#,
+            "test",
+        );
+        assert!(err.is_err());
+    }
+}
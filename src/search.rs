@@ -142,7 +142,10 @@ impl SearchEngine {
             }
 
             // Check for match
-            let (is_match, matches, score) = if let Some(ref regex) = regex {
+            let (is_match, matches, score) = if search_term.is_empty() && regex.is_none() {
+                // An empty term (e.g. only a `session:` qualifier) lists everything
+                (true, Vec::new(), 0.0)
+            } else if let Some(ref regex) = regex {
                 self.regex_match(&entry.command, regex)?
             } else if query.fuzzy {
                 self.fuzzy_match(&entry.command, &search_term, query.case_sensitive)
@@ -446,6 +449,33 @@ impl Default for SearchEngine {
     }
 }
 
+/// Split a `session:"glob"` (or `session:glob`) qualifier off a search
+/// term. Returns the remaining term and the session name glob, if any.
+pub fn split_session_qualifier(term: &str) -> (String, Option<String>) {
+    let Some(start) = term
+        .match_indices("session:")
+        .map(|(i, _)| i)
+        .find(|&i| i == 0 || term[..i].ends_with(char::is_whitespace))
+    else {
+        return (term.to_string(), None);
+    };
+
+    let rest = &term[start + "session:".len()..];
+    let (glob, after) = match rest.strip_prefix('"') {
+        Some(quoted) => match quoted.find('"') {
+            Some(end) => (&quoted[..end], &quoted[end + 1..]),
+            None => (quoted, ""),
+        },
+        None => {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (&rest[..end], &rest[end..])
+        }
+    };
+
+    let remaining = format!("{} {}", term[..start].trim(), after.trim());
+    (remaining.trim().to_string(), Some(glob.to_string()))
+}
+
 impl SearchQuery {
     /// Create a new simple search query
     pub fn new(term: String) -> Self {
@@ -683,4 +713,29 @@ mod tests {
         // First result should have higher score
         assert!(results[0].score >= results[1].score);
     }
+
+    #[test]
+    fn test_split_session_qualifier() {
+        assert_eq!(
+            split_session_qualifier(r#"kubectl session:"incident*" logs"#),
+            ("kubectl logs".to_string(), Some("incident*".to_string()))
+        );
+        assert_eq!(
+            split_session_qualifier("session:deploy"),
+            (String::new(), Some("deploy".to_string()))
+        );
+        assert_eq!(
+            split_session_qualifier("grep mysession:x"),
+            ("grep mysession:x".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn test_empty_term_matches_everything() {
+        let engine = SearchEngine::new();
+        let entries = create_test_entries();
+        let query = SearchQuery::new(String::new());
+        let results = engine.search_with_query(&entries, &query).unwrap();
+        assert_eq!(results.len(), entries.len());
+    }
 }
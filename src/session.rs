@@ -0,0 +1,203 @@
+//! Shell process tracking, session tags and the session tree
+//!
+//! The shell integration exports `ZAM_SHELL_PID` and `ZAM_TTY` next to
+//! `ZAM_SESSION_ID`. They are recorded when the shell's own session is
+//! created so sessions whose shell died without running its exit hook
+//! (killed terminal, dropped SSH connection) can be reaped later.
+//!
+//! A shell started inside another one inherits its `ZAM_SESSION_ID`; the
+//! integration moves it to `ZAM_PARENT_SESSION_ID` before starting its own
+//! session, which records the outer session as its parent.
+
+use crate::database::Session;
+use crate::error::{Error, Result};
+use std::collections::HashMap;
+use std::env;
+
+/// The shell process that owns a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShellProcess {
+    pub pid: u32,
+    pub tty: Option<String>,
+}
+
+impl ShellProcess {
+    /// The shell owning `session_id`, taken from the environment. Only the
+    /// shell's own session qualifies: other sessions logged from the same
+    /// terminal (e.g. `--session-id` from a long-running tool) do not share
+    /// its lifetime.
+    pub fn for_session(session_id: &str) -> Option<Self> {
+        if env::var("ZAM_SESSION_ID").ok().as_deref() != Some(session_id) {
+            return None;
+        }
+        let pid = env::var("ZAM_SHELL_PID").ok()?.trim().parse().ok()?;
+        let tty = env::var("ZAM_TTY")
+            .ok()
+            .filter(|t| !t.is_empty() && t != "not a tty");
+        Some(Self { pid, tty })
+    }
+}
+
+/// The session `session_id` was started from, taken from the environment.
+/// As with [`ShellProcess::for_session`], only the shell's own session
+/// qualifies.
+pub fn inherited_parent_session(session_id: &str) -> Option<String> {
+    if env::var("ZAM_SESSION_ID").ok().as_deref() != Some(session_id) {
+        return None;
+    }
+    env::var("ZAM_PARENT_SESSION_ID")
+        .ok()
+        .filter(|p| !p.is_empty() && p != session_id)
+}
+
+/// Order sessions as a tree: each session is followed by its children
+/// (oldest first), roots keep their input order. Sessions whose parent is
+/// not in `sessions` are roots. Returns `(index into sessions, depth)`.
+pub fn tree_order(sessions: &[Session]) -> Vec<(usize, usize)> {
+    let index: HashMap<&str, usize> = sessions
+        .iter()
+        .enumerate()
+        .map(|(i, s)| (s.id.as_str(), i))
+        .collect();
+    let mut children: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut roots = Vec::new();
+    for (i, session) in sessions.iter().enumerate() {
+        match session.parent_id.as_deref().and_then(|p| index.get(p)) {
+            Some(&parent) if parent != i => children.entry(parent).or_default().push(i),
+            _ => roots.push(i),
+        }
+    }
+    for kids in children.values_mut() {
+        kids.sort_by_key(|&i| sessions[i].started_at);
+    }
+
+    let mut order = Vec::with_capacity(sessions.len());
+    let mut visited = vec![false; sessions.len()];
+    let mut stack: Vec<(usize, usize)> = roots.into_iter().rev().map(|i| (i, 0)).collect();
+    while let Some((i, depth)) = stack.pop() {
+        if std::mem::replace(&mut visited[i], true) {
+            continue;
+        }
+        order.push((i, depth));
+        if let Some(kids) = children.get(&i) {
+            stack.extend(kids.iter().rev().map(|&k| (k, depth + 1)));
+        }
+    }
+    // Sessions caught in a parent cycle are not reachable from a root
+    for (i, seen) in visited.iter().enumerate() {
+        if !seen {
+            order.push((i, 0));
+        }
+    }
+    order
+}
+
+/// Whether a process with `pid` is still running. On platforms where this
+/// cannot be determined the process is assumed alive, so nothing is reaped.
+pub fn is_process_alive(pid: u32) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        std::path::Path::new("/proc").join(pid.to_string()).exists()
+    }
+    #[cfg(all(unix, not(target_os = "linux")))]
+    {
+        std::process::Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(true)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = pid;
+        true
+    }
+}
+
+/// Check that a session tag is a single word of letters, digits and
+/// `_ . : / -`, so tags stay unambiguous in listings and searches
+pub fn validate_tag(tag: &str) -> Result<()> {
+    if tag.is_empty()
+        || !tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_.:/-".contains(c))
+    {
+        return Err(Error::invalid_arguments(format!(
+            "Invalid tag '{}': use letters, digits and _ . : / -",
+            tag
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(id: &str, parent: Option<&str>, minute: u32) -> Session {
+        use crate::types::{HostId, SessionId};
+        use chrono::TimeZone;
+        Session {
+            id: SessionId::new(id.to_string()),
+            host_id: HostId::new(1),
+            hostname: "h".to_string(),
+            started_at: chrono::Utc
+                .with_ymd_and_hms(2026, 1, 1, 0, minute, 0)
+                .unwrap(),
+            ended_at: None,
+            pid: None,
+            tty: None,
+            name: None,
+            note: None,
+            tags: Vec::new(),
+            replay_of: None,
+            parent_id: parent.map(String::from),
+        }
+    }
+
+    #[test]
+    fn test_tree_order() {
+        // Newest first, as listed
+        let sessions = vec![
+            session("tmux-2", Some("zsh-1"), 3),
+            session("other", None, 2),
+            session("ssh", Some("tmux-1"), 2),
+            session("tmux-1", Some("zsh-1"), 1),
+            session("zsh-1", Some("gone"), 0),
+        ];
+        let order: Vec<(&str, usize)> = tree_order(&sessions)
+            .into_iter()
+            .map(|(i, depth)| (sessions[i].id.as_str(), depth))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                ("other", 0),
+                ("zsh-1", 0),
+                ("tmux-1", 1),
+                ("ssh", 2),
+                ("tmux-2", 1),
+            ]
+        );
+
+        // Cycles do not lose sessions
+        let cyclic = vec![session("a", Some("b"), 0), session("b", Some("a"), 1)];
+        assert_eq!(tree_order(&cyclic).len(), 2);
+    }
+
+    #[test]
+    fn test_validate_tag() {
+        assert!(validate_tag("oncall").is_ok());
+        assert!(validate_tag("incident:4211").is_ok());
+        assert!(validate_tag("").is_err());
+        assert!(validate_tag("two words").is_err());
+        assert!(validate_tag("a,b").is_err());
+    }
+
+    #[test]
+    fn test_is_process_alive() {
+        assert!(is_process_alive(std::process::id()));
+        assert!(!is_process_alive(u32::MAX - 1));
+    }
+}
@@ -0,0 +1,281 @@
+//! Structured redaction for URL query strings and request bodies
+//!
+//! Secrets often travel as key/value pairs that a single regex cannot
+//! describe well: query parameters (`?access_token=…&sig=…`), presigned S3
+//! URLs, `curl -d` form bodies and inline JSON payloads. This module locates
+//! those pairs and reports the values whose key is on the sensitive-key list.
+
+use crate::rules::{RuleMatch, remove_overlaps};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Default sensitive keys. A key matches when it equals an entry or ends with
+/// `_<entry>` after normalisation, so `db_password` and `X-Amz-Signature` are
+/// covered by `password` and `signature`.
+pub const DEFAULT_SENSITIVE_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "pwd",
+    "pass",
+    "passphrase",
+    "secret",
+    "token",
+    "auth",
+    "api_key",
+    "apikey",
+    "access_key",
+    "secret_key",
+    "private_key",
+    "signature",
+    "sig",
+    "credential",
+];
+
+static URL_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)\b[a-z][a-z0-9+.-]*://[^\s'"<>]+"#).unwrap());
+
+static BODY_FLAG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:^|\s)(?:-d|--data(?:-raw|-binary|-urlencode|-ascii)?|-F|--form(?:-string)?|--post-data|--body-data)(?:=|\s+)",
+    )
+    .unwrap()
+});
+
+static JSON_PAIR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\\?"([A-Za-z0-9_.\[\]-]+)\\?"\s*:\s*(?:\\?"([^"\\]*)\\?"|([A-Za-z0-9_.+-]+))"#)
+        .unwrap()
+});
+
+/// Sensitive-key list used to decide which values to redact
+#[derive(Debug, Clone, Default)]
+pub struct SensitiveKeys {
+    keys: Vec<String>,
+}
+
+impl SensitiveKeys {
+    /// Build a key list from configuration entries
+    pub fn new<S: AsRef<str>>(keys: &[S]) -> Self {
+        Self {
+            keys: keys
+                .iter()
+                .map(|k| normalize_key(k.as_ref()))
+                .filter(|k| !k.is_empty())
+                .collect(),
+        }
+    }
+
+    /// The built-in key list
+    pub fn builtin() -> Self {
+        Self::new(DEFAULT_SENSITIVE_KEYS)
+    }
+
+    /// Whether the list is empty (structured redaction disabled)
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Whether `key` names a sensitive value
+    pub fn is_sensitive(&self, key: &str) -> bool {
+        let key = normalize_key(key);
+        self.keys
+            .iter()
+            .any(|k| key == *k || key.ends_with(&format!("_{k}")))
+    }
+
+    /// Find sensitive values in URL query strings, form bodies and inline JSON
+    pub fn find_secrets(&self, command: &str) -> Vec<RuleMatch> {
+        if self.keys.is_empty() {
+            return Vec::new();
+        }
+
+        let mut matches = Vec::new();
+
+        for url in URL_RE.find_iter(command) {
+            let Some(q) = url.as_str().find('?') else {
+                continue;
+            };
+            let query = &url.as_str()[q + 1..];
+            let query = query.split('#').next().unwrap_or(query);
+            self.scan_pairs(query, url.start() + q + 1, "url-query", &mut matches);
+        }
+
+        for flag in BODY_FLAG_RE.find_iter(command) {
+            let (body, offset) = shell_argument(command, flag.end());
+            let trimmed = body.trim_start();
+            if trimmed.starts_with('{') || trimmed.starts_with('[') || trimmed.starts_with('@') {
+                continue;
+            }
+            self.scan_pairs(body, offset, "form-body", &mut matches);
+        }
+
+        for caps in JSON_PAIR_RE.captures_iter(command) {
+            let (Some(key), Some(value)) = (caps.get(1), caps.get(2).or_else(|| caps.get(3)))
+            else {
+                continue;
+            };
+            if matches!(value.as_str(), "" | "null" | "true" | "false") {
+                continue;
+            }
+            if self.is_sensitive(key.as_str()) {
+                matches.push(secret_match(
+                    "json-body",
+                    key.as_str(),
+                    value.start(),
+                    value.as_str(),
+                ));
+            }
+        }
+
+        remove_overlaps(matches)
+    }
+
+    /// Scan `key=value&key=value` pairs starting at byte `offset` of the command
+    fn scan_pairs(&self, pairs: &str, offset: usize, source: &str, out: &mut Vec<RuleMatch>) {
+        let mut pos = offset;
+        for pair in pairs.split('&') {
+            if let Some((key, value)) = pair.split_once('=')
+                && !value.is_empty()
+                && self.is_sensitive(key)
+            {
+                out.push(secret_match(source, key, pos + key.len() + 1, value));
+            }
+            pos += pair.len() + 1;
+        }
+    }
+}
+
+/// Token type recorded for a value stored under `key`
+pub fn token_type_for_key(key: &str) -> &'static str {
+    let key = normalize_key(key);
+    if key.contains("pass") || key.ends_with("pwd") {
+        "password"
+    } else if key.contains("signature") || key == "sig" || key.ends_with("_sig") {
+        "signature"
+    } else if key.contains("secret") || key.contains("private_key") {
+        "secret"
+    } else if key.contains("credential") || key.contains("access_key") {
+        "credential"
+    } else {
+        "api_key"
+    }
+}
+
+fn secret_match(source: &str, key: &str, start: usize, value: &str) -> RuleMatch {
+    RuleMatch {
+        rule: source.to_string(),
+        token_type: token_type_for_key(key).to_string(),
+        start,
+        end: start + value.len(),
+        value: value.to_string(),
+    }
+}
+
+/// Lowercase a key, take the innermost `[...]` segment (`user[password]`)
+/// and fold `-` into `_`
+fn normalize_key(key: &str) -> String {
+    let key = key.trim().trim_end_matches(']');
+    let key = key.rsplit('[').next().unwrap_or(key);
+    key.to_lowercase().replace('-', "_")
+}
+
+/// Read one shell argument starting at `start`, honouring simple quoting.
+/// Returns the unquoted contents and their byte offset in `command`.
+fn shell_argument(command: &str, start: usize) -> (&str, usize) {
+    let rest = &command[start..];
+    match rest.chars().next() {
+        Some(quote @ ('\'' | '"')) => {
+            let inner = &rest[1..];
+            let mut end = inner.len();
+            let mut escaped = false;
+            for (i, c) in inner.char_indices() {
+                if quote == '"' && c == '\\' && !escaped {
+                    escaped = true;
+                    continue;
+                }
+                if c == quote && !escaped {
+                    end = i;
+                    break;
+                }
+                escaped = false;
+            }
+            (&inner[..end], start + 1)
+        }
+        _ => {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (&rest[..end], start)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(command: &str) -> Vec<(String, String)> {
+        SensitiveKeys::builtin()
+            .find_secrets(command)
+            .into_iter()
+            .map(|m| {
+                assert_eq!(&command[m.start..m.end], m.value);
+                (m.token_type, m.value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_url_query_parameters() {
+        let found = values("curl 'https://api.example.com/v1?page=2&access_token=abc123&sig=zzz9'");
+        assert_eq!(
+            found,
+            vec![
+                ("api_key".to_string(), "abc123".to_string()),
+                ("signature".to_string(), "zzz9".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_presigned_s3_url() {
+        let cmd = "wget https://b.s3.amazonaws.com/k?X-Amz-Algorithm=AWS4-HMAC-SHA256&X-Amz-Credential=AKIAEXAMPLE%2F20240101&X-Amz-Security-Token=FwoGZX&X-Amz-Signature=deadbeef#frag";
+        let found: Vec<String> = values(cmd).into_iter().map(|(_, v)| v).collect();
+        assert_eq!(found, vec!["AKIAEXAMPLE%2F20240101", "FwoGZX", "deadbeef"]);
+    }
+
+    #[test]
+    fn test_json_body() {
+        let found =
+            values(r#"curl -d '{"user":"bob","password":"hunter2","pin":1234}' https://x.io"#);
+        assert_eq!(found, vec![("password".to_string(), "hunter2".to_string())]);
+
+        let escaped = values(r#"curl -d "{\"client_secret\":\"s3cr3t\"}" https://x.io"#);
+        assert_eq!(escaped, vec![("secret".to_string(), "s3cr3t".to_string())]);
+    }
+
+    #[test]
+    fn test_form_body() {
+        let found = values("curl --data 'user=bob&db_password=pa55&remember=1' https://x.io");
+        assert_eq!(found, vec![("password".to_string(), "pa55".to_string())]);
+
+        let found = values("curl -F user[password]=pa55 -F file=@a.txt https://x.io");
+        assert_eq!(found, vec![("password".to_string(), "pa55".to_string())]);
+    }
+
+    #[test]
+    fn test_non_sensitive_keys_untouched() {
+        assert!(values("curl 'https://example.com/search?q=token&page=2'").is_empty());
+        assert!(values(r#"echo '{"keyboard":"qwerty","bypass":"yes"}'"#).is_empty());
+        assert!(
+            SensitiveKeys::new::<&str>(&[])
+                .find_secrets("https://x?token=1")
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_custom_key_list() {
+        let keys = SensitiveKeys::new(&["otp"]);
+        let found = keys.find_secrets("https://x.io/login?otp=123456&token=abc");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].value, "123456");
+    }
+}
@@ -0,0 +1,289 @@
+//! Session transcripts for zam
+//!
+//! Turns the commands of one session into documentation: a shell script
+//! that replays it (with `cd` steps and failed commands commented out), a
+//! Markdown runbook, or JSON. Redaction placeholders are kept unless the
+//! caller restores the stored token values first.
+
+use crate::database::{CommandEntry, Session, Token};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// A session and its commands, oldest first
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub session: Session,
+    pub commands: Vec<CommandEntry>,
+}
+
+/// JSON shape of a transcript
+#[derive(Serialize)]
+struct TranscriptJson<'a> {
+    id: &'a str,
+    name: Option<&'a str>,
+    hostname: &'a str,
+    tags: &'a [String],
+    note: Option<&'a str>,
+    started_at: DateTime<Utc>,
+    ended_at: Option<DateTime<Utc>>,
+    commands: &'a [CommandEntry],
+}
+
+impl Transcript {
+    pub fn new(session: Session, mut commands: Vec<CommandEntry>) -> Self {
+        commands.sort_by_key(|c| (c.timestamp, c.id.as_i64()));
+        Self { session, commands }
+    }
+
+    /// Replace redaction placeholders with the original values from `tokens`
+    pub fn reveal(&mut self, tokens: &[Token]) {
+        for command in self.commands.iter_mut().filter(|c| c.redacted) {
+            for token in tokens.iter().filter(|t| t.command_id == command.id) {
+                command.command = command
+                    .command
+                    .replace(&token.placeholder, &token.original_value);
+            }
+        }
+    }
+
+    /// Time from the session start to its end (or its last command)
+    pub fn duration(&self) -> Duration {
+        let end = self
+            .session
+            .ended_at
+            .or_else(|| self.commands.last().map(|c| c.timestamp))
+            .unwrap_or(self.session.started_at);
+        end - self.session.started_at
+    }
+
+    /// Render as a POSIX shell script
+    pub fn to_shell_script(&self) -> String {
+        let session = &self.session;
+        let mut out = String::from("#!/bin/sh\n");
+        out.push_str(&format!("# Session: {}\n", session.id));
+        if let Some(ref name) = session.name {
+            out.push_str(&format!("# Name: {}\n", name));
+        }
+        out.push_str(&format!("# Host: {}\n", session.hostname));
+        out.push_str(&format!(
+            "# Started: {}\n",
+            session.started_at.format("%Y-%m-%d %H:%M:%S")
+        ));
+        if !session.tags.is_empty() {
+            out.push_str(&format!("# Tags: {}\n", session.tags.join(", ")));
+        }
+        if let Some(ref note) = session.note {
+            for line in note.lines() {
+                out.push_str(&format!("# Note: {}\n", line));
+            }
+        }
+
+        let mut directory: Option<&str> = None;
+        for cmd in &self.commands {
+            out.push('\n');
+            // Imported commands carry a placeholder rather than a real path
+            if cmd.directory.starts_with('/') && directory != Some(cmd.directory.as_str()) {
+                out.push_str(&format!("cd {}\n", shell_quote(&cmd.directory)));
+                directory = Some(&cmd.directory);
+            }
+
+            let status = cmd
+                .exit_code
+                .map(|code| format!(" exit={}", code))
+                .unwrap_or_default();
+            let failed = cmd.exit_code.is_some_and(|code| code != 0);
+            out.push_str(&format!(
+                "# {}{}{}\n",
+                cmd.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                status,
+                if failed {
+                    " (failed, not replayed)"
+                } else {
+                    ""
+                }
+            ));
+            for line in cmd.command.lines() {
+                if failed {
+                    out.push_str("# ");
+                }
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    /// Render as a Markdown runbook
+    pub fn to_markdown(&self) -> String {
+        let session = &self.session;
+        let mut out = format!("# {}\n\n", session.label());
+        out.push_str(&format!("- **Session:** `{}`\n", session.id));
+        out.push_str(&format!("- **Host:** {}\n", session.hostname));
+        out.push_str(&format!(
+            "- **Started:** {}\n",
+            session.started_at.format("%Y-%m-%d %H:%M:%S")
+        ));
+        match session.ended_at {
+            Some(ended) => out.push_str(&format!(
+                "- **Ended:** {}\n",
+                ended.format("%Y-%m-%d %H:%M:%S")
+            )),
+            None => out.push_str("- **Ended:** still active\n"),
+        }
+        out.push_str(&format!(
+            "- **Duration:** {}\n",
+            format_duration(self.duration())
+        ));
+        if !session.tags.is_empty() {
+            out.push_str(&format!("- **Tags:** {}\n", session.tags.join(", ")));
+        }
+        if let Some(ref note) = session.note {
+            out.push('\n');
+            for line in note.lines() {
+                out.push_str(&format!("> {}\n", line));
+            }
+        }
+
+        out.push_str("\n## Steps\n");
+        let mut previous = session.started_at;
+        for (i, cmd) in self.commands.iter().enumerate() {
+            let status = match cmd.exit_code {
+                Some(0) => " — ok".to_string(),
+                Some(code) => format!(" — **exit {}**", code),
+                None => String::new(),
+            };
+            out.push_str(&format!(
+                "\n{}. `{}` in `{}` (+{}){}\n\n",
+                i + 1,
+                cmd.timestamp.format("%H:%M:%S"),
+                cmd.directory,
+                format_duration(cmd.timestamp - previous),
+                status
+            ));
+            previous = cmd.timestamp;
+
+            // A fence longer than any backtick run inside the command
+            let longest_run = cmd
+                .command
+                .split(|c| c != '`')
+                .map(str::len)
+                .max()
+                .unwrap_or(0);
+            let fence = "`".repeat(longest_run.max(2) + 1);
+            out.push_str(&format!("   {}sh\n", fence));
+            for line in cmd.command.lines() {
+                out.push_str(&format!("   {}\n", line));
+            }
+            out.push_str(&format!("   {}\n", fence));
+        }
+        out
+    }
+
+    /// Render as pretty-printed JSON
+    pub fn to_json(&self) -> crate::error::Result<String> {
+        let session = &self.session;
+        Ok(serde_json::to_string_pretty(&TranscriptJson {
+            id: session.id.as_str(),
+            name: session.name.as_deref(),
+            hostname: &session.hostname,
+            tags: &session.tags,
+            note: session.note.as_deref(),
+            started_at: session.started_at,
+            ended_at: session.ended_at,
+            commands: &self.commands,
+        })?)
+    }
+}
+
+/// Quote a value for a POSIX shell, escaping embedded single quotes as '\''
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+/// Compact human-readable duration, e.g. `1h 2m 5s`
+fn format_duration(duration: Duration) -> String {
+    let total = duration.num_seconds().max(0);
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CommandId, HostId, SessionId};
+
+    fn transcript() -> Transcript {
+        let start: DateTime<Utc> = "2026-03-04T10:00:00Z".parse().unwrap();
+        let command = |id: i64, secs: i64, dir: &str, cmd: &str, exit: i32| CommandEntry {
+            id: CommandId::new(id),
+            session_id: SessionId::new("zsh-1".to_string()),
+            command: cmd.to_string(),
+            timestamp: start + Duration::seconds(secs),
+            directory: dir.to_string(),
+            redacted: cmd.contains('<'),
+            exit_code: Some(exit),
+        };
+        Transcript::new(
+            Session {
+                id: SessionId::new("zsh-1".to_string()),
+                host_id: HostId::new(1),
+                hostname: "web1".to_string(),
+                started_at: start,
+                ended_at: Some(start + Duration::seconds(125)),
+                pid: None,
+                tty: None,
+                name: Some("incident 4211".to_string()),
+                note: Some("api 500s".to_string()),
+                tags: vec!["oncall".to_string()],
+                replay_of: None,
+                parent_id: None,
+            },
+            vec![
+                command(3, 90, "/srv/app", "psql -p <password:1>", 0),
+                command(1, 10, "/srv/app", "systemctl status api", 0),
+                command(2, 30, "/srv/it's", "systemctl restart ap", 5),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_shell_script() {
+        let script = transcript().to_shell_script();
+        assert!(script.starts_with("#!/bin/sh\n# Session: zsh-1\n# Name: incident 4211\n"));
+        assert_eq!(script.matches("cd '/srv/app'").count(), 2);
+        assert!(script.contains("cd '/srv/it'\\''s'\n"));
+        assert!(script.contains("exit=5 (failed, not replayed)\n# systemctl restart ap\n"));
+        assert!(script.contains("exit=0\nsystemctl status api\n"));
+        assert!(script.contains("psql -p <password:1>"));
+    }
+
+    #[test]
+    fn test_markdown() {
+        let md = transcript().to_markdown();
+        assert!(md.starts_with("# incident 4211\n"));
+        assert!(md.contains("- **Duration:** 2m 5s\n"));
+        assert!(md.contains("> api 500s\n"));
+        assert!(md.contains("2. `10:00:30` in `/srv/it's` (+20s) — **exit 5**"));
+        assert!(md.contains("   ```sh\n   psql -p <password:1>\n   ```\n"));
+    }
+
+    #[test]
+    fn test_reveal() {
+        let mut transcript = transcript();
+        transcript.reveal(&[Token {
+            id: 1,
+            command_id: CommandId::new(3),
+            token_type: "password".to_string(),
+            placeholder: "<password:1>".to_string(),
+            original_value: "hunter2".to_string(),
+            created_at: Utc::now(),
+        }]);
+        assert!(transcript.to_json().unwrap().contains("psql -p hunter2"));
+    }
+}
@@ -1,21 +1,38 @@
-//! Interactive TUI for browsing and managing all database entities
-
-use crate::database::{Alias, CommandEntry, Database, Host, Session, Token};
-use crate::error::Result;
+//! Interactive TUI for browsing and managing history, sessions, aliases,
+//! hosts and tokens
+
+use crate::agent::ActorFilter;
+use crate::alias::{self as alias_check, AliasConflict, AliasSuggestion};
+use crate::backend::HistoryQuery;
+use crate::clipboard::Clipboard;
+use crate::config::{CommandJoin, ThemeBase, ThemeConfig, TuiConfig};
+use crate::database::{Alias, CommandEntry, CommandFilter, Host, Session, Token, UsageStats};
+use crate::error::{Error, Result};
+use crate::keymap::{Action, Key, Keymap, Section};
+use crate::private::EphemeralBuffer;
+use crate::session::tree_order;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
 use ratatui::{
-    Frame, Terminal,
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    Frame, Terminal, TerminalOptions, Viewport,
+    backend::{Backend, ClearType, CrosstermBackend, WindowSize},
+    buffer::Cell as BufferCell,
+    layout::{Constraint, Direction, Layout, Position, Rect, Size},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState, Wrap},
+    widgets::{
+        Bar, BarChart, Block, Borders, Cell, Padding, Paragraph, Row, Sparkline, Table, TableState,
+        Wrap,
+    },
 };
+use rustix::event::{PollFd, PollFlags, Timespec};
+use std::collections::BTreeMap;
 use std::fs::File;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
 
 /// Color theme for the TUI, with dark and light variants.
 /// Palette derived from Matrix and Photophobia Zed themes.
@@ -78,6 +95,56 @@ impl Theme {
         }
     }
 
+    /// The configured palette: `tui.theme.base` with `tui.theme.colors` on top
+    fn from_config(config: &ThemeConfig) -> Result<Self> {
+        let theme = match config.base {
+            ThemeBase::Auto => Self::detect(),
+            ThemeBase::Dark => Self::dark(),
+            ThemeBase::Light => Self::light(),
+        };
+        theme.with_colors(&config.colors)
+    }
+
+    fn with_colors(mut self, colors: &BTreeMap<String, String>) -> Result<Self> {
+        for (name, value) in colors {
+            let slot = match name.as_str() {
+                "tab_number" => &mut self.tab_number,
+                "tab_text" => &mut self.tab_text,
+                "tab_highlight" => &mut self.tab_highlight,
+                "header" => &mut self.header,
+                "row_highlight" => &mut self.row_highlight,
+                "status_default" => &mut self.status_default,
+                "status_active" => &mut self.status_active,
+                "popup_text" => &mut self.popup_text,
+                "popup_confirm" => &mut self.popup_confirm,
+                "popup_accent" => &mut self.popup_accent,
+                "match_highlight" => &mut self.match_highlight,
+                "success" => &mut self.success,
+                "error" => &mut self.error,
+                "flash_bg" => &mut self.flash_bg,
+                "flash_fg" => &mut self.flash_fg,
+                _ => {
+                    let reason = format!(
+                        "unknown colour '{}', expected one of: {}",
+                        name,
+                        THEME_COLORS.join(", ")
+                    );
+                    return Err(Error::config_validation(
+                        "tui.theme.colors",
+                        reason.as_str(),
+                    ));
+                }
+            };
+            *slot = value.parse().map_err(|_| {
+                Error::config_validation(
+                    "tui.theme.colors",
+                    format!("invalid colour '{}' for {}", value, name).as_str(),
+                )
+            })?;
+        }
+        Ok(self)
+    }
+
     fn detect() -> Self {
         // ZAM_THEME env var overrides auto-detection
         if let Ok(val) = std::env::var("ZAM_THEME") {
@@ -117,7 +184,34 @@ impl Theme {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+/// Palette entries that `tui.theme.colors` can set
+pub const THEME_COLORS: &[&str] = &[
+    "tab_number",
+    "tab_text",
+    "tab_highlight",
+    "header",
+    "row_highlight",
+    "status_default",
+    "status_active",
+    "popup_text",
+    "popup_confirm",
+    "popup_accent",
+    "match_highlight",
+    "success",
+    "error",
+    "flash_bg",
+    "flash_fg",
+];
+
+/// Check `tui.theme` without detecting the terminal's background
+pub fn check_theme(config: &ThemeConfig) -> Result<()> {
+    Theme::dark().with_colors(&config.colors).map(|_| ())
+}
+
+/// Command table columns that `tui.hide_columns` can leave out
+pub const COLUMN_NAMES: &[&str] = &["exit", "time", "id", "directory", "redacted"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Tab {
     Local,
     Frequent,
@@ -126,10 +220,11 @@ enum Tab {
     Hosts,
     Sessions,
     Tokens,
+    Stats,
     Help,
 }
 
-const TABS: [Tab; 8] = [
+const TABS: [Tab; 9] = [
     Tab::Commands,
     Tab::Local,
     Tab::Sessions,
@@ -137,6 +232,7 @@ const TABS: [Tab; 8] = [
     Tab::Aliases,
     Tab::Hosts,
     Tab::Tokens,
+    Tab::Stats,
     Tab::Help,
 ];
 
@@ -150,6 +246,7 @@ impl Tab {
             Tab::Hosts => "hosts",
             Tab::Sessions => "sessions",
             Tab::Tokens => "tokens",
+            Tab::Stats => "stats",
             Tab::Help => "?",
         }
     }
@@ -159,11 +256,53 @@ impl Tab {
     }
 }
 
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 enum Mode {
     Filter,
     Confirm,
     EditAlias,
+    EditCommand,
+    NewAlias,
+}
+
+/// What the shell should do with the chosen command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TuiAction {
+    /// Run it right away
+    Run,
+    /// Put it on the prompt for further editing
+    Insert,
+}
+
+impl TuiAction {
+    /// Name printed for shell widgets (`zam tui --widget`)
+    pub fn name(self) -> &'static str {
+        match self {
+            TuiAction::Run => "run",
+            TuiAction::Insert => "insert",
+        }
+    }
+}
+
+/// The command(s) chosen in the TUI and what to do with them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TuiSelection {
+    pub command: String,
+    pub action: TuiAction,
+}
+
+impl TuiSelection {
+    /// What `zam tui --widget` prints: the action on the first line, then
+    /// the command
+    pub fn widget_output(&self) -> String {
+        format!("{}\n{}\n", self.action.name(), self.command)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum ConfirmAction {
+    Delete,
+    AllowToken,
 }
 
 #[derive(Clone, Copy, PartialEq)]
@@ -172,32 +311,130 @@ enum EditField {
     Description,
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum NewAliasField {
+    Name,
+    Command,
+    Description,
+}
+
+/// Alias being created in [`Mode::NewAlias`]
+struct NewAlias {
+    name: String,
+    command: String,
+    description: String,
+    field: NewAliasField,
+    /// Aliases that exist, to check the name against
+    existing: Vec<Alias>,
+    /// What is wrong with `name`, shown under the fields
+    conflict: Option<AliasConflict>,
+    /// Name whose conflict the user chose to ignore by pressing Enter again
+    overridden: Option<String>,
+    /// Why saving failed
+    error: Option<String>,
+}
+
+impl NewAlias {
+    fn new(name: String, command: String, existing: Vec<Alias>) -> Self {
+        let mut new_alias = Self {
+            name,
+            command,
+            description: String::new(),
+            field: NewAliasField::Name,
+            existing,
+            conflict: None,
+            overridden: None,
+            error: None,
+        };
+        new_alias.check_name();
+        new_alias
+    }
+
+    fn check_name(&mut self) {
+        self.conflict = alias_check::find_conflict(&self.name, &self.existing);
+        self.error = None;
+    }
+
+    fn field_mut(&mut self) -> &mut String {
+        match self.field {
+            NewAliasField::Name => &mut self.name,
+            NewAliasField::Command => &mut self.command,
+            NewAliasField::Description => &mut self.description,
+        }
+    }
+}
+
 struct FrequentCommand {
     command: String,
     count: usize,
 }
 
+/// Commands shown around the previewed one, on each side
+const PREVIEW_CONTEXT: usize = 3;
+
+/// Terminal width from which the preview sits beside the table
+const PREVIEW_SIDE_MIN_WIDTH: u16 = 120;
+
+/// Frequent commands considered for alias suggestions
+const SUGGESTION_POOL: usize = 500;
+
+/// Alias suggestions listed on the aliases tab
+const SUGGESTION_LIMIT: usize = 50;
+
+/// Entries in each ranking of the stats tab
+const STATS_TOP: usize = 10;
+
+/// Time window of the stats tab when the filter names none
+const STATS_DEFAULT_DAYS: i64 = 90;
+
+/// Longest window of the stats tab; longer ones are cut to this
+const STATS_MAX_DAYS: i64 = 100 * 365;
+
+/// Details shown in the preview pane for the highlighted command
+struct CommandPreview {
+    entry: CommandEntry,
+    host: String,
+    session: String,
+    runs: usize,
+    /// Commands run just before it in the same session, oldest first
+    before: Vec<CommandEntry>,
+    /// Commands run just after it in the same session, oldest first
+    after: Vec<CommandEntry>,
+}
+
 struct AppTUI<'a> {
-    db: &'a Database,
+    history: &'a dyn HistoryQuery,
     cwd: String,
     home: String,
+    /// The shell's session (`$ZAM_SESSION_ID`), if run from one
+    session_id: Option<String>,
     tab: Tab,
     mode: Mode,
     theme: Theme,
+    keymap: Keymap,
+    /// In vi normal mode, where plain keys are commands
+    normal_mode: bool,
+    hidden_columns: Vec<String>,
 
     // Data
     commands: Vec<CommandEntry>,
     local_commands: Vec<CommandEntry>,
     frequent: Vec<FrequentCommand>,
     aliases: Vec<Alias>,
+    /// Suggested aliases, listed instead of `aliases` when `show_suggestions`
+    suggestions: Vec<AliasSuggestion>,
+    show_suggestions: bool,
     hosts: Vec<Host>,
     sessions: Vec<Session>,
     session_cmd_counts: Vec<usize>,
+    session_depths: Vec<usize>,
     tokens: Vec<Token>,
+    usage: UsageStats,
 
     // Session detail drill-down
     session_commands: Vec<CommandEntry>,
     session_detail_id: Option<String>,
+    include_descendants: bool,
 
     // Pagination (Commands and Sessions tabs)
     page: usize,
@@ -210,68 +447,107 @@ struct AppTUI<'a> {
 
     // Filter
     filter: String,
+    actor_filter: ActorFilter,
 
-    // Confirm delete
+    // Confirm delete / allow
     confirm_msg: String,
+    confirm_action: ConfirmAction,
 
     // Edit alias
     edit_field: EditField,
     edit_buf: String,
     edit_alias_name: String,
+    new_alias: Option<NewAlias>,
+    /// Cursor position (in chars) while editing a command
+    edit_cursor: usize,
+
+    // Multi-select: (command as listed, command to return), in mark order
+    marked: Vec<(String, String)>,
+    join: CommandJoin,
 
     // Status
+    clipboard: Clipboard,
     copied_at: Option<std::time::Instant>,
     status: Option<String>,
     show_values: bool,
     relative_time: bool,
+    show_preview: bool,
+    preview: Option<CommandPreview>,
     running: bool,
-    selected_command: Option<String>,
+    selected_command: Option<TuiSelection>,
 }
 
 impl<'a> AppTUI<'a> {
-    fn new(db: &'a Database, cwd: String) -> Result<Self> {
+    fn new(
+        history: &'a dyn HistoryQuery,
+        cwd: String,
+        session_id: Option<String>,
+        theme: Theme,
+        config: &TuiConfig,
+    ) -> Result<Self> {
         let mut app = Self {
-            db,
+            history,
             cwd,
             home: std::env::var("HOME").unwrap_or_default(),
+            session_id,
             tab: Tab::Commands,
             mode: Mode::Filter,
-            theme: Theme::detect(),
+            theme,
+            keymap: Keymap::from_config(config)?,
+            normal_mode: false,
+            hidden_columns: config.hide_columns.clone(),
             commands: Vec::new(),
             local_commands: Vec::new(),
             session_commands: Vec::new(),
             session_detail_id: None,
+            include_descendants: false,
             frequent: Vec::new(),
             aliases: Vec::new(),
+            suggestions: Vec::new(),
+            show_suggestions: false,
             hosts: Vec::new(),
             sessions: Vec::new(),
             session_cmd_counts: Vec::new(),
+            session_depths: Vec::new(),
             tokens: Vec::new(),
+            usage: UsageStats::default(),
             page: 0,
             page_size: 100,
             total_paged_rows: 0,
             table_state: TableState::default(),
             row_count: 0,
             filter: String::new(),
+            actor_filter: ActorFilter::All,
             confirm_msg: String::new(),
+            confirm_action: ConfirmAction::Delete,
             edit_field: EditField::Command,
             edit_buf: String::new(),
             edit_alias_name: String::new(),
+            new_alias: None,
+            edit_cursor: 0,
+            marked: Vec::new(),
+            join: config.join,
+            clipboard: Clipboard::new(config.clipboard),
             copied_at: None,
             status: None,
             show_values: false,
-            relative_time: db.get_bool_preference("relative_time").unwrap_or(false),
+            relative_time: history
+                .get_bool_preference("relative_time")
+                .unwrap_or(false),
+            show_preview: history.get_bool_preference("preview").unwrap_or(false),
+            preview: None,
             running: true,
             selected_command: None,
         };
         // Restore last tab from preferences
-        if let Ok(Some(val)) = db.get_preference("last_tab")
+        if let Ok(Some(val)) = history.get_preference("last_tab")
             && let Ok(idx) = val.parse::<usize>()
             && idx < TABS.len()
         {
             app.tab = TABS[idx];
         }
         app.load_tab()?;
+        app.refresh_preview();
         Ok(app)
     }
 
@@ -284,32 +560,43 @@ impl<'a> AppTUI<'a> {
         };
         match self.tab {
             Tab::Commands => {
-                self.total_paged_rows = self.db.count_unique_commands_filtered(filter)?;
-                self.commands = self.db.get_unique_commands_filtered(
+                self.total_paged_rows = self
+                    .history
+                    .count_unique_commands_filtered(filter, &self.actor_filter)?;
+                self.commands = self.history.get_unique_commands_filtered(
                     self.page * self.page_size,
                     self.page_size,
                     filter,
+                    &self.actor_filter,
                 )?;
+                if self.page == 0 && self.actor_filter != ActorFilter::Agents {
+                    self.prepend_ephemeral_commands()?;
+                }
                 self.row_count = self.commands.len();
             }
             Tab::Sessions => {
-                self.total_paged_rows = self.db.count_sessions_filtered(filter)?;
-                self.sessions = self.db.get_sessions_filtered(
+                self.total_paged_rows = self.history.count_sessions_filtered(filter)?;
+                let mut sessions = self.history.get_sessions_filtered(
                     self.page * self.page_size,
                     self.page_size,
                     filter,
                 )?;
+                // Children follow their parent when both are on the page
+                let order = tree_order(&sessions);
+                self.session_depths = order.iter().map(|&(_, depth)| depth).collect();
+                let mut slots: Vec<Option<Session>> = sessions.drain(..).map(Some).collect();
+                self.sessions = order.iter().filter_map(|&(i, _)| slots[i].take()).collect();
                 let sids: Vec<&str> = self.sessions.iter().map(|s| s.id.as_ref()).collect();
-                self.session_cmd_counts = self.db.count_commands_for_sessions(&sids)?;
+                self.session_cmd_counts = self.history.count_commands_for_sessions(&sids)?;
                 self.row_count = self.sessions.len();
             }
             Tab::Local => {
-                self.local_commands = self.db.get_commands_for_directory(&self.cwd)?;
+                self.local_commands = self.history.get_commands_for_directory(&self.cwd)?;
                 self.row_count = self.local_commands.len();
             }
             Tab::Frequent => {
                 self.frequent = self
-                    .db
+                    .history
                     .get_frequent_commands(50)?
                     .into_iter()
                     .map(|(command, count)| FrequentCommand { command, count })
@@ -317,17 +604,28 @@ impl<'a> AppTUI<'a> {
                 self.row_count = self.frequent.len();
             }
             Tab::Aliases => {
-                self.aliases = self.db.list_aliases()?;
+                self.aliases = self.history.list_aliases()?;
                 self.row_count = self.aliases.len();
+                if self.show_suggestions {
+                    let frequent = self.history.get_frequent_commands(SUGGESTION_POOL)?;
+                    self.suggestions =
+                        alias_check::suggest_aliases(&frequent, &self.aliases, SUGGESTION_LIMIT);
+                    self.row_count = self.suggestions.len();
+                }
             }
             Tab::Hosts => {
-                self.hosts = self.db.get_hosts()?;
+                self.hosts = self.history.get_hosts()?;
                 self.row_count = self.hosts.len();
             }
             Tab::Tokens => {
-                self.tokens = self.db.get_all_tokens()?;
+                self.tokens = self.history.get_all_tokens()?;
                 self.row_count = self.tokens.len();
             }
+            Tab::Stats => {
+                let (filter, _) = stats_filter(&self.filter);
+                self.usage = self.history.usage_stats(&filter, STATS_TOP)?;
+                self.row_count = 0;
+            }
             Tab::Help => {
                 self.row_count = 0;
             }
@@ -370,6 +668,13 @@ impl<'a> AppTUI<'a> {
                 .filter(|(_, f)| self.matches_filter(&f.command))
                 .map(|(i, _)| i)
                 .collect(),
+            Tab::Aliases if self.show_suggestions => self
+                .suggestions
+                .iter()
+                .enumerate()
+                .filter(|(_, s)| self.matches_filter(&s.command))
+                .map(|(i, _)| i)
+                .collect(),
             Tab::Aliases => self
                 .aliases
                 .iter()
@@ -429,6 +734,11 @@ impl<'a> AppTUI<'a> {
                 .iter()
                 .filter(|f| self.matches_filter(&f.command))
                 .count(),
+            Tab::Aliases if self.show_suggestions => self
+                .suggestions
+                .iter()
+                .filter(|s| self.matches_filter(&s.command))
+                .count(),
             Tab::Aliases => self
                 .aliases
                 .iter()
@@ -453,7 +763,7 @@ impl<'a> AppTUI<'a> {
                 .count(),
             // Commands tab is DB-filtered, handled by early return above
             Tab::Commands => self.row_count,
-            Tab::Help => 0,
+            Tab::Stats | Tab::Help => 0,
         }
     }
 
@@ -483,6 +793,19 @@ impl<'a> AppTUI<'a> {
         self.table_state.select(Some(i));
     }
 
+    fn select_first(&mut self) {
+        if self.filtered_row_count() > 0 {
+            self.table_state.select(Some(0));
+        }
+    }
+
+    fn select_last(&mut self) {
+        let count = self.filtered_row_count();
+        if count > 0 {
+            self.table_state.select(Some(count - 1));
+        }
+    }
+
     fn next_tab(&mut self) -> Result<()> {
         let idx = (self.tab.index() + 1) % TABS.len();
         self.tab = TABS[idx];
@@ -490,7 +813,7 @@ impl<'a> AppTUI<'a> {
         self.page = 0;
         self.session_detail_id = None;
         self.session_commands.clear();
-        let _ = self.db.set_preference("last_tab", &idx.to_string());
+        let _ = self.history.set_preference("last_tab", &idx.to_string());
         self.load_tab()
     }
 
@@ -505,7 +828,7 @@ impl<'a> AppTUI<'a> {
         self.page = 0;
         self.session_detail_id = None;
         self.session_commands.clear();
-        let _ = self.db.set_preference("last_tab", &idx.to_string());
+        let _ = self.history.set_preference("last_tab", &idx.to_string());
         self.load_tab()
     }
 
@@ -530,6 +853,7 @@ impl<'a> AppTUI<'a> {
                     return;
                 }
             }
+            Tab::Aliases if self.show_suggestions => return,
             Tab::Aliases => {
                 if let Some(a) = self.aliases.get(idx) {
                     format!("Delete alias '{}'?", a.alias)
@@ -561,12 +885,41 @@ impl<'a> AppTUI<'a> {
                     return;
                 }
             }
-            Tab::Frequent | Tab::Help => return,
+            Tab::Frequent | Tab::Stats | Tab::Help => return,
         };
         self.confirm_msg = msg;
+        self.confirm_action = ConfirmAction::Delete;
+        self.mode = Mode::Confirm;
+    }
+
+    fn request_allow_token(&mut self) {
+        let Some(t) = self.resolve_selected().and_then(|idx| self.tokens.get(idx)) else {
+            return;
+        };
+        self.confirm_msg = format!(
+            "Mark token {} ({}) as not a secret and restore it?",
+            t.id, t.token_type
+        );
+        self.confirm_action = ConfirmAction::AllowToken;
         self.mode = Mode::Confirm;
     }
 
+    fn confirm(&mut self) -> Result<()> {
+        match self.confirm_action {
+            ConfirmAction::Delete => self.confirm_delete(),
+            ConfirmAction::AllowToken => self.confirm_allow_token(),
+        }
+    }
+
+    fn confirm_allow_token(&mut self) -> Result<()> {
+        if let Some(t) = self.resolve_selected().and_then(|idx| self.tokens.get(idx)) {
+            let token = self.history.allow_token(t.id)?;
+            self.status = Some(format!("{} allowed", token.placeholder));
+        }
+        self.mode = Mode::Filter;
+        self.load_tab()
+    }
+
     fn confirm_delete(&mut self) -> Result<()> {
         let Some(idx) = self.resolve_selected() else {
             self.mode = Mode::Filter;
@@ -575,41 +928,46 @@ impl<'a> AppTUI<'a> {
         match self.tab {
             Tab::Commands => {
                 if let Some(cmd) = self.commands.get(idx) {
-                    self.db.delete_command(cmd.id)?;
-                    self.status = Some("Entry deleted".into());
+                    if cmd.id.as_i64() == 0 {
+                        self.status =
+                            Some("Private entries are wiped when the session ends".into());
+                    } else {
+                        self.history.delete_command(cmd.id)?;
+                        self.status = Some("Entry deleted".into());
+                    }
                 }
             }
             Tab::Local => {
                 if let Some(cmd) = self.local_commands.get(idx) {
-                    self.db.delete_command(cmd.id)?;
+                    self.history.delete_command(cmd.id)?;
                     self.status = Some("Entry deleted".into());
                 }
             }
             Tab::Aliases => {
                 if let Some(a) = self.aliases.get(idx) {
-                    self.db.remove_alias(&a.alias)?;
+                    self.history.remove_alias(&a.alias)?;
                     self.status = Some(format!("Alias '{}' deleted", a.alias));
                 }
             }
             Tab::Hosts => {
                 if let Some(h) = self.hosts.get(idx) {
-                    self.db.delete_host(h.id)?;
+                    self.history.delete_host(h.id)?;
                     self.status = Some(format!("Host '{}' deleted", h.hostname));
                 }
             }
             Tab::Sessions => {
                 if let Some(s) = self.sessions.get(idx) {
-                    self.db.delete_session(s.id.as_ref())?;
+                    self.history.delete_session(s.id.as_ref())?;
                     self.status = Some("Session deleted".into());
                 }
             }
             Tab::Tokens => {
                 if let Some(t) = self.tokens.get(idx) {
-                    self.db.delete_token(t.id)?;
+                    self.history.delete_token(t.id)?;
                     self.status = Some("Token deleted".into());
                 }
             }
-            Tab::Frequent | Tab::Help => {}
+            Tab::Frequent | Tab::Stats | Tab::Help => {}
         }
         self.mode = Mode::Filter;
         self.load_tab()
@@ -649,6 +1007,106 @@ impl<'a> AppTUI<'a> {
         Ok(())
     }
 
+    /// Open the new alias popup for the selected command, or with the
+    /// selected suggestion's name
+    fn start_new_alias(&mut self) -> Result<()> {
+        let Some(command) = self.selected_listed_command() else {
+            return Ok(());
+        };
+        if alias_check::contains_placeholder(&command) {
+            self.status = Some("Cannot alias a redacted command".into());
+            return Ok(());
+        }
+        let existing = self.history.list_aliases()?;
+        let suggested = (self.tab == Tab::Aliases && self.show_suggestions)
+            .then(|| self.resolve_selected())
+            .flatten()
+            .and_then(|idx| self.suggestions.get(idx))
+            .map(|s| s.name.clone());
+        let name = suggested.unwrap_or_else(|| alias_check::suggest_name(&command, &existing, &[]));
+        self.new_alias = Some(NewAlias::new(name, command, existing));
+        self.mode = Mode::NewAlias;
+        Ok(())
+    }
+
+    fn handle_new_alias_key(&mut self, key: KeyEvent) -> Result<()> {
+        let Some(new_alias) = self.new_alias.as_mut() else {
+            self.mode = Mode::Filter;
+            return Ok(());
+        };
+        match key.code {
+            KeyCode::Esc => {
+                self.new_alias = None;
+                self.mode = Mode::Filter;
+            }
+            KeyCode::Tab | KeyCode::BackTab => {
+                let forward = key.code == KeyCode::Tab;
+                new_alias.field = match (new_alias.field, forward) {
+                    (NewAliasField::Name, true) | (NewAliasField::Description, false) => {
+                        NewAliasField::Command
+                    }
+                    (NewAliasField::Command, true) | (NewAliasField::Name, false) => {
+                        NewAliasField::Description
+                    }
+                    (NewAliasField::Description, true) | (NewAliasField::Command, false) => {
+                        NewAliasField::Name
+                    }
+                };
+            }
+            KeyCode::Backspace => {
+                new_alias.field_mut().pop();
+                new_alias.check_name();
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            {
+                new_alias.field_mut().push(c);
+                new_alias.check_name();
+            }
+            KeyCode::Enter => self.commit_new_alias()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Save the new alias unless its name conflicts. A conflict that only
+    /// shadows an executable is saved on the second Enter.
+    fn commit_new_alias(&mut self) -> Result<()> {
+        let Some(new_alias) = self.new_alias.as_mut() else {
+            return Ok(());
+        };
+        let command = new_alias.command.trim();
+        if command.is_empty() {
+            new_alias.error = Some("The command is empty".into());
+            return Ok(());
+        }
+        if let Some(conflict) = &new_alias.conflict {
+            if !conflict.can_override() {
+                return Ok(());
+            }
+            if new_alias.overridden.as_deref() != Some(new_alias.name.as_str()) {
+                new_alias.overridden = Some(new_alias.name.clone());
+                return Ok(());
+            }
+        }
+        if let Err(e) =
+            self.history
+                .add_alias(&new_alias.name, command, new_alias.description.trim())
+        {
+            new_alias.error = Some(e.to_string());
+            return Ok(());
+        }
+        self.status = Some(format!("Alias '{}' added", new_alias.name));
+        self.new_alias = None;
+        self.mode = Mode::Filter;
+        if self.tab == Tab::Aliases {
+            self.load_tab()?;
+        }
+        Ok(())
+    }
+
     fn start_edit_alias(&mut self) {
         if self.tab != Tab::Aliases {
             return;
@@ -673,7 +1131,8 @@ impl<'a> AppTUI<'a> {
         }
         match self.edit_field {
             EditField::Command => {
-                self.db.update_alias(&self.edit_alias_name, &value, None)?;
+                self.history
+                    .update_alias(&self.edit_alias_name, &value, None)?;
                 self.status = Some(format!("Alias '{}' command updated", self.edit_alias_name));
             }
             EditField::Description => {
@@ -683,7 +1142,7 @@ impl<'a> AppTUI<'a> {
                     .iter()
                     .find(|a| a.alias == self.edit_alias_name)
                 {
-                    self.db
+                    self.history
                         .update_alias(&self.edit_alias_name, &a.command, Some(&value))?;
                     self.status = Some(format!(
                         "Alias '{}' description updated",
@@ -696,18 +1155,55 @@ impl<'a> AppTUI<'a> {
         self.load_tab()
     }
 
+    /// Show commands buffered by an ephemeral private session above the
+    /// stored history. They are never written to the database.
+    fn prepend_ephemeral_commands(&mut self) -> Result<()> {
+        let Some(buffer) = self
+            .session_id
+            .as_deref()
+            .and_then(EphemeralBuffer::for_session)
+        else {
+            return Ok(());
+        };
+        let filter = self.filter.to_lowercase();
+        let mut seen = std::collections::HashSet::new();
+        let mut buffered: Vec<CommandEntry> = buffer
+            .command_entries()?
+            .into_iter()
+            .filter(|e| e.command.to_lowercase().contains(&filter))
+            .filter(|e| seen.insert(e.command.clone()))
+            .collect();
+        if buffered.is_empty() {
+            return Ok(());
+        }
+        self.commands.retain(|c| !seen.contains(&c.command));
+        buffered.append(&mut self.commands);
+        self.commands = buffered;
+        Ok(())
+    }
+
+    /// Show the commands of a session (and its descendants when enabled)
+    fn open_session(&mut self, sid: String) -> Result<()> {
+        self.session_commands = if self.include_descendants {
+            self.history.get_commands_for_session_tree(&sid)?
+        } else {
+            self.history.get_commands_for_session(&sid)?
+        };
+        self.row_count = self.session_commands.len();
+        self.session_detail_id = Some(sid);
+        self.filter.clear();
+        self.table_state = TableState::default();
+        if self.row_count > 0 {
+            self.table_state.select(Some(0));
+        }
+        Ok(())
+    }
+
     fn jump_to_session_current(&mut self) -> Result<()> {
         self.jump_to_tab(Tab::Sessions.index())?;
         // Auto-drill into the current session if ZAM_SESSION_ID is set
-        if let Ok(sid) = std::env::var("ZAM_SESSION_ID") {
-            self.session_commands = self.db.get_commands_for_session(&sid)?;
-            self.row_count = self.session_commands.len();
-            self.session_detail_id = Some(sid);
-            self.filter.clear();
-            self.table_state = TableState::default();
-            if self.row_count > 0 {
-                self.table_state.select(Some(0));
-            }
+        if let Some(sid) = self.session_id.clone() {
+            self.open_session(sid)?;
         }
         Ok(())
     }
@@ -719,7 +1215,7 @@ impl<'a> AppTUI<'a> {
             self.page = 0;
             self.session_detail_id = None;
             self.session_commands.clear();
-            let _ = self.db.set_preference("last_tab", &idx.to_string());
+            let _ = self.history.set_preference("last_tab", &idx.to_string());
             self.load_tab()?;
         }
         Ok(())
@@ -732,7 +1228,7 @@ impl<'a> AppTUI<'a> {
         if !entry.redacted {
             return Some(entry.command.clone());
         }
-        let tokens = self.db.get_tokens_for_command(entry.id).ok()?;
+        let tokens = self.history.get_tokens_for_command(entry.id).ok()?;
         if tokens.is_empty() {
             return None;
         }
@@ -743,48 +1239,252 @@ impl<'a> AppTUI<'a> {
         Some(cmd)
     }
 
+    /// The history entry on the currently selected row, if the tab lists them.
+    fn selected_entry(&self) -> Option<&CommandEntry> {
+        let idx = self.resolve_selected()?;
+        match self.tab {
+            Tab::Commands => self.commands.get(idx),
+            Tab::Local => self.local_commands.get(idx),
+            Tab::Sessions if self.session_detail_id.is_some() => self.session_commands.get(idx),
+            _ => None,
+        }
+    }
+
     /// Get the command string for the currently selected row, if applicable.
     fn selected_command_text(&self) -> Option<String> {
-        let idx = self.resolve_selected()?;
+        if let Some(command) = self.selected_plain_command() {
+            return Some(command);
+        }
+        self.selected_entry().and_then(|c| self.unredact_command(c))
+    }
+
+    /// Command on the selected row of the tabs that list bare commands
+    fn selected_plain_command(&self) -> Option<String> {
         match self.tab {
-            Tab::Commands => self
-                .commands
-                .get(idx)
-                .and_then(|c| self.unredact_command(c)),
-            Tab::Local => self
-                .local_commands
-                .get(idx)
-                .and_then(|c| self.unredact_command(c)),
-            Tab::Frequent => self.frequent.get(idx).map(|f| f.command.clone()),
-            Tab::Sessions if self.session_detail_id.is_some() => self
-                .session_commands
-                .get(idx)
-                .and_then(|c| self.unredact_command(c)),
+            Tab::Frequent => {
+                let idx = self.resolve_selected()?;
+                self.frequent.get(idx).map(|f| f.command.clone())
+            }
+            Tab::Aliases if self.show_suggestions => {
+                let idx = self.resolve_selected()?;
+                self.suggestions.get(idx).map(|s| s.command.clone())
+            }
             _ => None,
         }
     }
 
-    /// Copy the currently selected command to the system clipboard via pbcopy.
+    /// The selected command ready to hand back to the shell. Sets a status
+    /// message when a redacted command cannot be restored.
+    fn resolve_selected_command(&mut self) -> Option<String> {
+        let resolved = self.selected_command_text();
+        if resolved.is_none() && self.selected_entry().is_some() {
+            self.status = Some("Cannot execute: redacted command has no stored tokens".into());
+        }
+        resolved
+    }
+
+    /// Command text as listed on the selected row
+    fn selected_listed_command(&self) -> Option<String> {
+        self.selected_plain_command()
+            .or_else(|| self.selected_entry().map(|c| c.command.clone()))
+    }
+
+    fn is_marked(&self, listed: &str) -> bool {
+        self.marked.iter().any(|(l, _)| l == listed)
+    }
+
+    /// Mark or unmark the selected command and move to the next row
+    fn toggle_mark(&mut self) {
+        let Some(listed) = self.selected_listed_command() else {
+            return;
+        };
+        if let Some(pos) = self.marked.iter().position(|(l, _)| *l == listed) {
+            self.marked.remove(pos);
+        } else if let Some(resolved) = self.resolve_selected_command() {
+            self.marked.push((listed, resolved));
+        } else {
+            return;
+        }
+        self.select_next();
+    }
+
+    /// The marked commands joined, or the selected command
+    fn chosen_command(&mut self) -> Option<String> {
+        if self.marked.is_empty() {
+            return self.resolve_selected_command();
+        }
+        let commands: Vec<&str> = self.marked.iter().map(|(_, c)| c.as_str()).collect();
+        Some(commands.join(self.join.separator()))
+    }
+
+    /// Return the chosen command to the shell
+    fn choose(&mut self, action: TuiAction) {
+        if let Some(command) = self.chosen_command() {
+            self.finish(command, action);
+        }
+    }
+
+    fn finish(&mut self, command: String, action: TuiAction) {
+        self.selected_command = Some(TuiSelection { command, action });
+        self.running = false;
+    }
+
+    /// Open the inline editor on the chosen command
+    fn start_edit_command(&mut self) {
+        let Some(command) = self.chosen_command() else {
+            return;
+        };
+        self.edit_cursor = command.chars().count();
+        self.edit_buf = command;
+        self.mode = Mode::EditCommand;
+    }
+
+    /// Byte offset of the editor cursor
+    fn edit_cursor_byte(&self) -> usize {
+        self.edit_buf
+            .char_indices()
+            .nth(self.edit_cursor)
+            .map_or(self.edit_buf.len(), |(i, _)| i)
+    }
+
+    fn handle_edit_command_key(&mut self, key: KeyEvent) {
+        let len = self.edit_buf.chars().count();
+        match key.code {
+            KeyCode::Esc => self.mode = Mode::Filter,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.mode = Mode::Filter;
+            }
+            KeyCode::Enter if !self.edit_buf.trim().is_empty() => {
+                let action = if key.modifiers.contains(KeyModifiers::ALT) {
+                    TuiAction::Insert
+                } else {
+                    TuiAction::Run
+                };
+                self.finish(self.edit_buf.clone(), action);
+            }
+            KeyCode::Left => self.edit_cursor = self.edit_cursor.saturating_sub(1),
+            KeyCode::Right => self.edit_cursor = (self.edit_cursor + 1).min(len),
+            KeyCode::Home => self.edit_cursor = 0,
+            KeyCode::End => self.edit_cursor = len,
+            KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.edit_cursor = 0;
+            }
+            KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.edit_cursor = len;
+            }
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let at = self.edit_cursor_byte();
+                self.edit_buf.replace_range(..at, "");
+                self.edit_cursor = 0;
+            }
+            KeyCode::Backspace if self.edit_cursor > 0 => {
+                self.edit_cursor -= 1;
+                let at = self.edit_cursor_byte();
+                self.edit_buf.remove(at);
+            }
+            KeyCode::Delete if self.edit_cursor < len => {
+                let at = self.edit_cursor_byte();
+                self.edit_buf.remove(at);
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                let at = self.edit_cursor_byte();
+                self.edit_buf.insert(at, c);
+                self.edit_cursor += 1;
+            }
+            _ => {}
+        }
+    }
+
+    /// Load the preview of the selected command unless it is already
+    /// loaded. Called after each key rather than while drawing, since it
+    /// queries the history.
+    fn refresh_preview(&mut self) {
+        if !self.preview_visible() {
+            return;
+        }
+        let Some(entry) = self.selected_entry().cloned() else {
+            self.preview = None;
+            return;
+        };
+        if self
+            .preview
+            .as_ref()
+            .is_some_and(|p| p.entry.id == entry.id && p.entry.command == entry.command)
+        {
+            return;
+        }
+        self.preview = Some(self.load_preview(entry));
+    }
+
+    fn load_preview(&self, entry: CommandEntry) -> CommandPreview {
+        // Ephemeral commands are not in the database
+        if entry.id.as_i64() == 0 {
+            return CommandPreview {
+                entry,
+                host: "-".into(),
+                session: "private (not stored)".into(),
+                runs: 0,
+                before: Vec::new(),
+                after: Vec::new(),
+            };
+        }
+        let session = self
+            .history
+            .get_session(entry.session_id.as_str())
+            .ok()
+            .flatten();
+        // Session commands come newest first
+        let mut neighbours = self
+            .history
+            .get_commands_for_session(entry.session_id.as_str())
+            .unwrap_or_default();
+        neighbours.reverse();
+        let pos = neighbours.iter().position(|c| c.id == entry.id);
+        let (before, after) = match pos {
+            Some(pos) => (
+                neighbours[pos.saturating_sub(PREVIEW_CONTEXT)..pos].to_vec(),
+                neighbours
+                    .iter()
+                    .skip(pos + 1)
+                    .take(PREVIEW_CONTEXT)
+                    .cloned()
+                    .collect(),
+            ),
+            None => (Vec::new(), Vec::new()),
+        };
+        CommandPreview {
+            host: session
+                .as_ref()
+                .map_or_else(|| "?".to_string(), |s| s.hostname.clone()),
+            session: session.as_ref().map_or_else(
+                || entry.session_id.as_str().to_string(),
+                |s| s.label().to_string(),
+            ),
+            runs: self.history.count_command_runs(&entry.command).unwrap_or(1),
+            before,
+            after,
+            entry,
+        }
+    }
+
+    /// Whether the preview pane is shown on the current tab
+    fn preview_visible(&self) -> bool {
+        self.show_preview
+            && (matches!(self.tab, Tab::Commands | Tab::Local)
+                || (self.tab == Tab::Sessions && self.session_detail_id.is_some()))
+    }
+
+    /// Copy the currently selected command to the system clipboard.
     fn yank_to_clipboard(&mut self) {
         let Some(cmd) = self.selected_command_text() else {
             return;
         };
-        use std::io::Write;
-        let result = std::process::Command::new("pbcopy")
-            .stdin(std::process::Stdio::piped())
-            .spawn()
-            .and_then(|mut child| {
-                if let Some(ref mut stdin) = child.stdin {
-                    stdin.write_all(cmd.as_bytes())?;
-                }
-                child.wait()
-            });
-        match result {
-            Ok(status) if status.success() => {
+        match self.clipboard.copy(&cmd) {
+            Ok(_) => {
                 self.copied_at = Some(std::time::Instant::now());
             }
-            _ => {
-                self.status = Some("Failed to copy to clipboard".into());
+            Err(e) => {
+                self.status = Some(e.to_string());
             }
         }
     }
@@ -812,68 +1512,151 @@ impl<'a> AppTUI<'a> {
         Some(indices)
     }
 
-    fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
-        // Global modifier shortcuts work in any mode except Confirm and EditAlias
-        if self.mode != Mode::Confirm && self.mode != Mode::EditAlias {
-            // Alt+1..8 jump to tab by number
-            if key.modifiers.contains(KeyModifiers::ALT)
-                && let KeyCode::Char(c @ '1'..='8') = key.code
+    fn handle_filter_key(&mut self, key: KeyEvent) -> Result<()> {
+        let pressed = Key::from_event(&key);
+        // Bound plain characters are typed once the filter has text
+        let action = self
+            .keymap
+            .lookup(pressed, self.normal_mode)
+            .filter(|_| self.normal_mode || !pressed.is_printable() || self.filter.is_empty());
+        if let Some(action) = action {
+            return self.perform(action);
+        }
+        if self.normal_mode {
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.page = 0;
+                self.load_tab()?;
+            }
+            KeyCode::Char(c)
+                if !key
+                    .modifiers
+                    .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
             {
-                self.jump_to_tab((c as usize) - ('1' as usize))?;
-                return Ok(());
+                self.filter.push(c);
+                self.page = 0;
+                self.load_tab()?;
             }
+            _ => {}
+        }
+        Ok(())
+    }
 
-            // Ctrl shortcuts
-            if key.modifiers.contains(KeyModifiers::CONTROL) {
-                match key.code {
-                    KeyCode::Char('c') => {
-                        self.running = false;
-                        return Ok(());
-                    }
-                    KeyCode::Char('d') => {
-                        self.request_delete();
-                        return Ok(());
-                    }
-                    KeyCode::Char('e') if self.tab == Tab::Aliases => {
-                        self.start_edit_alias();
-                        return Ok(());
-                    }
-                    KeyCode::Char('t') => {
-                        self.relative_time = !self.relative_time;
-                        let _ = self.db.set_preference(
-                            "relative_time",
-                            if self.relative_time { "true" } else { "false" },
-                        );
-                        return Ok(());
-                    }
-                    KeyCode::Char('v') if self.tab == Tab::Tokens => {
-                        self.show_values = !self.show_values;
-                        return Ok(());
-                    }
-                    KeyCode::Char('l') => {
-                        self.jump_to_tab(Tab::Local.index())?;
-                        return Ok(());
-                    }
-                    KeyCode::Char('s') => {
-                        self.jump_to_session_current()?;
-                        return Ok(());
-                    }
-                    KeyCode::Char('h') => {
-                        self.jump_to_tab(Tab::Commands.index())?;
-                        return Ok(());
-                    }
-                    KeyCode::Char('y') => {
-                        self.yank_to_clipboard();
-                        return Ok(());
-                    }
-                    _ => {}
+    fn perform(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Run | Action::Insert
+                if self.tab == Tab::Sessions && self.session_detail_id.is_none() =>
+            {
+                if let Some(idx) = self.resolve_selected()
+                    && let Some(s) = self.sessions.get(idx)
+                {
+                    let sid = s.id.as_ref().to_string();
+                    self.open_session(sid)?;
                 }
             }
+            Action::Run => self.choose(TuiAction::Run),
+            Action::Insert => self.choose(TuiAction::Insert),
+            Action::Edit if self.tab == Tab::Aliases && self.show_suggestions => {
+                self.start_new_alias()?
+            }
+            Action::Edit if self.tab == Tab::Aliases => self.start_edit_alias(),
+            Action::Edit => self.start_edit_command(),
+            Action::Mark => self.toggle_mark(),
+            Action::Back => {
+                if self.session_detail_id.is_some() {
+                    self.session_detail_id = None;
+                    self.session_commands.clear();
+                    self.filter.clear();
+                    self.page = 0;
+                    self.load_tab()?;
+                } else if self.tab == Tab::Help {
+                    self.jump_to_tab(Tab::Commands.index())?;
+                } else if self.filter.is_empty() {
+                    self.running = false;
+                } else {
+                    self.filter.clear();
+                    self.page = 0;
+                    self.load_tab()?;
+                }
+            }
+            Action::Quit => self.running = false,
+            Action::NormalMode => self.normal_mode = true,
+            Action::InsertMode => self.normal_mode = false,
+            Action::Up => self.select_prev(),
+            Action::Down => self.select_next(),
+            Action::First => self.select_first(),
+            Action::Last => self.select_last(),
+            Action::PrevPage if self.is_paginated_tab() => self.prev_page()?,
+            Action::NextPage if self.is_paginated_tab() => self.next_page()?,
+            Action::PrevPage | Action::PrevTab => self.prev_tab()?,
+            Action::NextPage | Action::NextTab => self.next_tab()?,
+            Action::JumpTab(idx) => self.jump_to_tab(idx)?,
+            Action::History => self.jump_to_tab(Tab::Commands.index())?,
+            Action::Local => self.jump_to_tab(Tab::Local.index())?,
+            Action::Session => self.jump_to_session_current()?,
+            Action::Help => self.jump_to_tab(Tab::Help.index())?,
+            Action::RelativeTime => {
+                self.relative_time = !self.relative_time;
+                let _ = self.history.set_preference(
+                    "relative_time",
+                    if self.relative_time { "true" } else { "false" },
+                );
+            }
+            Action::Delete => self.request_delete(),
+            Action::Actors if self.tab == Tab::Commands => {
+                self.actor_filter = self.actor_filter.cycle();
+                self.page = 0;
+                self.load_tab()?;
+            }
+            Action::Descendants if self.session_detail_id.is_some() => {
+                self.include_descendants = !self.include_descendants;
+                if let Some(sid) = self.session_detail_id.clone() {
+                    self.open_session(sid)?;
+                }
+                self.status = Some(if self.include_descendants {
+                    "Including child sessions".into()
+                } else {
+                    "Child sessions hidden".into()
+                });
+            }
+            Action::Reveal if self.tab == Tab::Tokens => {
+                self.show_values = !self.show_values;
+            }
+            Action::AllowToken if self.tab == Tab::Tokens => self.request_allow_token(),
+            Action::Yank => self.yank_to_clipboard(),
+            Action::Preview => {
+                self.show_preview = !self.show_preview;
+                let _ = self
+                    .history
+                    .set_preference("preview", if self.show_preview { "true" } else { "false" });
+            }
+            Action::NewAlias => self.start_new_alias()?,
+            Action::Suggestions => {
+                self.show_suggestions = !self.show_suggestions || self.tab != Tab::Aliases;
+                if self.tab == Tab::Aliases {
+                    self.filter.clear();
+                    self.load_tab()?;
+                } else {
+                    self.jump_to_tab(Tab::Aliases.index())?;
+                }
+            }
+            Action::Actors | Action::Descendants | Action::Reveal | Action::AllowToken => {}
         }
+        Ok(())
+    }
 
+    fn handle_key(&mut self, key: KeyEvent) -> Result<()> {
+        // Status messages last until the next key
+        self.status = None;
         match self.mode {
+            Mode::Filter => self.handle_filter_key(key)?,
+            Mode::NewAlias => self.handle_new_alias_key(key)?,
+            Mode::EditCommand => self.handle_edit_command_key(key),
             Mode::Confirm => match key.code {
-                KeyCode::Char('y') | KeyCode::Char('Y') => self.confirm_delete()?,
+                KeyCode::Char('y') | KeyCode::Char('Y') => self.confirm()?,
                 _ => self.mode = Mode::Filter,
             },
             Mode::EditAlias => match key.code {
@@ -912,115 +1695,8 @@ impl<'a> AppTUI<'a> {
                 }
                 _ => {}
             },
-            Mode::Filter => match key.code {
-                KeyCode::Esc => {
-                    if self.session_detail_id.is_some() {
-                        self.session_detail_id = None;
-                        self.session_commands.clear();
-                        self.filter.clear();
-                        self.page = 0;
-                        self.load_tab()?;
-                    } else if self.tab == Tab::Help {
-                        self.jump_to_tab(Tab::Commands.index())?;
-                    } else if self.filter.is_empty() {
-                        self.running = false;
-                    } else {
-                        self.filter.clear();
-                        self.page = 0;
-                        self.load_tab()?;
-                    }
-                }
-                KeyCode::Enter if self.tab == Tab::Frequent => {
-                    if let Some(idx) = self.resolve_selected()
-                        && let Some(f) = self.frequent.get(idx)
-                    {
-                        self.selected_command = Some(f.command.clone());
-                        self.running = false;
-                    }
-                }
-                KeyCode::Enter if self.tab == Tab::Local => {
-                    if let Some(idx) = self.resolve_selected()
-                        && let Some(cmd) = self.local_commands.get(idx)
-                    {
-                        if let Some(resolved) = self.unredact_command(cmd) {
-                            self.selected_command = Some(resolved);
-                            self.running = false;
-                        } else {
-                            self.status = Some(
-                                "Cannot execute: redacted command has no stored tokens".into(),
-                            );
-                        }
-                    }
-                }
-                KeyCode::Enter if self.tab == Tab::Sessions && self.session_detail_id.is_some() => {
-                    if let Some(idx) = self.resolve_selected()
-                        && let Some(cmd) = self.session_commands.get(idx)
-                    {
-                        if let Some(resolved) = self.unredact_command(cmd) {
-                            self.selected_command = Some(resolved);
-                            self.running = false;
-                        } else {
-                            self.status = Some(
-                                "Cannot execute: redacted command has no stored tokens".into(),
-                            );
-                        }
-                    }
-                }
-                KeyCode::Enter if self.tab == Tab::Sessions && self.session_detail_id.is_none() => {
-                    if let Some(idx) = self.resolve_selected()
-                        && let Some(s) = self.sessions.get(idx)
-                    {
-                        let sid = s.id.as_ref().to_string();
-                        self.session_commands = self.db.get_commands_for_session(&sid)?;
-                        self.row_count = self.session_commands.len();
-                        self.session_detail_id = Some(sid);
-                        self.filter.clear();
-                        self.table_state = TableState::default();
-                        if self.row_count > 0 {
-                            self.table_state.select(Some(0));
-                        }
-                    }
-                }
-                KeyCode::Enter if self.tab == Tab::Commands => {
-                    if let Some(idx) = self.resolve_selected()
-                        && let Some(cmd) = self.commands.get(idx)
-                    {
-                        if let Some(resolved) = self.unredact_command(cmd) {
-                            self.selected_command = Some(resolved);
-                            self.running = false;
-                        } else {
-                            self.status = Some(
-                                "Cannot execute: redacted command has no stored tokens".into(),
-                            );
-                        }
-                    }
-                }
-                KeyCode::Enter => {}
-                KeyCode::Backspace => {
-                    self.filter.pop();
-                    self.page = 0;
-                    self.load_tab()?;
-                }
-                KeyCode::Up => self.select_prev(),
-                KeyCode::Down => self.select_next(),
-                KeyCode::Left if self.is_paginated_tab() => self.prev_page()?,
-                KeyCode::Right if self.is_paginated_tab() => self.next_page()?,
-                KeyCode::Left => self.prev_tab()?,
-                KeyCode::Right => self.next_tab()?,
-                KeyCode::Tab => self.next_tab()?,
-                KeyCode::BackTab => self.prev_tab()?,
-                KeyCode::Delete => self.request_delete(),
-                KeyCode::Char('?') if self.filter.is_empty() => {
-                    self.jump_to_tab(Tab::Help.index())?;
-                }
-                KeyCode::Char(c) => {
-                    self.filter.push(c);
-                    self.page = 0;
-                    self.load_tab()?;
-                }
-                _ => {}
-            },
         }
+        self.refresh_preview();
         Ok(())
     }
 
@@ -1039,13 +1715,29 @@ impl<'a> AppTUI<'a> {
             ])
             .split(outer[1]);
 
-        self.render_table(frame, chunks[0]);
+        if self.preview_visible() {
+            let direction = if chunks[0].width >= PREVIEW_SIDE_MIN_WIDTH {
+                Direction::Horizontal
+            } else {
+                Direction::Vertical
+            };
+            let panes = Layout::default()
+                .direction(direction)
+                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .split(chunks[0]);
+            self.render_table(frame, panes[0]);
+            self.render_preview(frame, panes[1], direction);
+        } else {
+            self.render_table(frame, chunks[0]);
+        }
         self.render_tabs(frame, chunks[1]);
         self.render_status(frame, chunks[2]);
 
         match self.mode {
             Mode::Confirm => self.render_confirm(frame, frame.area()),
             Mode::EditAlias => self.render_edit_alias(frame, frame.area()),
+            Mode::EditCommand => self.render_edit_command(frame, frame.area()),
+            Mode::NewAlias => self.render_new_alias(frame, frame.area()),
             _ => {}
         }
     }
@@ -1113,10 +1805,11 @@ impl<'a> AppTUI<'a> {
             }
             Tab::Sessions => self.render_sessions(frame, area),
             Tab::Tokens => self.render_tokens(frame, area),
+            Tab::Stats => self.render_stats(frame, area),
             Tab::Help => self.render_help_tab(frame, area),
         }
         // Empty state overlay
-        if self.tab != Tab::Help && self.filtered_row_count() == 0 {
+        if !matches!(self.tab, Tab::Stats | Tab::Help) && self.filtered_row_count() == 0 {
             let msg = if self.filter.is_empty() {
                 "no entries"
             } else {
@@ -1146,6 +1839,29 @@ impl<'a> AppTUI<'a> {
         }
     }
 
+    /// Entries of a command table row whose column is shown. `None` marks
+    /// columns that cannot be hidden.
+    fn visible<T>(&self, entries: Vec<(Option<&str>, T)>) -> Vec<T> {
+        entries
+            .into_iter()
+            .filter(|(column, _)| {
+                column.is_none_or(|c| !self.hidden_columns.iter().any(|h| h == c))
+            })
+            .map(|(_, entry)| entry)
+            .collect()
+    }
+
+    /// Row style for a command marked for multi-select
+    fn mark_style(&self, listed: &str) -> Style {
+        if self.is_marked(listed) {
+            Style::default()
+                .fg(self.theme.success)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        }
+    }
+
     fn matches_filter(&self, text: &str) -> bool {
         if self.filter.is_empty() {
             return true;
@@ -1199,6 +1915,7 @@ impl<'a> AppTUI<'a> {
                     Cell::from(f.count.to_string()),
                     Cell::from(f.command.as_str()),
                 ])
+                .style(self.mark_style(&f.command))
             })
             .collect();
 
@@ -1212,7 +1929,13 @@ impl<'a> AppTUI<'a> {
     fn render_commands(&mut self, frame: &mut Frame, area: Rect) {
         let filter_ref = self.filter.clone();
         let dir_width = dir_col_width(area.width);
-        let header = Row::new(vec!["", "timestamp", "command", "directory"]).style(
+        let header = Row::new(self.visible(vec![
+            (Some("exit"), ""),
+            (Some("time"), "timestamp"),
+            (None, "command"),
+            (Some("directory"), "directory"),
+        ]))
+        .style(
             Style::default()
                 .fg(self.theme.header)
                 .add_modifier(Modifier::BOLD),
@@ -1231,26 +1954,30 @@ impl<'a> AppTUI<'a> {
                 } else {
                     Cell::from(c.command.as_str())
                 };
-                Row::new(vec![
-                    exit_code_cell(c.exit_code, &self.theme),
-                    Cell::from(self.fmt_time(c.timestamp)),
-                    cmd_cell,
-                    Cell::from(truncate_left(
-                        &shorten_dir(&c.directory, &self.home),
-                        dir_width as usize,
-                    )),
-                ])
+                Row::new(self.visible(vec![
+                    (Some("exit"), exit_code_cell(c.exit_code, &self.theme)),
+                    (Some("time"), Cell::from(self.fmt_time(c.timestamp))),
+                    (None, cmd_cell),
+                    (
+                        Some("directory"),
+                        Cell::from(truncate_left(
+                            &shorten_dir(&c.directory, &self.home),
+                            dir_width as usize,
+                        )),
+                    ),
+                ]))
+                .style(self.mark_style(&c.command))
             })
             .collect();
 
         let table = Table::new(
             rows,
-            [
-                Constraint::Length(2),
-                Constraint::Length(16),
-                Constraint::Min(20),
-                Constraint::Length(dir_width),
-            ],
+            self.visible(vec![
+                (Some("exit"), Constraint::Length(2)),
+                (Some("time"), Constraint::Length(16)),
+                (None, Constraint::Min(20)),
+                (Some("directory"), Constraint::Length(dir_width)),
+            ]),
         )
         .header(header)
         .row_highlight_style(self.row_highlight_style());
@@ -1260,7 +1987,12 @@ impl<'a> AppTUI<'a> {
 
     fn render_local(&mut self, frame: &mut Frame, area: Rect) {
         let filter_ref = self.filter.clone();
-        let header = Row::new(vec!["", "timestamp", "command"]).style(
+        let header = Row::new(self.visible(vec![
+            (Some("exit"), ""),
+            (Some("time"), "timestamp"),
+            (None, "command"),
+        ]))
+        .style(
             Style::default()
                 .fg(self.theme.header)
                 .add_modifier(Modifier::BOLD),
@@ -1280,21 +2012,22 @@ impl<'a> AppTUI<'a> {
                 } else {
                     Cell::from(c.command.as_str())
                 };
-                Row::new(vec![
-                    exit_code_cell(c.exit_code, &self.theme),
-                    Cell::from(self.fmt_time(c.timestamp)),
-                    cmd_cell,
-                ])
+                Row::new(self.visible(vec![
+                    (Some("exit"), exit_code_cell(c.exit_code, &self.theme)),
+                    (Some("time"), Cell::from(self.fmt_time(c.timestamp))),
+                    (None, cmd_cell),
+                ]))
+                .style(self.mark_style(&c.command))
             })
             .collect();
 
         let table = Table::new(
             rows,
-            [
-                Constraint::Length(2),
-                Constraint::Length(16),
-                Constraint::Min(20),
-            ],
+            self.visible(vec![
+                (Some("exit"), Constraint::Length(2)),
+                (Some("time"), Constraint::Length(16)),
+                (None, Constraint::Min(20)),
+            ]),
         )
         .header(header)
         .row_highlight_style(self.row_highlight_style());
@@ -1303,6 +2036,9 @@ impl<'a> AppTUI<'a> {
     }
 
     fn render_aliases(&mut self, frame: &mut Frame, area: Rect) {
+        if self.show_suggestions {
+            return self.render_suggestions(frame, area);
+        }
         let header = Row::new(vec!["alias", "command", "description", "updated"]).style(
             Style::default()
                 .fg(self.theme.header)
@@ -1338,6 +2074,44 @@ impl<'a> AppTUI<'a> {
         frame.render_stateful_widget(table, area, &mut self.table_state);
     }
 
+    /// Proposed aliases, the ones saving the most typing first
+    fn render_suggestions(&mut self, frame: &mut Frame, area: Rect) {
+        let header = Row::new(vec!["alias", "runs", "saves", "command"]).style(
+            Style::default()
+                .fg(self.theme.header)
+                .add_modifier(Modifier::BOLD),
+        );
+
+        let rows: Vec<Row> = self
+            .suggestions
+            .iter()
+            .filter(|s| self.matches_filter(&s.command))
+            .map(|s| {
+                Row::new(vec![
+                    Cell::from(s.name.as_str()),
+                    Cell::from(format_thousands(s.runs)),
+                    Cell::from(format_thousands(s.saved)),
+                    Cell::from(s.command.as_str()),
+                ])
+                .style(self.mark_style(&s.command))
+            })
+            .collect();
+
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(15),
+                Constraint::Length(7),
+                Constraint::Length(9),
+                Constraint::Min(20),
+            ],
+        )
+        .header(header)
+        .row_highlight_style(self.row_highlight_style());
+
+        frame.render_stateful_widget(table, area, &mut self.table_state);
+    }
+
     fn render_hosts(&mut self, frame: &mut Frame, area: Rect) {
         let header = Row::new(vec!["id", "hostname", "created"]).style(
             Style::default()
@@ -1373,7 +2147,7 @@ impl<'a> AppTUI<'a> {
     }
 
     fn render_sessions(&mut self, frame: &mut Frame, area: Rect) {
-        let header = Row::new(vec!["session", "host", "started", "status"]).style(
+        let header = Row::new(vec!["session", "tags", "host", "started", "status"]).style(
             Style::default()
                 .fg(self.theme.header)
                 .add_modifier(Modifier::BOLD),
@@ -1389,9 +2163,16 @@ impl<'a> AppTUI<'a> {
                     .map(|e| self.fmt_time(e))
                     .unwrap_or_else(|| "active".into());
                 let cmd_count = self.session_cmd_counts.get(i).copied().unwrap_or(0);
-                let id_display = format!("{} ({} cmds)", s.id, cmd_count);
+                let depth = self.session_depths.get(i).copied().unwrap_or(0);
+                let branch = if depth == 0 {
+                    String::new()
+                } else {
+                    format!("{}└ ", "  ".repeat(depth - 1))
+                };
+                let id_display = format!("{}{} ({} cmds)", branch, s.label(), cmd_count);
                 Row::new(vec![
                     Cell::from(id_display),
+                    Cell::from(s.tags.join(",")),
                     Cell::from(s.hostname.as_str()),
                     Cell::from(self.fmt_time(s.started_at)),
                     Cell::from(status),
@@ -1403,6 +2184,7 @@ impl<'a> AppTUI<'a> {
             rows,
             [
                 Constraint::Min(30),
+                Constraint::Length(20),
                 Constraint::Length(16),
                 Constraint::Length(16),
                 Constraint::Length(16),
@@ -1416,7 +2198,15 @@ impl<'a> AppTUI<'a> {
 
     fn render_session_commands(&mut self, frame: &mut Frame, area: Rect) {
         let dir_width = dir_col_width(area.width);
-        let header = Row::new(vec!["", "id", "timestamp", "command", "directory", "r"]).style(
+        let header = Row::new(self.visible(vec![
+            (Some("exit"), ""),
+            (Some("id"), "id"),
+            (Some("time"), "timestamp"),
+            (None, "command"),
+            (Some("directory"), "directory"),
+            (Some("redacted"), "r"),
+        ]))
+        .style(
             Style::default()
                 .fg(self.theme.header)
                 .add_modifier(Modifier::BOLD),
@@ -1428,30 +2218,34 @@ impl<'a> AppTUI<'a> {
             .filter(|c| self.matches_filter(&c.command))
             .map(|c| {
                 let r = if c.redacted { "Y" } else { "" };
-                Row::new(vec![
-                    exit_code_cell(c.exit_code, &self.theme),
-                    Cell::from(c.id.to_string()),
-                    Cell::from(self.fmt_time(c.timestamp)),
-                    Cell::from(c.command.as_str()),
-                    Cell::from(truncate_left(
-                        &shorten_dir(&c.directory, &self.home),
-                        dir_width as usize,
-                    )),
-                    Cell::from(r),
-                ])
+                Row::new(self.visible(vec![
+                    (Some("exit"), exit_code_cell(c.exit_code, &self.theme)),
+                    (Some("id"), Cell::from(c.id.to_string())),
+                    (Some("time"), Cell::from(self.fmt_time(c.timestamp))),
+                    (None, Cell::from(c.command.as_str())),
+                    (
+                        Some("directory"),
+                        Cell::from(truncate_left(
+                            &shorten_dir(&c.directory, &self.home),
+                            dir_width as usize,
+                        )),
+                    ),
+                    (Some("redacted"), Cell::from(r)),
+                ]))
+                .style(self.mark_style(&c.command))
             })
             .collect();
 
         let table = Table::new(
             rows,
-            [
-                Constraint::Length(2),
-                Constraint::Length(6),
-                Constraint::Length(16),
-                Constraint::Min(20),
-                Constraint::Length(dir_width),
-                Constraint::Length(1),
-            ],
+            self.visible(vec![
+                (Some("exit"), Constraint::Length(2)),
+                (Some("id"), Constraint::Length(6)),
+                (Some("time"), Constraint::Length(16)),
+                (None, Constraint::Min(20)),
+                (Some("directory"), Constraint::Length(dir_width)),
+                (Some("redacted"), Constraint::Length(1)),
+            ]),
         )
         .header(header)
         .row_highlight_style(self.row_highlight_style());
@@ -1504,14 +2298,99 @@ impl<'a> AppTUI<'a> {
         frame.render_stateful_widget(table, area, &mut self.table_state);
     }
 
+    fn render_preview(&self, frame: &mut Frame, area: Rect, direction: Direction) {
+        let (borders, padding) = if direction == Direction::Horizontal {
+            (Borders::LEFT, Padding::left(1))
+        } else {
+            (Borders::TOP, Padding::ZERO)
+        };
+        let block = Block::default()
+            .borders(borders)
+            .padding(padding)
+            .border_style(Style::default().fg(self.theme.status_default))
+            .title(Span::styled(
+                " preview ",
+                Style::default().fg(self.theme.header),
+            ));
+        let Some(ref preview) = self.preview else {
+            frame.render_widget(block, area);
+            return;
+        };
+
+        let label = Style::default().fg(self.theme.header);
+        let dim = Style::default().fg(self.theme.status_default);
+        let field = |name: &'static str, value: String| {
+            Line::from(vec![
+                Span::styled(format!("{:<9}", name), label),
+                Span::raw(value),
+            ])
+        };
+        let entry = &preview.entry;
+
+        let mut lines: Vec<Line> = entry
+            .command
+            .lines()
+            .map(|l| {
+                Line::from(Span::styled(
+                    l.to_string(),
+                    Style::default()
+                        .fg(self.theme.tab_text)
+                        .add_modifier(Modifier::BOLD),
+                ))
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(field("dir", shorten_dir(&entry.directory, &self.home)));
+        lines.push(field("host", preview.host.clone()));
+        lines.push(field("session", preview.session.clone()));
+        lines.push(field(
+            "exit",
+            entry
+                .exit_code
+                .map_or_else(|| "-".to_string(), |c| c.to_string()),
+        ));
+        lines.push(field(
+            "time",
+            entry.timestamp.format("%Y-%m-%d %H:%M:%S").to_string(),
+        ));
+        lines.push(field("runs", format_thousands(preview.runs)));
+
+        if !preview.before.is_empty() || !preview.after.is_empty() {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("in session", label)));
+            let context_line = |c: &CommandEntry| {
+                Line::from(Span::styled(
+                    format!("  {}", c.command.lines().next().unwrap_or_default()),
+                    dim,
+                ))
+            };
+            lines.extend(preview.before.iter().map(context_line));
+            lines.push(Line::from(Span::styled(
+                format!("› {}", entry.command.lines().next().unwrap_or_default()),
+                Style::default().fg(self.theme.status_active),
+            )));
+            lines.extend(preview.after.iter().map(context_line));
+        }
+
+        let p = Paragraph::new(lines)
+            .block(block)
+            .wrap(Wrap { trim: false });
+        frame.render_widget(p, area);
+    }
+
     fn render_status(&self, frame: &mut Frame, area: Rect) {
         let (left, right, active) = match self.mode {
             Mode::Filter => {
-                let left = if self.filter.is_empty() {
+                let mut left = if let Some(status) = &self.status {
+                    format!(" {}", status)
+                } else if self.filter.is_empty() {
                     String::new()
                 } else {
                     format!(" / {}_", self.filter)
                 };
+                if self.normal_mode {
+                    left = format!(" NORMAL{}", left);
+                }
 
                 let mut right_parts = Vec::new();
                 let count_info = if self.is_paginated_tab() {
@@ -1531,14 +2410,33 @@ impl<'a> AppTUI<'a> {
                         format_thousands(total)
                     }
                 };
-                right_parts.push(count_info);
+                if self.tab == Tab::Stats {
+                    right_parts.push(format!(
+                        "{} runs, {}",
+                        format_thousands(self.usage.total_runs),
+                        stats_filter(&self.filter).1
+                    ));
+                } else {
+                    right_parts.push(count_info);
+                }
+                if !self.marked.is_empty() {
+                    right_parts.push(format!("{} marked", self.marked.len()));
+                }
+                if self.tab == Tab::Aliases && self.show_suggestions {
+                    right_parts.push("suggestions".to_string());
+                }
+                if self.tab == Tab::Commands && self.actor_filter != ActorFilter::All {
+                    right_parts.push(format!("by {}", self.actor_filter.label()));
+                }
                 if self.is_paginated_tab() && self.total_pages() > 1 {
                     right_parts.push(format!("pg {}/{}", self.page + 1, self.total_pages()));
                 }
                 let right = format!("{} ", right_parts.join("  "));
-                (left, right, !self.filter.is_empty())
+                (left, right, !self.filter.is_empty() || self.normal_mode)
+            }
+            Mode::Confirm | Mode::EditCommand | Mode::NewAlias => {
+                (String::new(), String::new(), false)
             }
-            Mode::Confirm => (String::new(), String::new(), false),
             Mode::EditAlias => {
                 let field = match self.edit_field {
                     EditField::Command => "command",
@@ -1578,11 +2476,15 @@ impl<'a> AppTUI<'a> {
     fn render_confirm(&self, frame: &mut Frame, area: Rect) {
         let block_area = centered_rect(50, 5, area);
         let text = format!("{} (y/n)", self.confirm_msg);
+        let title = match self.confirm_action {
+            ConfirmAction::Delete => "confirm delete",
+            ConfirmAction::AllowToken => "confirm allow",
+        };
         let popup = Paragraph::new(text)
             .block(
                 Block::default()
                     .borders(Borders::ALL)
-                    .title("confirm delete")
+                    .title(title)
                     .style(Style::default().fg(self.theme.popup_confirm)),
             )
             .style(Style::default().fg(self.theme.popup_text))
@@ -1616,34 +2518,282 @@ impl<'a> AppTUI<'a> {
         frame.render_widget(popup, block_area);
     }
 
+    fn render_new_alias(&self, frame: &mut Frame, area: Rect) {
+        let Some(new_alias) = &self.new_alias else {
+            return;
+        };
+        let label = Style::default().fg(self.theme.popup_accent);
+        let field = |name: &'static str, value: &str, which: NewAliasField| {
+            let cursor = if new_alias.field == which { "_" } else { "" };
+            Line::from(vec![
+                Span::styled(format!("{:<13}", name), label),
+                Span::raw(format!("{}{}", value, cursor)),
+            ])
+        };
+        let mut text = vec![
+            field("name", &new_alias.name, NewAliasField::Name),
+            field("command", &new_alias.command, NewAliasField::Command),
+            field(
+                "description",
+                &new_alias.description,
+                NewAliasField::Description,
+            ),
+            Line::from(""),
+        ];
+        let problem = match (&new_alias.error, &new_alias.conflict) {
+            (Some(error), _) => Some(error.clone()),
+            (None, Some(conflict)) if conflict.can_override() => Some(format!(
+                "'{}' {}: Enter{} to save anyway",
+                new_alias.name,
+                conflict,
+                if new_alias.overridden.as_deref() == Some(new_alias.name.as_str()) {
+                    " again"
+                } else {
+                    " twice"
+                }
+            )),
+            (None, Some(conflict)) => Some(format!("'{}' {}", new_alias.name, conflict)),
+            (None, None) => None,
+        };
+        text.push(match problem {
+            Some(problem) => Line::styled(problem, Style::default().fg(self.theme.error)),
+            None => Line::from(""),
+        });
+        text.push(Line::from("Tab=next field  Enter=save  Esc=cancel"));
+
+        let block_area = centered_rect(70, text.len() as u16 + 2, area);
+        let popup = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("new alias")
+                    .style(Style::default().fg(self.theme.popup_accent)),
+            )
+            .style(Style::default().fg(self.theme.popup_text))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(ratatui::widgets::Clear, block_area);
+        frame.render_widget(popup, block_area);
+    }
+
+    fn render_edit_command(&self, frame: &mut Frame, area: Rect) {
+        let lines = self.edit_buf.lines().count().max(1) as u16;
+        let block_area = centered_rect(70, lines + 4, area);
+        let mut text = editor_lines(&self.edit_buf, self.edit_cursor);
+        text.push(Line::from(""));
+        text.push(Line::from("Enter=run  Alt+Enter=insert  Esc=cancel"));
+
+        let popup = Paragraph::new(text)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("edit command")
+                    .style(Style::default().fg(self.theme.popup_accent)),
+            )
+            .style(Style::default().fg(self.theme.popup_text))
+            .wrap(Wrap { trim: false });
+        frame.render_widget(ratatui::widgets::Clear, block_area);
+        frame.render_widget(popup, block_area);
+    }
+
+    /// Charts of the commands matching the filter box (see [`stats_filter`])
+    fn render_stats(&self, frame: &mut Frame, area: Rect) {
+        let usage = &self.usage;
+        let title_style = Style::default()
+            .fg(self.theme.header)
+            .add_modifier(Modifier::BOLD);
+        let chart = |title: String| {
+            Block::default()
+                .borders(Borders::TOP)
+                .title(title)
+                .title_style(title_style)
+                .border_style(Style::default().fg(self.theme.tab_number))
+        };
+        let rows = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Length(9),
+            Constraint::Min(4),
+        ])
+        .split(area);
+
+        // Newest days, as many as fit
+        let days = usage.per_day.len().min(rows[0].width as usize);
+        let per_day: Vec<u64> = usage.per_day[usage.per_day.len() - days..]
+            .iter()
+            .map(|(_, n)| *n as u64)
+            .collect();
+        let busiest = per_day.iter().max().copied().unwrap_or(0);
+        frame.render_widget(
+            Sparkline::default()
+                .block(chart(format!(
+                    "commands per day, last {} days (max {})",
+                    days, busiest
+                )))
+                .data(&per_day)
+                .style(Style::default().fg(self.theme.tab_highlight)),
+            rows[0],
+        );
+
+        let middle =
+            Layout::horizontal([Constraint::Min(20), Constraint::Length(54)]).split(rows[1]);
+        let weeks = usage.per_week.len().min(middle[0].width as usize / 6);
+        let per_week: Vec<Bar> = usage.per_week[usage.per_week.len() - weeks..]
+            .iter()
+            .map(|(monday, n)| Bar::with_label(monday.format("%m-%d").to_string(), *n as u64))
+            .collect();
+        frame.render_widget(
+            BarChart::vertical(per_week)
+                .block(chart("commands per week".into()))
+                .bar_width(5)
+                .bar_gap(1)
+                .bar_style(Style::default().fg(self.theme.tab_highlight))
+                .value_style(
+                    Style::default()
+                        .fg(self.theme.flash_fg)
+                        .bg(self.theme.tab_highlight),
+                ),
+            middle[0],
+        );
+        frame.render_widget(
+            Paragraph::new(heatmap_lines(&usage.by_hour, self.theme.tab_highlight))
+                .block(chart("hour of day".into())),
+            middle[1],
+        );
+
+        let bottom = Layout::horizontal([
+            Constraint::Percentage(35),
+            Constraint::Percentage(40),
+            Constraint::Percentage(25),
+        ])
+        .split(rows[2]);
+        let label_width = (bottom[0].width as usize / 2).max(8);
+        let directories: Vec<Bar> = usage
+            .top_directories
+            .iter()
+            .map(|(dir, n)| {
+                Bar::with_label(
+                    truncate_left(&shorten_dir(dir, &self.home), label_width),
+                    *n as u64,
+                )
+            })
+            .collect();
+        frame.render_widget(
+            BarChart::horizontal(directories)
+                .block(chart("top directories".into()))
+                .bar_gap(0)
+                .bar_style(Style::default().fg(self.theme.tab_highlight))
+                .value_style(Style::default().fg(self.theme.tab_text)),
+            bottom[0],
+        );
+
+        let failing: Vec<Row> = usage
+            .failing
+            .iter()
+            .map(|(command, failures, runs)| {
+                Row::new(vec![
+                    Cell::from(format!("{:>3}%", failures * 100 / runs)),
+                    Cell::from(format!("{}/{}", failures, runs)),
+                    Cell::from(command.as_str()),
+                ])
+            })
+            .collect();
+        frame.render_widget(
+            Table::new(
+                failing,
+                [
+                    Constraint::Length(4),
+                    Constraint::Length(9),
+                    Constraint::Min(10),
+                ],
+            )
+            .block(chart("most failing commands".into()))
+            .style(Style::default().fg(self.theme.error)),
+            bottom[1],
+        );
+
+        let redactions: Vec<Bar> = usage
+            .redactions
+            .iter()
+            .map(|(token_type, n)| Bar::with_label(token_type.as_str(), *n as u64))
+            .collect();
+        frame.render_widget(
+            BarChart::horizontal(redactions)
+                .block(chart("redactions by type".into()))
+                .bar_gap(0)
+                .bar_style(Style::default().fg(self.theme.popup_confirm))
+                .value_style(Style::default().fg(self.theme.tab_text)),
+            bottom[2],
+        );
+    }
+
+    /// Help tab lines for the actions of `section` that have keys
+    fn help_lines(&self, section: Section, normal: bool) -> Vec<Line<'static>> {
+        let mut lines = Vec::new();
+        let mut tab_keys = Vec::new();
+        for action in Action::ALL.into_iter().filter(|a| a.section() == section) {
+            let keys = self.keymap.keys_for(action, normal);
+            if let Action::JumpTab(_) = action {
+                tab_keys.extend(keys.first().map(Key::label));
+                continue;
+            }
+            if keys.is_empty() {
+                continue;
+            }
+            let labels: Vec<String> = keys.iter().map(Key::label).collect();
+            lines.push(Line::from(format!(
+                "  {:<14} {}",
+                labels.join(" "),
+                action.description()
+            )));
+        }
+        // Alt+1..8 rather than eight lines
+        if let (Some(first), Some(last)) = (tab_keys.first(), tab_keys.last()) {
+            let prefix = first.trim_end_matches(|c: char| c.is_ascii_digit());
+            let keys = if tab_keys.len() == 1 {
+                first.clone()
+            } else {
+                format!("{}..{}", first, last.strip_prefix(prefix).unwrap_or(last))
+            };
+            lines.push(Line::from(format!(
+                "  {:<14} {}",
+                keys,
+                Action::JumpTab(0).description()
+            )));
+        }
+        lines
+    }
+
     fn render_help_tab(&self, frame: &mut Frame, area: Rect) {
         let header_style = Style::default()
             .fg(self.theme.header)
             .add_modifier(Modifier::BOLD);
 
-        let mut help = vec![
-            Line::from(vec![Span::styled("search", header_style)]),
-            Line::from("  type to fuzzy filter    Enter  run command    Esc  clear / quit"),
-            Line::from(""),
-            Line::from(vec![Span::styled("navigation", header_style)]),
-            Line::from("  ↑/↓       move up/down"),
-            Line::from("  ←/→       page prev/next (History, Sessions)"),
-            Line::from("  Tab       next tab           Shift+Tab  prev tab"),
-            Line::from("  Alt+1..8  jump to tab"),
-            Line::from(""),
-            Line::from(vec![Span::styled("quick jump", header_style)]),
-            Line::from("  ^H  History        ^L  Local (cwd)     ^S  Current session"),
-            Line::from(""),
-            Line::from(vec![Span::styled("actions", header_style)]),
-            Line::from("  ^T  toggle relative time"),
-            Line::from("  ^D  delete selected"),
-            Line::from("  ^E  edit alias (Aliases tab)"),
-            Line::from("  ^V  reveal token values (Tokens tab)"),
-            Line::from("  ^Y  copy selected command to clipboard"),
-            Line::from("  ^C  quit"),
-        ];
+        let mut help = Vec::new();
+        for section in Section::ALL {
+            let lines = self.help_lines(section, false);
+            if !lines.is_empty() {
+                help.push(Line::from(vec![Span::styled(
+                    section.title(),
+                    header_style,
+                )]));
+                help.extend(lines);
+                help.push(Line::from(""));
+            }
+        }
+        if self.keymap.has_normal_mode() {
+            help.push(Line::from(vec![Span::styled(
+                "vi normal mode",
+                header_style,
+            )]));
+            for section in Section::ALL {
+                help.extend(self.help_lines(section, true));
+            }
+            help.push(Line::from(""));
+        }
+        help.push(Line::from(
+            "  while typing, keys without ^ or Alt are shortcuts only if the filter is empty",
+        ));
 
-        if let Ok(stats) = self.db.get_stats() {
+        if let Ok(stats) = self.history.get_stats() {
             help.push(Line::from(""));
             help.push(Line::from(vec![Span::styled(
                 "database stats",
@@ -1674,6 +2824,109 @@ impl<'a> AppTUI<'a> {
     }
 }
 
+/// Lines of an edited text with the char at `cursor` shown reversed
+fn editor_lines(text: &str, cursor: usize) -> Vec<Line<'static>> {
+    let cursor_style = Style::default().add_modifier(Modifier::REVERSED);
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let mut plain = String::new();
+    for (i, c) in text.chars().chain(std::iter::once(' ')).enumerate() {
+        let at_end = i == text.chars().count();
+        if i == cursor {
+            spans.push(Span::raw(std::mem::take(&mut plain)));
+            let shown = if c == '\n' { ' ' } else { c };
+            spans.push(Span::styled(shown.to_string(), cursor_style));
+        } else if c != '\n' && !at_end {
+            plain.push(c);
+        }
+        if c == '\n' {
+            spans.push(Span::raw(std::mem::take(&mut plain)));
+            lines.push(Line::from(std::mem::take(&mut spans)));
+        }
+    }
+    spans.push(Span::raw(plain));
+    lines.push(Line::from(spans));
+    lines
+}
+
+/// Commands the stats tab covers, from the filter box: `host:NAME` (may
+/// repeat), a window such as `7d`, `4w`, `6m` or `1y` (default 90 days) or
+/// `all`, and any other words as text the command must contain. Returns
+/// the filter and a description of its window.
+fn stats_filter(text: &str) -> (CommandFilter, String) {
+    let mut filter = CommandFilter::default();
+    let mut days = Some(STATS_DEFAULT_DAYS);
+    let mut words = Vec::new();
+    for word in text.split_whitespace() {
+        if let Some(host) = word.strip_prefix("host:").filter(|h| !h.is_empty()) {
+            filter
+                .hostnames
+                .get_or_insert_with(Vec::new)
+                .push(host.to_string());
+        } else if word == "all" {
+            days = None;
+        } else if let Some(n) = window_days(word) {
+            days = Some(n);
+        } else {
+            words.push(word);
+        }
+    }
+    if !words.is_empty() {
+        filter.text = Some(words.join(" "));
+    }
+    filter.since = days
+        .and_then(|n| chrono::Utc::now().checked_sub_signed(chrono::Duration::try_days(n - 1)?));
+    let window = match days {
+        Some(n) => format!("last {} days", n),
+        None => "all time".to_string(),
+    };
+    (filter, window)
+}
+
+/// Days in a window written as `7d`, `4w`, `6m` or `1y`, at most
+/// [`STATS_MAX_DAYS`]
+fn window_days(word: &str) -> Option<i64> {
+    let unit = match word.chars().last()? {
+        'd' => 1,
+        'w' => 7,
+        'm' => 30,
+        'y' => 365,
+        _ => return None,
+    };
+    let n: i64 = word[..word.len() - 1].parse().ok()?;
+    (n > 0).then(|| {
+        n.checked_mul(unit)
+            .map_or(STATS_MAX_DAYS, |days| days.min(STATS_MAX_DAYS))
+    })
+}
+
+/// Weekday by hour grid, shaded by the number of runs
+fn heatmap_lines(by_hour: &[[usize; 24]; 7], color: Color) -> Vec<Line<'static>> {
+    const SHADES: [&str; 5] = ["··", "░░", "▒▒", "▓▓", "██"];
+    const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+    let busiest = by_hour.iter().flatten().max().copied().unwrap_or(0).max(1);
+    let mut lines = vec![Line::from(
+        "    0     3     6     9     12    15    18    21",
+    )];
+    for (day, hours) in WEEKDAYS.iter().zip(by_hour) {
+        let mut spans = vec![Span::raw(format!("{} ", day))];
+        for runs in hours {
+            // Any activity gets at least the lightest shade
+            let shade = if *runs == 0 {
+                0
+            } else {
+                1 + (runs * (SHADES.len() - 2)).div_ceil(busiest)
+            };
+            spans.push(Span::styled(
+                SHADES[shade.min(SHADES.len() - 1)],
+                Style::default().fg(color),
+            ));
+        }
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
 fn dir_col_width(term_width: u16) -> u16 {
     // ~25% of terminal width, clamped to 20..60
     ((term_width as u32) / 4).clamp(20, 60) as u16
@@ -1790,16 +3043,33 @@ fn centered_rect(percent_x: u16, height: u16, area: Rect) -> Rect {
         .split(popup_layout[1])[1]
 }
 
-/// Run the interactive TUI for browsing database entities.
-/// Returns the selected command string if the user pressed Enter on a Local entry.
-pub fn run_tui(db: &Database, cwd: String) -> Result<Option<String>> {
-    let mut tty = File::options().write(true).open("/dev/tty")?;
+/// Run the interactive TUI for browsing `history`.
+/// Returns the chosen command(s) and whether to run or insert them.
+pub fn run_tui(
+    history: &dyn HistoryQuery,
+    cwd: String,
+    config: &TuiConfig,
+) -> Result<Option<TuiSelection>> {
+    // Anything that can fail without the terminal happens before raw mode
+    let session_id = std::env::var("ZAM_SESSION_ID").ok();
+    let mut app = AppTUI::new(
+        history,
+        cwd,
+        session_id,
+        Theme::from_config(&config.theme)?,
+        config,
+    )?;
+
+    let tty = File::options().write(true).open("/dev/tty")?;
+    let inline_rows = config.height.inline_rows(crossterm::terminal::size()?.1);
     enable_raw_mode()?;
-    execute!(tty, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(tty);
-    let mut terminal = Terminal::new(backend)?;
-
-    let mut app = AppTUI::new(db, cwd)?;
+    let (mut terminal, prompt_column) = match open_terminal(tty, inline_rows) {
+        Ok(opened) => opened,
+        Err(e) => {
+            disable_raw_mode()?;
+            return Err(e);
+        }
+    };
 
     let result = (|| -> Result<()> {
         while app.running {
@@ -1815,9 +3085,908 @@ pub fn run_tui(db: &Database, cwd: String) -> Result<Option<String>> {
     })();
 
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    match prompt_column {
+        Some(column) => {
+            // Wipe the inline area and put the cursor back on the prompt
+            let top = terminal.get_frame().area().y;
+            terminal.clear()?;
+            terminal.set_cursor_position((column, top.saturating_sub(1)))?;
+        }
+        None => execute!(terminal.backend_mut(), LeaveAlternateScreen)?,
+    }
     terminal.show_cursor()?;
 
     result?;
     Ok(app.selected_command)
 }
+
+/// Set up drawing on `tty`: the alternate screen, or `inline_rows` lines
+/// under the prompt. For inline drawing, also returns the cursor's column
+/// on the prompt line so it can be put back there.
+fn open_terminal(
+    mut tty: File,
+    inline_rows: Option<u16>,
+) -> Result<(Terminal<TtyBackend>, Option<u16>)> {
+    let (viewport, prompt_column) = match inline_rows {
+        Some(rows) => {
+            let column = query_cursor_position(&mut tty)?.x;
+            // Start on the line below, leaving the prompt readable
+            tty.write_all(b"\r\n")?;
+            (Viewport::Inline(rows), Some(column))
+        }
+        None => {
+            execute!(tty, EnterAlternateScreen)?;
+            (Viewport::Fullscreen, None)
+        }
+    };
+    let mut terminal = Terminal::with_options(
+        TtyBackend(CrosstermBackend::new(tty)),
+        TerminalOptions { viewport },
+    )?;
+    if prompt_column.is_some() {
+        terminal.clear()?;
+    }
+    Ok((terminal, prompt_column))
+}
+
+/// Crossterm backend on /dev/tty that also asks /dev/tty for the cursor
+/// position. crossterm sends that query to stdout, which the shell widgets
+/// capture, so inline drawing would never get an answer.
+struct TtyBackend(CrosstermBackend<File>);
+
+impl Write for TtyBackend {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Write::flush(&mut self.0)
+    }
+}
+
+impl Backend for TtyBackend {
+    type Error = io::Error;
+
+    fn draw<'a, I>(&mut self, content: I) -> io::Result<()>
+    where
+        I: Iterator<Item = (u16, u16, &'a BufferCell)>,
+    {
+        self.0.draw(content)
+    }
+
+    fn append_lines(&mut self, n: u16) -> io::Result<()> {
+        self.0.append_lines(n)
+    }
+
+    fn hide_cursor(&mut self) -> io::Result<()> {
+        self.0.hide_cursor()
+    }
+
+    fn show_cursor(&mut self) -> io::Result<()> {
+        self.0.show_cursor()
+    }
+
+    fn get_cursor_position(&mut self) -> io::Result<Position> {
+        query_cursor_position(&mut self.0)
+    }
+
+    fn set_cursor_position<P: Into<Position>>(&mut self, position: P) -> io::Result<()> {
+        self.0.set_cursor_position(position)
+    }
+
+    fn clear(&mut self) -> io::Result<()> {
+        self.0.clear()
+    }
+
+    fn clear_region(&mut self, clear_type: ClearType) -> io::Result<()> {
+        self.0.clear_region(clear_type)
+    }
+
+    fn size(&self) -> io::Result<Size> {
+        self.0.size()
+    }
+
+    fn window_size(&mut self) -> io::Result<WindowSize> {
+        self.0.window_size()
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Backend::flush(&mut self.0)
+    }
+}
+
+/// Ask the terminal for the cursor position (needs raw mode). The reply is
+/// read from /dev/tty as `poll` reports it readable, giving up after two
+/// seconds so a terminal that never answers cannot hang the TUI.
+fn query_cursor_position(tty: &mut impl Write) -> io::Result<Position> {
+    use std::io::Read;
+
+    let mut input = File::open("/dev/tty")?;
+    tty.write_all(b"\x1b[6n")?;
+    tty.flush()?;
+
+    // Read with a deadline rather than from a helper thread: a thread left
+    // blocked on /dev/tty when the terminal never answers would go on to
+    // swallow the user's next keystrokes.
+    let deadline = Instant::now() + Duration::from_secs(2);
+    let mut reply = Vec::new();
+    let mut byte = [0u8];
+    while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+        let timeout = Timespec::try_from(remaining).map_err(io::Error::other)?;
+        let mut fds = [PollFd::new(&input, PollFlags::IN)];
+        if rustix::event::poll(&mut fds, Some(&timeout))? == 0 || input.read(&mut byte)? == 0 {
+            break;
+        }
+        reply.push(byte[0]);
+        // An `R` typed before the reply arrives is not the end of it
+        if byte[0] == b'R'
+            && let Some(position) = parse_cursor_report(&reply)
+        {
+            return Ok(position);
+        }
+    }
+    Err(io::Error::other(
+        "The terminal did not report the cursor position",
+    ))
+}
+
+/// Parse a `ESC [ row ; column R` cursor position report, ignoring any
+/// keys typed before it
+fn parse_cursor_report(reply: &[u8]) -> Option<Position> {
+    let start = reply.windows(2).rposition(|w| w == b"\x1b[")? + 2;
+    let report = std::str::from_utf8(reply[start..].strip_suffix(b"R")?).ok()?;
+    let (row, column) = report.split_once(';')?;
+    Some(Position {
+        x: column.parse::<u16>().ok()?.saturating_sub(1),
+        y: row.parse::<u16>().ok()?.saturating_sub(1),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    //! Scripted key presses against a temporary database, rendered into a
+    //! `TestBackend` and compared with the text in `src/snapshots/`. Run
+    //! with `UPDATE_SNAPSHOTS=1` to rewrite the snapshots after an
+    //! intended change, then review the diff.
+
+    use super::*;
+    use crate::database::{Database, DatabaseStats};
+    use crate::types::{CommandId, SessionId};
+    use ratatui::backend::TestBackend;
+    use std::path::PathBuf;
+    use tempfile::TempDir;
+
+    const WIDTH: u16 = 100;
+    const HEIGHT: u16 = 16;
+
+    /// A database with two named sessions, aliases and a redacted command.
+    /// Values that depend on the machine or the clock are pinned.
+    fn fixture() -> (TempDir, Database) {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("zam.db");
+        let mut db = Database::new(&path).unwrap();
+        let at = |minute: i64| {
+            "2026-01-05T09:00:00Z"
+                .parse::<chrono::DateTime<chrono::Utc>>()
+                .unwrap()
+                + chrono::Duration::minutes(minute)
+        };
+
+        let build = db.start_session().unwrap();
+        db.set_session_name(&build, Some("build")).unwrap();
+        let commands = [
+            ("git pull", "/home/dev/api", Some(0)),
+            ("cargo build", "/home/dev/api", Some(101)),
+            ("cargo build", "/home/dev/api", Some(0)),
+            ("cargo test", "/home/dev/api", Some(1)),
+            ("git status", "/home/dev/api", Some(0)),
+        ];
+        for (minute, (command, directory, exit_code)) in commands.iter().enumerate() {
+            db.add_command(command, directory, at(minute as i64), false, *exit_code)
+                .unwrap();
+        }
+        db.end_session(&build).unwrap();
+
+        let deploy = db.start_session().unwrap();
+        db.set_session_name(&deploy, Some("deploy")).unwrap();
+        let id = db
+            .add_command(
+                "mysql -u root --password=<password:1> prod",
+                "/srv",
+                at(10),
+                true,
+                Some(0),
+            )
+            .unwrap();
+        db.store_token(id, "password", "<password:1>", "hunter2", "")
+            .unwrap();
+        db.add_command("ls -la", "/srv", at(11), false, Some(0))
+            .unwrap();
+        db.add_command("git log --oneline", "/home/dev/web", at(12), false, None)
+            .unwrap();
+
+        db.add_alias("gs", "git status", "short status").unwrap();
+        db.add_alias("ll", "ls -la", "long listing").unwrap();
+
+        let conn = rusqlite::Connection::open(&path).unwrap();
+        conn.execute_batch(&format!(
+            "UPDATE hosts SET hostname = 'devbox', created_at = '{start}';
+             UPDATE sessions SET started_at = '{start}', ended_at = '{end}' WHERE id = '{build}';
+             UPDATE sessions SET started_at = '{later}' WHERE id = '{deploy}';
+             UPDATE tokens SET created_at = '{start}';
+             UPDATE aliases SET date_created = '{start}', date_updated = '{start}';",
+            start = at(0).to_rfc3339(),
+            end = at(5).to_rfc3339(),
+            later = at(10).to_rfc3339(),
+        ))
+        .unwrap();
+        (dir, db)
+    }
+
+    fn app<'a>(db: &'a dyn HistoryQuery, config: &TuiConfig) -> AppTUI<'a> {
+        // Nothing from the environment: no shell session, and the dark
+        // palette in place of detecting the terminal's background
+        let theme = match config.theme.base {
+            ThemeBase::Auto => Theme::dark().with_colors(&config.theme.colors),
+            _ => Theme::from_config(&config.theme),
+        };
+        let mut app =
+            AppTUI::new(db, "/home/dev/api".into(), None, theme.unwrap(), config).unwrap();
+        app.home = "/home/dev".into();
+        app
+    }
+
+    /// In-memory history, newest first, for tests that need no database.
+    /// Like the file backend it has only commands: every other tab is empty.
+    struct FakeHistory {
+        commands: std::cell::RefCell<Vec<CommandEntry>>,
+    }
+
+    impl FakeHistory {
+        fn new(commands: &[(&str, &str)]) -> Self {
+            let now = chrono::Utc::now();
+            let entries = commands
+                .iter()
+                .enumerate()
+                .map(|(i, (command, directory))| CommandEntry {
+                    id: CommandId::new(i as i64 + 1),
+                    session_id: SessionId::new("fake".to_string()),
+                    command: command.to_string(),
+                    timestamp: now - chrono::Duration::minutes(i as i64),
+                    directory: directory.to_string(),
+                    redacted: false,
+                    exit_code: Some(0),
+                })
+                .collect();
+            Self {
+                commands: std::cell::RefCell::new(entries),
+            }
+        }
+
+        fn unique(&self, keep: impl Fn(&CommandEntry) -> bool) -> Vec<CommandEntry> {
+            let mut seen = std::collections::HashSet::new();
+            self.commands
+                .borrow()
+                .iter()
+                .filter(|c| keep(c) && seen.insert(c.command.clone()))
+                .cloned()
+                .collect()
+        }
+    }
+
+    impl HistoryQuery for FakeHistory {
+        fn count_unique_commands_filtered(
+            &self,
+            filter: Option<&str>,
+            actor: &ActorFilter,
+        ) -> Result<usize> {
+            Ok(self
+                .get_unique_commands_filtered(0, usize::MAX, filter, actor)?
+                .len())
+        }
+
+        fn get_unique_commands_filtered(
+            &self,
+            offset: usize,
+            limit: usize,
+            filter: Option<&str>,
+            _actor: &ActorFilter,
+        ) -> Result<Vec<CommandEntry>> {
+            let filter = filter.unwrap_or_default().to_lowercase();
+            Ok(self
+                .unique(|c| c.command.to_lowercase().contains(&filter))
+                .into_iter()
+                .skip(offset)
+                .take(limit)
+                .collect())
+        }
+
+        fn get_commands_for_directory(&self, directory: &str) -> Result<Vec<CommandEntry>> {
+            Ok(self.unique(|c| c.directory == directory))
+        }
+
+        fn get_frequent_commands(&self, limit: usize) -> Result<Vec<(String, usize)>> {
+            let mut counts: Vec<(String, usize)> = self
+                .unique(|_| true)
+                .into_iter()
+                .map(|c| {
+                    (
+                        c.command.clone(),
+                        self.count_command_runs(&c.command).unwrap(),
+                    )
+                })
+                .collect();
+            counts.sort_by(|a, b| b.1.cmp(&a.1));
+            counts.truncate(limit);
+            Ok(counts)
+        }
+
+        fn count_command_runs(&self, command: &str) -> Result<usize> {
+            Ok(self
+                .commands
+                .borrow()
+                .iter()
+                .filter(|c| c.command == command)
+                .count())
+        }
+
+        fn delete_command(&self, id: CommandId) -> Result<()> {
+            self.commands.borrow_mut().retain(|c| c.id != id);
+            Ok(())
+        }
+
+        fn get_stats(&self) -> Result<DatabaseStats> {
+            let commands = self.commands.borrow();
+            Ok(DatabaseStats {
+                total_commands: commands.len(),
+                total_sessions: 0,
+                total_hosts: 0,
+                redacted_commands: 0,
+                stored_tokens: 0,
+                oldest_entry: commands.last().map(|c| c.timestamp),
+                newest_entry: commands.first().map(|c| c.timestamp),
+            })
+        }
+    }
+
+    fn press(app: &mut AppTUI, code: KeyCode) {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+            .unwrap();
+    }
+
+    fn ctrl(app: &mut AppTUI, c: char) {
+        app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+            .unwrap();
+    }
+
+    fn alt(app: &mut AppTUI, c: char) {
+        app.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::ALT))
+            .unwrap();
+    }
+
+    fn type_text(app: &mut AppTUI, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    /// The screen as text, without trailing spaces
+    fn screen(app: &mut AppTUI) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let mut text = String::new();
+        for y in 0..buffer.area.height {
+            let line: String = (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            text.push_str(line.trim_end());
+            text.push('\n');
+        }
+        text
+    }
+
+    fn assert_snapshot(name: &str, app: &mut AppTUI) {
+        let actual = screen(app);
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "src", "snapshots", name]
+            .iter()
+            .collect::<PathBuf>()
+            .with_extension("txt");
+        if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, &actual).unwrap();
+            return;
+        }
+        let expected = std::fs::read_to_string(&path).unwrap_or_default();
+        assert!(
+            actual == expected,
+            "{} differs from {} (UPDATE_SNAPSHOTS=1 rewrites it)\n--- expected\n{}--- actual\n{}",
+            name,
+            path.display(),
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_filter_commands() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        assert_snapshot("tui_commands", &mut app);
+
+        type_text(&mut app, "git");
+        assert_eq!(app.commands.len(), 3);
+        assert_snapshot("tui_commands_filtered", &mut app);
+
+        // Backspace widens the filter again
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Backspace);
+        }
+        assert_eq!(app.commands.len(), 7);
+    }
+
+    #[test]
+    fn test_pagination() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        app.page_size = 3;
+        app.load_tab().unwrap();
+
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.page, 1);
+        assert_snapshot("tui_commands_page_2", &mut app);
+
+        // The last page is as far as it goes
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.page, 2);
+        assert_eq!(app.commands.len(), 1);
+        press(&mut app, KeyCode::Left);
+        assert_eq!(app.page, 1);
+    }
+
+    #[test]
+    fn test_session_drill_down() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        alt(&mut app, '3');
+        assert_eq!(app.tab, Tab::Sessions);
+        assert_snapshot("tui_sessions", &mut app);
+
+        // Sessions are newest first: open "build"
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Enter);
+        assert!(app.running);
+        assert_eq!(app.session_commands.len(), 5);
+        assert_snapshot("tui_session_build", &mut app);
+
+        press(&mut app, KeyCode::Esc);
+        assert!(app.session_detail_id.is_none());
+        assert_eq!(app.sessions.len(), 2);
+    }
+
+    #[test]
+    fn test_alias_editing() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        alt(&mut app, '5');
+        assert_eq!(app.tab, Tab::Aliases);
+
+        ctrl(&mut app, 'e');
+        assert_eq!(app.mode, Mode::EditAlias);
+        for _ in 0.."status".len() {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "status --short");
+        assert_snapshot("tui_alias_edit", &mut app);
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Filter);
+        assert_eq!(db.list_aliases().unwrap()[0].command, "git status --short");
+        assert!(screen(&mut app).contains("git status --short"));
+    }
+
+    #[test]
+    fn test_new_alias() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        // The newest command is "git log --oneline"
+        alt(&mut app, 'a');
+        assert_eq!(app.mode, Mode::NewAlias);
+        let new_alias = app.new_alias.as_ref().unwrap();
+        assert_eq!(new_alias.command, "git log --oneline");
+        assert_eq!(
+            new_alias.name,
+            alias_check::suggest_name("git log --oneline", &db.list_aliases().unwrap(), &[])
+        );
+
+        // Existing aliases and builtins are refused
+        for _ in 0..new_alias.name.len() {
+            press(&mut app, KeyCode::Backspace);
+        }
+        type_text(&mut app, "gs");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::NewAlias);
+        assert!(screen(&mut app).contains("'gs' is already an alias for 'git status'"));
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Backspace);
+        type_text(&mut app, "cd");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::NewAlias);
+        assert!(screen(&mut app).contains("'cd' is a shell builtin"));
+
+        press(&mut app, KeyCode::Backspace);
+        press(&mut app, KeyCode::Backspace);
+        type_text(&mut app, "zq-glo");
+        press(&mut app, KeyCode::Tab);
+        press(&mut app, KeyCode::Tab);
+        type_text(&mut app, "short log");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Filter);
+        assert!(screen(&mut app).contains("Alias 'zq-glo' added"));
+        let added = db.list_aliases().unwrap();
+        let added = added.iter().find(|a| a.alias == "zq-glo").unwrap();
+        assert_eq!(added.command, "git log --oneline");
+        assert_eq!(added.description, "short log");
+
+        // The message goes with the next key
+        press(&mut app, KeyCode::Down);
+        assert!(!screen(&mut app).contains("added"));
+    }
+
+    #[test]
+    fn test_alias_suggestions() {
+        let (_dir, mut db) = fixture();
+        let at = "2026-01-05T12:00:00Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap();
+        for _ in 0..4 {
+            db.add_command("zam sessions --tree", "/home/dev/api", at, false, Some(0))
+                .unwrap();
+        }
+        let mut app = app(&db, &TuiConfig::default());
+        alt(&mut app, 's');
+        assert_eq!(app.tab, Tab::Aliases);
+        assert!(app.show_suggestions);
+        assert_eq!(app.suggestions.len(), 1);
+        assert_snapshot("tui_alias_suggestions", &mut app);
+
+        // Saving a suggestion removes it from the list
+        alt(&mut app, 'a');
+        assert_eq!(app.new_alias.as_ref().unwrap().name, "zs");
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Filter);
+        assert!(app.suggestions.is_empty());
+        assert!(db.list_aliases().unwrap().iter().any(|a| a.alias == "zs"));
+
+        alt(&mut app, 's');
+        assert!(!app.show_suggestions);
+        assert_eq!(app.aliases.len(), 3);
+    }
+
+    #[test]
+    fn test_window_days() {
+        assert_eq!(window_days("7d"), Some(7));
+        assert_eq!(window_days("4w"), Some(28));
+        assert_eq!(window_days("6m"), Some(180));
+        assert_eq!(window_days("1y"), Some(365));
+        assert_eq!(window_days("0d"), None);
+        assert_eq!(window_days("-3d"), None);
+        assert_eq!(window_days("d"), None);
+        assert_eq!(window_days("7x"), None);
+        assert_eq!(window_days("git"), None);
+        // Huge windows are capped instead of overflowing
+        assert_eq!(window_days("100000000d"), Some(STATS_MAX_DAYS));
+        assert_eq!(window_days("300000y"), Some(STATS_MAX_DAYS));
+        assert_eq!(window_days("9223372036854775807y"), Some(STATS_MAX_DAYS));
+    }
+
+    #[test]
+    fn test_stats_filter() {
+        let (filter, window) = stats_filter("");
+        assert_eq!(window, "last 90 days");
+        assert!(filter.text.is_none());
+        let since = filter.since.unwrap();
+        let expected = chrono::Utc::now() - chrono::Duration::days(STATS_DEFAULT_DAYS - 1);
+        assert!((since - expected).num_seconds().abs() < 5);
+
+        let (filter, window) = stats_filter("host:devbox cargo 2w build host:ci");
+        assert_eq!(window, "last 14 days");
+        assert_eq!(filter.text.as_deref(), Some("cargo build"));
+        assert_eq!(
+            filter.hostnames,
+            Some(vec!["devbox".to_string(), "ci".to_string()])
+        );
+
+        let (filter, window) = stats_filter("all");
+        assert_eq!(window, "all time");
+        assert!(filter.since.is_none());
+
+        // Would overflow chrono without the cap
+        let (filter, window) = stats_filter("300000y");
+        assert_eq!(window, format!("last {} days", STATS_MAX_DAYS));
+        assert!(filter.since.is_some());
+        stats_filter("100000000d");
+    }
+
+    #[test]
+    fn test_editor_lines() {
+        let text = |line: &Line| {
+            line.spans
+                .iter()
+                .map(|s| s.content.as_ref())
+                .collect::<String>()
+        };
+        let cursor = |line: &Line| {
+            line.spans
+                .iter()
+                .find(|s| s.style.add_modifier.contains(Modifier::REVERSED))
+                .map(|s| s.content.to_string())
+        };
+
+        // The cursor past the end is a reversed space
+        let lines = editor_lines("ls", 2);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(text(&lines[0]), "ls ");
+        assert_eq!(cursor(&lines[0]).as_deref(), Some(" "));
+
+        // Cursor positions count chars, not bytes
+        let lines = editor_lines("echo héllo", 6);
+        assert_eq!(text(&lines[0]), "echo héllo");
+        assert_eq!(cursor(&lines[0]).as_deref(), Some("é"));
+
+        // A cursor on a newline shows as a space at the end of its line
+        let lines = editor_lines("cd /srv\nls", 7);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(text(&lines[0]), "cd /srv ");
+        assert_eq!(cursor(&lines[0]).as_deref(), Some(" "));
+        assert_eq!(text(&lines[1]), "ls");
+        assert_eq!(cursor(&lines[1]), None);
+    }
+
+    #[test]
+    fn test_edit_command() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        ctrl(&mut app, 'e');
+        assert_eq!(app.mode, Mode::EditCommand);
+        assert_eq!(app.edit_buf, "git log --oneline");
+        assert_eq!(app.edit_cursor, 17);
+
+        app.edit_buf = "echo ñandú".into();
+        app.edit_cursor = 10;
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.edit_buf, "echo ñand");
+        type_text(&mut app, "ú!");
+        assert_eq!(app.edit_buf, "echo ñandú!");
+        press(&mut app, KeyCode::Home);
+        for _ in 0..5 {
+            press(&mut app, KeyCode::Right);
+        }
+        press(&mut app, KeyCode::Delete);
+        assert_eq!(app.edit_buf, "echo andú!");
+        press(&mut app, KeyCode::Char('ñ'));
+        assert_eq!(app.edit_cursor, 6);
+        ctrl(&mut app, 'u');
+        assert_eq!((app.edit_buf.as_str(), app.edit_cursor), ("andú!", 0));
+        // Left and Right stop at the ends
+        press(&mut app, KeyCode::Left);
+        assert_eq!(app.edit_cursor, 0);
+        ctrl(&mut app, 'e');
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.edit_cursor, 5);
+        press(&mut app, KeyCode::End);
+        press(&mut app, KeyCode::Backspace);
+        assert_eq!(app.edit_buf, "andú");
+
+        // Esc returns to the list without choosing anything
+        press(&mut app, KeyCode::Esc);
+        assert_eq!(app.mode, Mode::Filter);
+        assert!(app.running);
+
+        // An empty command cannot be chosen
+        ctrl(&mut app, 'e');
+        ctrl(&mut app, 'u');
+        press(&mut app, KeyCode::Enter);
+        assert!(app.running);
+        type_text(&mut app, "git log -p");
+        app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT))
+            .unwrap();
+        assert!(!app.running);
+        assert_eq!(
+            app.selected_command.unwrap().widget_output(),
+            "insert\ngit log -p\n"
+        );
+    }
+
+    #[test]
+    fn test_run_and_insert() {
+        let (_dir, db) = fixture();
+        let mut run = app(&db, &TuiConfig::default());
+        press(&mut run, KeyCode::Enter);
+        let selection = run.selected_command.unwrap();
+        assert_eq!(selection.action, TuiAction::Run);
+        assert_eq!(selection.widget_output(), "run\ngit log --oneline\n");
+
+        let mut insert = app(&db, &TuiConfig::default());
+        insert
+            .handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::ALT))
+            .unwrap();
+        let selection = insert.selected_command.unwrap();
+        assert_eq!(selection.action, TuiAction::Insert);
+        assert_eq!(selection.widget_output(), "insert\ngit log --oneline\n");
+    }
+
+    #[test]
+    fn test_marked_commands() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        // Space marks and moves down: git log, ls -la, then the mysql
+        // command, which is chosen with its secret filled in
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char(' '));
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(app.marked.len(), 3);
+        // Marking again unmarks: back up to ls -la
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Up);
+        press(&mut app, KeyCode::Char(' '));
+        assert_eq!(app.marked.len(), 2);
+        assert_eq!(
+            app.chosen_command().unwrap(),
+            "git log --oneline && mysql -u root --password=hunter2 prod"
+        );
+
+        app.join = CommandJoin::Newline;
+        assert_eq!(
+            app.chosen_command().unwrap(),
+            "git log --oneline\nmysql -u root --password=hunter2 prod"
+        );
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(
+            app.selected_command.unwrap().command,
+            "git log --oneline\nmysql -u root --password=hunter2 prod"
+        );
+    }
+
+    #[test]
+    fn test_preview() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        assert!(app.preview.is_none());
+
+        ctrl(&mut app, 'p');
+        let commands = |entries: &[CommandEntry]| -> Vec<String> {
+            entries.iter().map(|c| c.command.clone()).collect()
+        };
+        // The newest command ends the deploy session
+        let preview = app.preview.as_ref().unwrap();
+        assert_eq!(preview.entry.command, "git log --oneline");
+        assert_eq!(preview.session, "deploy");
+        assert_eq!(preview.host, "devbox");
+        assert_eq!(
+            commands(&preview.before),
+            ["mysql -u root --password=<password:1> prod", "ls -la"]
+        );
+        assert!(preview.after.is_empty());
+
+        // Neighbours are limited to PREVIEW_CONTEXT on each side
+        type_text(&mut app, "cargo test");
+        let preview = app.preview.as_ref().unwrap();
+        assert_eq!(preview.entry.command, "cargo test");
+        assert_eq!(
+            commands(&preview.before),
+            ["git pull", "cargo build", "cargo build"]
+        );
+        assert_eq!(commands(&preview.after), ["git status"]);
+        assert_eq!(preview.runs, 1);
+
+        // Drawing does not query the history
+        app.preview = None;
+        screen(&mut app);
+        assert!(app.preview.is_none());
+    }
+
+    #[test]
+    fn test_preview_of_ephemeral_command() {
+        let (_dir, db) = fixture();
+        let app = app(&db, &TuiConfig::default());
+        let preview = app.load_preview(CommandEntry {
+            id: CommandId::new(0),
+            session_id: SessionId::new("zsh-1".to_string()),
+            command: "echo private".to_string(),
+            timestamp: chrono::Utc::now(),
+            directory: "/tmp".to_string(),
+            redacted: false,
+            exit_code: None,
+        });
+        assert_eq!(preview.session, "private (not stored)");
+        assert_eq!(preview.runs, 0);
+        assert!(preview.before.is_empty() && preview.after.is_empty());
+    }
+
+    #[test]
+    fn test_fake_history() {
+        let history = FakeHistory::new(&[
+            ("cargo test", "/home/dev/api"),
+            ("git status", "/home/dev/web"),
+            ("cargo test", "/home/dev/api"),
+            ("cargo build", "/home/dev/api"),
+        ]);
+        let mut app = app(&history, &TuiConfig::default());
+        let commands: Vec<_> = app.commands.iter().map(|c| c.command.as_str()).collect();
+        assert_eq!(commands, ["cargo test", "git status", "cargo build"]);
+
+        type_text(&mut app, "cargo");
+        assert_eq!(app.commands.len(), 2);
+        assert!(screen(&mut app).contains("cargo build"));
+
+        // Deleting goes through the trait, not a database
+        ctrl(&mut app, 'd');
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(history.count_command_runs("cargo test").unwrap(), 1);
+
+        // Tabs the fake has no data for stay empty
+        app.jump_to_tab(Tab::Sessions.index()).unwrap();
+        assert!(app.sessions.is_empty());
+    }
+
+    #[test]
+    fn test_confirm_delete() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        ctrl(&mut app, 'd');
+        assert_eq!(app.mode, Mode::Confirm);
+        assert_snapshot("tui_confirm_delete", &mut app);
+
+        // Anything but y backs out
+        press(&mut app, KeyCode::Char('n'));
+        assert_eq!(app.mode, Mode::Filter);
+        assert_eq!(app.commands.len(), 7);
+
+        ctrl(&mut app, 'd');
+        press(&mut app, KeyCode::Char('y'));
+        assert_eq!(app.commands.len(), 6);
+        assert!(
+            app.commands
+                .iter()
+                .all(|c| c.command != "git log --oneline")
+        );
+    }
+
+    #[test]
+    fn test_token_reveal() {
+        let (_dir, db) = fixture();
+        let mut app = app(&db, &TuiConfig::default());
+        alt(&mut app, '7');
+        assert_eq!(app.tab, Tab::Tokens);
+        assert!(!screen(&mut app).contains("hunter2"));
+
+        ctrl(&mut app, 'v');
+        assert_snapshot("tui_tokens_revealed", &mut app);
+        ctrl(&mut app, 'v');
+        assert!(!screen(&mut app).contains("hunter2"));
+    }
+
+    #[test]
+    fn test_parse_cursor_report() {
+        assert_eq!(
+            parse_cursor_report(b"\x1b[12;40R"),
+            Some(Position { x: 39, y: 11 })
+        );
+        assert_eq!(
+            parse_cursor_report(b"\x1b[1;1R"),
+            Some(Position { x: 0, y: 0 })
+        );
+        // Keys typed while waiting for the reply come before it
+        assert_eq!(
+            parse_cursor_report(b"ls\x1b[A\x1b[12;40R"),
+            Some(Position { x: 39, y: 11 })
+        );
+        // A typed `R` ends the read early without a complete report
+        assert_eq!(parse_cursor_report(b"R"), None);
+        assert_eq!(parse_cursor_report(b"\x1b[AR"), None);
+        assert_eq!(parse_cursor_report(b"\x1b[12;40"), None);
+        assert_eq!(parse_cursor_report(b"\x1b[12;R"), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! Scripted JSON-RPC exchanges with `zam mcp`
+
+use serde_json::{Value, json};
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use tempfile::TempDir;
+use zam::Config;
+
+fn zam(home: &Path) -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_zam"));
+    cmd.env("HOME", home)
+        .env_remove("ZAM_SESSION_ID")
+        .env_remove("ZAM_PARENT_SESSION_ID")
+        .current_dir(home);
+    cmd
+}
+
+fn log(home: &Path, args: &[&str]) {
+    let status = zam(home).arg("log").args(args).status().unwrap();
+    assert!(status.success());
+}
+
+/// Send `requests` to `zam mcp` and return its responses
+fn exchange(home: &Path, requests: &[Value]) -> Vec<Value> {
+    let mut child = zam(home)
+        .arg("mcp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for request in requests {
+        writeln!(stdin, "{}", request).unwrap();
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+fn initialize() -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 0,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-06-18",
+            "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" }
+        }
+    })
+}
+
+fn call(id: i64, tool: &str, arguments: Value) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/call",
+        "params": { "name": tool, "arguments": arguments }
+    })
+}
+
+fn commands(response: &Value) -> Vec<&str> {
+    response["result"]["structuredContent"]["commands"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["command"].as_str().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_handshake_and_tools() {
+    let home = TempDir::new().unwrap();
+    let responses = exchange(
+        home.path(),
+        &[
+            initialize(),
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "resources/list" }),
+        ],
+    );
+
+    // The notification gets no response
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["result"]["protocolVersion"], "2025-06-18");
+    assert_eq!(responses[0]["result"]["serverInfo"]["name"], "zam");
+
+    let tools: Vec<&str> = responses[1]["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(tools, zam::mcp::TOOL_NAMES);
+
+    assert_eq!(responses[2]["id"], 2);
+    assert_eq!(responses[2]["error"]["code"], -32601);
+}
+
+#[test]
+fn test_history_tools_keep_placeholders() {
+    let home = TempDir::new().unwrap();
+    let dir = home.path().to_string_lossy().to_string();
+    log(
+        home.path(),
+        &[
+            "--session-id",
+            "s1",
+            "mysql -u root --password=hunter2 prod",
+        ],
+    );
+    log(home.path(), &["--session-id", "s1", "git status"]);
+    log(home.path(), &["--session-id", "s2", "make test"]);
+    let status = zam(home.path())
+        .args(["alias", "add", "gs", "git status", "short status"])
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let responses = exchange(
+        home.path(),
+        &[
+            initialize(),
+            call(1, "search_history", json!({ "query": "mysql" })),
+            call(
+                2,
+                "recent_in_directory",
+                json!({ "directory": dir, "limit": 2 }),
+            ),
+            call(3, "session_commands", json!({ "session_id": "s1" })),
+            call(4, "list_aliases", json!({})),
+            call(5, "search_history", json!({})),
+        ],
+    );
+
+    assert_eq!(
+        commands(&responses[1]),
+        vec!["mysql -u root --password=<password:1> prod"]
+    );
+    assert!(!responses[1].to_string().contains("hunter2"));
+    assert_eq!(commands(&responses[2]), vec!["make test", "git status"]);
+    assert_eq!(
+        commands(&responses[3]),
+        vec!["mysql -u root --password=<password:1> prod", "git status"]
+    );
+    assert_eq!(
+        responses[4]["result"]["structuredContent"]["aliases"][0]["alias"],
+        "gs"
+    );
+    assert_eq!(responses[5]["result"]["isError"], true);
+}
+
+#[test]
+fn test_config_allowlist() {
+    let home = TempDir::new().unwrap();
+    log(home.path(), &["git status"]);
+
+    let mut config = Config {
+        history_file: home.path().join(".local/zam/zam.log"),
+        ..Default::default()
+    };
+    config.mcp.tools = vec!["search_history".to_string()];
+    config.mcp.hosts = vec!["some-other-host".to_string()];
+    config.save_to_path(&home.path().join(".zam.json")).unwrap();
+
+    let responses = exchange(
+        home.path(),
+        &[
+            initialize(),
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+            call(2, "list_aliases", json!({})),
+            call(3, "search_history", json!({ "query": "git" })),
+        ],
+    );
+
+    let tools = responses[1]["result"]["tools"].as_array().unwrap();
+    assert_eq!(tools.len(), 1);
+    assert_eq!(tools[0]["name"], "search_history");
+    assert_eq!(responses[2]["error"]["code"], -32602);
+    // Commands from hosts outside the allowlist are never returned
+    assert!(commands(&responses[3]).is_empty());
+}
+
+#[test]
+fn test_invalid_config() {
+    let home = TempDir::new().unwrap();
+    std::fs::write(home.path().join(".zam.json"), "{ not json").unwrap();
+
+    // Falling back to the defaults would expose every tool
+    let output = zam(home.path()).arg("mcp").output().unwrap();
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("invalid configuration"));
+
+    // Only the configuration in use matters
+    let config = home.path().join("mcp.json");
+    Config {
+        history_file: home.path().join(".local/zam/zam.log"),
+        ..Default::default()
+    }
+    .save_to_path(&config)
+    .unwrap();
+    let mut child = zam(home.path())
+        .arg("-c")
+        .arg(&config)
+        .arg("mcp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    writeln!(child.stdin.take().unwrap(), "{}", initialize()).unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("serverInfo"));
+}